@@ -0,0 +1,217 @@
+use crate::duration::DurationMs;
+use crate::events::{AnalyticsEvent, EnrichedEvent, Enrichment};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+type KeyFn = dyn Fn(&AnalyticsEvent) -> String + Send + Sync;
+
+/// Rule replacing raw events of one type with per-key summaries
+///
+/// Events whose [`event_type`](AnalyticsEvent::event_type) matches are not
+/// sent individually. Instead the client counts them per key and, once per
+/// flush interval (and on `flush`/`shutdown`), emits one
+/// [`AnalyticsEvent::EventSummary`] per key holding the count and the
+/// p50/p95 of [`duration_ms`](AnalyticsEvent::duration_ms).
+///
+/// Everything else about the individual events (user ids, exact timestamps,
+/// other fields) is lost, so only aggregate high-volume, low-value events:
+///
+/// ```rust
+/// use lib_analytics_core::{Aggregation, AnalyticsEvent, ClientConfig};
+///
+/// let config = ClientConfig {
+///     aggregations: vec![Aggregation::new("api_request", |event| match event {
+///         AnalyticsEvent::ApiRequest { endpoint, status_code, .. } => {
+///             format!("{} {}", endpoint, status_code)
+///         }
+///         _ => String::new(),
+///     })],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct Aggregation {
+    event_type: String,
+    key: Arc<KeyFn>,
+}
+
+impl Aggregation {
+    /// Aggregate events of `event_type`, grouped by the key `key` returns
+    pub fn new(
+        event_type: impl Into<String>,
+        key: impl Fn(&AnalyticsEvent) -> String + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            event_type: event_type.into(),
+            key: Arc::new(key),
+        }
+    }
+
+    /// Type of the aggregated events
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+}
+
+impl fmt::Debug for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Aggregation")
+            .field("event_type", &self.event_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Counters for one key within the current window
+#[derive(Default)]
+struct Counter {
+    count: u64,
+    durations: Vec<i64>,
+}
+
+/// Per-window counters kept by the worker
+pub(crate) struct Aggregator {
+    rules: Vec<Aggregation>,
+    window_start: DateTime<Utc>,
+    counters: HashMap<(usize, String), Counter>,
+}
+
+impl Aggregator {
+    pub(crate) fn new(rules: Vec<Aggregation>) -> Self {
+        Self {
+            rules,
+            window_start: Utc::now(),
+            counters: HashMap::new(),
+        }
+    }
+
+    /// Count the event if a rule matches it
+    ///
+    /// Returns `false` when no rule applies and the event should be sent as-is.
+    pub(crate) fn record(&mut self, event: &EnrichedEvent) -> bool {
+        let event_type = event.event.event_type();
+        let Some(rule) = self.rules.iter().position(|r| r.event_type == event_type) else {
+            return false;
+        };
+
+        let key = (self.rules[rule].key)(&event.event);
+        let counter = self.counters.entry((rule, key)).or_default();
+        counter.count += 1;
+        if let Some(duration) = event.event.duration_ms() {
+            counter.durations.push(duration.as_millis());
+        }
+        true
+    }
+
+    /// Whether nothing was counted in the current window
+    pub(crate) fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+
+    /// Emit one summary per key and start a new window
+    pub(crate) fn drain(&mut self, enrichment: &Enrichment) -> Vec<EnrichedEvent> {
+        let window_start = std::mem::replace(&mut self.window_start, Utc::now());
+        let window_end = self.window_start;
+
+        self.counters
+            .drain()
+            .map(|((rule, key), mut counter)| {
+                counter.durations.sort_unstable();
+                enrichment.enrich(AnalyticsEvent::EventSummary {
+                    event_type: self.rules[rule].event_type.clone(),
+                    key,
+                    count: counter.count,
+                    p50_duration_ms: percentile(&counter.durations, 50),
+                    p95_duration_ms: percentile(&counter.durations, 95),
+                    window_start,
+                    window_end,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[i64], p: usize) -> Option<DurationMs> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    Some(DurationMs(sorted[rank - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_request(endpoint: &str, duration_ms: i64) -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::ApiRequest {
+            service: "platform".to_string(),
+            endpoint: endpoint.to_string(),
+            method: "GET".to_string(),
+            status_code: 200,
+            duration_ms: DurationMs(duration_ms),
+            user_id: None,
+        })
+    }
+
+    fn by_endpoint() -> Aggregation {
+        Aggregation::new("api_request", |event| match event {
+            AnalyticsEvent::ApiRequest { endpoint, .. } => endpoint.clone(),
+            _ => String::new(),
+        })
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<i64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50), Some(DurationMs(50)));
+        assert_eq!(percentile(&values, 95), Some(DurationMs(95)));
+        assert_eq!(percentile(&[7], 95), Some(DurationMs(7)));
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn test_aggregator_summarizes_per_key() {
+        let mut aggregator = Aggregator::new(vec![by_endpoint()]);
+
+        for duration in 1..=20 {
+            assert!(aggregator.record(&api_request("/tasks", duration)));
+        }
+        assert!(aggregator.record(&api_request("/projects", 5)));
+        assert!(
+            !aggregator.record(&EnrichedEvent::new(AnalyticsEvent::AuthSessionValidated {
+                user_id: uuid::Uuid::new_v4(),
+                valid: true,
+            }))
+        );
+
+        let enrichment = Enrichment::new(None, None);
+        let mut summaries = aggregator.drain(&enrichment);
+        summaries.sort_by_key(|s| match &s.event {
+            AnalyticsEvent::EventSummary { key, .. } => key.clone(),
+            _ => String::new(),
+        });
+
+        assert_eq!(summaries.len(), 2);
+        let AnalyticsEvent::EventSummary {
+            event_type,
+            key,
+            count,
+            p50_duration_ms,
+            p95_duration_ms,
+            ..
+        } = &summaries[1].event
+        else {
+            panic!("unexpected event: {:?}", summaries[1].event);
+        };
+        assert_eq!(event_type, "api_request");
+        assert_eq!(key, "/tasks");
+        assert_eq!(*count, 20);
+        assert_eq!(*p50_duration_ms, Some(DurationMs(10)));
+        assert_eq!(*p95_duration_ms, Some(DurationMs(19)));
+
+        assert!(aggregator.drain(&enrichment).is_empty());
+    }
+}
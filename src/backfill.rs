@@ -0,0 +1,275 @@
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use std::fmt;
+use std::sync::Arc;
+
+/// Default upper bound for the serialized events of one backfill request
+pub const DEFAULT_BACKFILL_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Called after each backfill chunk completed
+type ProgressFn = Arc<dyn Fn(&BackfillProgress) + Send + Sync>;
+
+/// Settings of [`AnalyticsClient::backfill`](crate::AnalyticsClient::backfill)
+///
+/// ```rust
+/// use lib_analytics_core::BackfillOptions;
+///
+/// let options = BackfillOptions::new()
+///     .max_chunk_bytes(256 * 1024)
+///     .on_progress(|progress| {
+///         println!("{} events imported", progress.events_sent);
+///     });
+/// ```
+#[derive(Clone)]
+pub struct BackfillOptions {
+    pub(crate) max_chunk_bytes: usize,
+    pub(crate) progress: Option<ProgressFn>,
+}
+
+impl BackfillOptions {
+    /// Chunks of up to [`DEFAULT_BACKFILL_CHUNK_BYTES`], without progress
+    /// reports
+    pub fn new() -> Self {
+        Self {
+            max_chunk_bytes: DEFAULT_BACKFILL_CHUNK_BYTES,
+            progress: None,
+        }
+    }
+
+    /// Close a chunk before its events serialize to more than `bytes`
+    ///
+    /// Keep it below the ingestion service's request size limit. Measured
+    /// on the events as JSON, before compression; an event larger than
+    /// `bytes` on its own is sent in a chunk by itself.
+    pub fn max_chunk_bytes(mut self, bytes: usize) -> Self {
+        self.max_chunk_bytes = bytes;
+        self
+    }
+
+    /// Call `progress` with the running totals after each chunk completed
+    ///
+    /// Called from the backfill future, so it must return quickly.
+    pub fn on_progress(
+        mut self,
+        progress: impl Fn(&BackfillProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+}
+
+impl Default for BackfillOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for BackfillOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackfillOptions")
+            .field("max_chunk_bytes", &self.max_chunk_bytes)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Settings of [`AnalyticsClient::track_bulk_with`](crate::AnalyticsClient::track_bulk_with)
+///
+/// ```rust
+/// use lib_analytics_core::BulkOptions;
+///
+/// // Import every event, whatever the client's sampling
+/// let options = BulkOptions::new().bypass_sampling(true);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BulkOptions {
+    pub(crate) bypass_sampling: bool,
+}
+
+impl BulkOptions {
+    /// Apply the client's `sampling` like `track` does
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enqueue events the client's `sampling` would leave out
+    ///
+    /// For intentional backfills that must stay complete. The `filter`
+    /// still applies.
+    pub fn bypass_sampling(mut self, bypass: bool) -> Self {
+        self.bypass_sampling = bypass;
+        self
+    }
+}
+
+/// Running totals of a backfill, passed to
+/// [`BackfillOptions::on_progress`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BackfillProgress {
+    /// Chunks the sink accepted
+    pub chunks_sent: usize,
+    /// Chunks the sink failed to deliver
+    pub chunks_failed: usize,
+    /// Events in accepted chunks
+    pub events_sent: usize,
+    /// Events in failed chunks
+    pub events_failed: usize,
+    /// Events left out before chunking: rejected by the `filter` or, with
+    /// `reject_invalid`, invalid
+    pub events_skipped: usize,
+}
+
+/// Outcome of one backfill request
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ChunkReport {
+    /// Position of the chunk in the backfill, from 0
+    pub index: usize,
+    /// Events in the chunk
+    pub events: usize,
+    /// The sink's result for the chunk
+    pub result: Result<()>,
+}
+
+/// Outcome of a backfill, returned by
+/// [`AnalyticsClient::backfill`](crate::AnalyticsClient::backfill)
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct BackfillReport {
+    /// Every chunk in order, with its result
+    pub chunks: Vec<ChunkReport>,
+    /// Final totals
+    pub progress: BackfillProgress,
+}
+
+impl BackfillReport {
+    /// Whether every chunk was delivered
+    pub fn is_complete(&self) -> bool {
+        self.progress.chunks_failed == 0
+    }
+
+    /// Errors of the failed chunks, by chunk index
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &AnalyticsError)> {
+        self.chunks
+            .iter()
+            .filter_map(|chunk| chunk.result.as_ref().err().map(|e| (chunk.index, e)))
+    }
+
+    /// Count an event left out before chunking
+    pub(crate) fn skip(&mut self) {
+        self.progress.events_skipped += 1;
+    }
+
+    /// Add a completed chunk and report the new totals
+    pub(crate) fn complete(&mut self, chunk: ChunkReport, options: &BackfillOptions) {
+        if chunk.result.is_ok() {
+            self.progress.chunks_sent += 1;
+            self.progress.events_sent += chunk.events;
+        } else {
+            self.progress.chunks_failed += 1;
+            self.progress.events_failed += chunk.events;
+        }
+        self.chunks.push(chunk);
+        if let Some(progress) = &options.progress {
+            progress(&self.progress);
+        }
+    }
+
+    /// Put the chunks, completed in any order, back in order
+    pub(crate) fn finish(mut self) -> Self {
+        self.chunks.sort_by_key(|chunk| chunk.index);
+        self
+    }
+}
+
+/// Cuts a stream of events into chunks bounded by count and size
+pub(crate) struct Chunker {
+    max_events: usize,
+    max_bytes: usize,
+    events: Vec<EnrichedEvent>,
+    bytes: usize,
+    next_index: usize,
+}
+
+impl Chunker {
+    pub(crate) fn new(max_events: usize, max_bytes: usize) -> Self {
+        Self {
+            max_events: max_events.max(1),
+            max_bytes,
+            events: Vec::new(),
+            bytes: 0,
+            next_index: 0,
+        }
+    }
+
+    /// Add an event, returning the previous chunk with its index if the
+    /// event didn't fit into it anymore
+    pub(crate) fn push(&mut self, event: EnrichedEvent) -> Option<(usize, Vec<EnrichedEvent>)> {
+        let len = event.estimated_size();
+        let full = self.events.len() >= self.max_events || self.bytes + len > self.max_bytes;
+        let chunk = if full { self.finish() } else { None };
+        self.events.push(event);
+        self.bytes += len;
+        chunk
+    }
+
+    /// Take the chunk being filled, if it holds any events
+    pub(crate) fn finish(&mut self) -> Option<(usize, Vec<EnrichedEvent>)> {
+        if self.events.is_empty() {
+            return None;
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        self.bytes = 0;
+        Some((index, std::mem::take(&mut self.events)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+
+    fn event() -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: uuid::Uuid::new_v4(),
+            user_id: uuid::Uuid::new_v4(),
+        })
+    }
+
+    fn chunk_sizes(chunker: &mut Chunker, events: usize) -> Vec<usize> {
+        let mut sizes: Vec<_> = (0..events)
+            .filter_map(|_| chunker.push(event()))
+            .map(|(_, chunk)| chunk.len())
+            .collect();
+        sizes.extend(chunker.finish().map(|(_, chunk)| chunk.len()));
+        sizes
+    }
+
+    #[test]
+    fn test_chunks_by_count() {
+        let mut chunker = Chunker::new(10, usize::MAX);
+        assert_eq!(chunk_sizes(&mut chunker, 25), [10, 10, 5]);
+        assert!(chunker.finish().is_none());
+    }
+
+    #[test]
+    fn test_chunks_by_size() {
+        let len = event().estimated_size();
+        let mut chunker = Chunker::new(100, 3 * len + len / 2);
+        assert_eq!(chunk_sizes(&mut chunker, 7), [3, 3, 1]);
+
+        // Oversized events still go out, one per chunk
+        let mut chunker = Chunker::new(100, 1);
+        assert_eq!(chunk_sizes(&mut chunker, 3), [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_chunk_indices() {
+        let mut chunker = Chunker::new(1, usize::MAX);
+        assert!(chunker.push(event()).is_none());
+        assert_eq!(chunker.push(event()).unwrap().0, 0);
+        assert_eq!(chunker.finish().unwrap().0, 1);
+    }
+}
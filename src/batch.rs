@@ -0,0 +1,200 @@
+use rand::Rng;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The batch being filled, as seen by a [`BatchPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BatchState {
+    /// Events in the batch
+    pub events: usize,
+    /// Approximate size of the events serialized as JSON
+    pub bytes: usize,
+    /// Time since the first event entered the batch
+    pub age: Duration,
+}
+
+/// Decides when the background task sends the batch it is filling
+///
+/// The default is [`CountOrInterval`] built from `batch_size`,
+/// `flush_interval` and `flush_jitter`; install another policy with
+/// [`ClientConfig::batch_policy`](crate::ClientConfig::batch_policy).
+///
+/// Contract:
+///
+/// * [`should_flush`](Self::should_flush) is called after every event added
+///   to the batch; returning `true` sends the batch right away.
+/// * [`max_delay`](Self::max_delay) is asked once per batch, when its first
+///   event arrives. The batch is sent when that time has passed, however
+///   small it is, so a policy never strands events.
+/// * [`on_flush`](Self::on_flush) reports each batch sent because of the
+///   policy (either way above), with its final state. Batches sent by
+///   `flush`, `shutdown` or `resume` are not reported.
+///
+/// `batch_size` still caps the events per request: larger batches are split.
+/// Methods are called from the background task only and must return
+/// quickly; policies shared between clients see all their batches.
+pub trait BatchPolicy: Send + Sync + 'static {
+    /// Whether to send the batch now
+    fn should_flush(&self, batch: &BatchState) -> bool;
+
+    /// Longest time the first event of a new batch may wait
+    fn max_delay(&self) -> Duration;
+
+    /// Called after the policy caused `batch` to be sent
+    fn on_flush(&self, batch: &BatchState) {
+        let _ = batch;
+    }
+}
+
+impl fmt::Debug for dyn BatchPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchPolicy").finish_non_exhaustive()
+    }
+}
+
+/// Send once a batch holds `events` events or its first event waited
+/// `interval`, whichever comes first (the default policy)
+///
+/// With a `jitter`, every batch waits a random time within
+/// `interval ± jitter` instead (see `ClientConfig::flush_jitter`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountOrInterval {
+    events: usize,
+    interval: Duration,
+    jitter: Duration,
+}
+
+impl CountOrInterval {
+    /// Send at `events` events or `interval` after the first one
+    pub fn new(events: usize, interval: Duration) -> Self {
+        Self {
+            events,
+            interval,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Randomize each batch's interval by up to `jitter` either way
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+}
+
+impl BatchPolicy for CountOrInterval {
+    fn should_flush(&self, batch: &BatchState) -> bool {
+        batch.events >= self.events
+    }
+
+    fn max_delay(&self) -> Duration {
+        jittered(self.interval, self.jitter)
+    }
+}
+
+/// Count threshold that grows during bursts and shrinks when traffic is low
+///
+/// Starts at `min_events`. A batch that fills up before `max_delay` doubles
+/// the threshold (up to `max_events`), so bursts go out in fewer, larger
+/// requests; a batch sent by the timer with fewer events halves it again
+/// (down to `min_events`), so quiet periods keep latency low. `max_events`
+/// above the client's `batch_size` still results in `batch_size` requests.
+#[derive(Debug)]
+pub struct Adaptive {
+    min_events: usize,
+    max_events: usize,
+    max_delay: Duration,
+    threshold: AtomicUsize,
+}
+
+impl Adaptive {
+    /// Threshold between `min_events` and `max_events`, sending partial
+    /// batches after `max_delay`
+    pub fn new(min_events: usize, max_events: usize, max_delay: Duration) -> Self {
+        let min_events = min_events.max(1);
+        Self {
+            min_events,
+            max_events: max_events.max(min_events),
+            max_delay,
+            threshold: AtomicUsize::new(min_events),
+        }
+    }
+
+    /// Event count that currently triggers a send
+    pub fn threshold(&self) -> usize {
+        self.threshold.load(Ordering::Relaxed)
+    }
+}
+
+impl BatchPolicy for Adaptive {
+    fn should_flush(&self, batch: &BatchState) -> bool {
+        batch.events >= self.threshold()
+    }
+
+    fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    fn on_flush(&self, batch: &BatchState) {
+        let threshold = self.threshold();
+        let next = if batch.events >= threshold {
+            threshold.saturating_mul(2).min(self.max_events)
+        } else {
+            (threshold / 2).max(self.min_events)
+        };
+        self.threshold.store(next, Ordering::Relaxed);
+    }
+}
+
+/// `interval` randomized by up to `jitter` either way
+pub(crate) fn jittered(interval: Duration, jitter: Duration) -> Duration {
+    let jitter = jitter.as_nanos();
+    if jitter == 0 {
+        return interval;
+    }
+
+    // Uniform in `interval ± jitter`
+    let random = rand::thread_rng().gen_range(0..=2 * jitter);
+    let interval = (interval.as_nanos() + random).saturating_sub(jitter);
+    Duration::from_nanos(interval.try_into().unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(events: usize) -> BatchState {
+        BatchState {
+            events,
+            bytes: 0,
+            age: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_count_or_interval() {
+        let policy = CountOrInterval::new(3, Duration::from_secs(10));
+
+        assert!(!policy.should_flush(&state(2)));
+        assert!(policy.should_flush(&state(3)));
+        assert_eq!(policy.max_delay(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_adaptive_grows_and_shrinks() {
+        let policy = Adaptive::new(10, 50, Duration::from_secs(1));
+
+        // Bursts fill batches before the timer
+        for expected in [20, 40, 50, 50] {
+            policy.on_flush(&state(policy.threshold()));
+            assert_eq!(policy.threshold(), expected);
+        }
+
+        // Timer flushes of partial batches
+        for expected in [25, 12, 10, 10] {
+            policy.on_flush(&state(3));
+            assert_eq!(policy.threshold(), expected);
+        }
+    }
+}
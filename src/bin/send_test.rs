@@ -0,0 +1,50 @@
+//! Smoke test for a deployment's analytics path
+//!
+//! Builds a client from the `ANALYTICS_*` environment variables (see
+//! `AnalyticsClient::from_env`), sends one synthetic `ApplicationError` with
+//! service `cli-test` and reports whether the ingestion service accepted it.
+//! Exits nonzero if the event could not be delivered or analytics is
+//! disabled.
+
+use lib_analytics_core::{AnalyticsClient, AnalyticsEvent};
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let client = match AnalyticsClient::from_env() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("✗ Invalid analytics configuration: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let event = AnalyticsEvent::ApplicationError {
+        service: "cli-test".to_string(),
+        error_type: "send_test".to_string(),
+        error_message: "Synthetic event sent by analytics-send-test".to_string(),
+        user_id: None,
+        context: None,
+        context_ref: None,
+    };
+
+    let result = client.track_and_flush(event).await;
+    let report = client.shutdown(Duration::from_secs(5)).await;
+
+    match (result, report) {
+        (Err(e), _) | (Ok(()), Err(e)) => {
+            eprintln!("✗ Test event not delivered: {}", e);
+            ExitCode::FAILURE
+        }
+        // A disabled client accepts the event without sending anything
+        (Ok(()), Ok(report)) if report.flushed == 0 => {
+            eprintln!("✗ Test event not sent, analytics is disabled");
+            ExitCode::FAILURE
+        }
+        (Ok(()), Ok(report)) => {
+            println!("✓ Test event delivered ({})", report);
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::error::Result;
+use crate::events::{AnalyticsEvent, EnrichedEvent};
+use crate::sanitize;
+use async_trait::async_trait;
+use std::fmt;
+
+/// Default size above which `ApplicationError` context is uploaded (8 KiB)
+pub const DEFAULT_BLOB_THRESHOLD: usize = 8 * 1024;
+
+/// Stores large `ApplicationError` context out of band
+///
+/// Set as [`ClientConfig::blob_uploader`](crate::ClientConfig::blob_uploader).
+/// When the serialized `context` of an `ApplicationError` exceeds
+/// `blob_threshold` bytes, the background task uploads it and sends the event
+/// with `context: None` and `context_ref` set to the returned reference
+/// instead, keeping batches small.
+///
+/// If the upload fails, the error is logged and the context is sent inline
+/// after all, truncated to `max_field_len` like without an uploader.
+///
+/// Uploads run on the background task before the event is batched, so a slow
+/// uploader delays delivery of everything tracked after the event.
+#[async_trait]
+pub trait BlobUploader: Send + Sync + 'static {
+    /// Store `body` (the JSON-encoded context of `event`) and return a
+    /// reference to it, e.g. an object storage key
+    async fn upload(&self, event: &EnrichedEvent, body: Vec<u8>) -> Result<String>;
+}
+
+impl fmt::Debug for dyn BlobUploader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobUploader").finish_non_exhaustive()
+    }
+}
+
+/// Whether the event's context is large enough to upload
+pub(crate) fn exceeds_threshold(event: &AnalyticsEvent, threshold: usize) -> bool {
+    match event {
+        AnalyticsEvent::ApplicationError {
+            context: Some(context),
+            ..
+        } => context.to_string().len() > threshold,
+        _ => false,
+    }
+}
+
+/// Replace a large context with a reference to its uploaded copy
+///
+/// Falls back to the truncated inline context if the upload fails.
+pub(crate) async fn offload_context(
+    uploader: &dyn BlobUploader,
+    event: &mut EnrichedEvent,
+    threshold: usize,
+    max_field_len: usize,
+) {
+    if !exceeds_threshold(&event.event, threshold) {
+        return;
+    }
+    let AnalyticsEvent::ApplicationError {
+        context: Some(context),
+        ..
+    } = &event.event
+    else {
+        return;
+    };
+
+    let body = context.to_string().into_bytes();
+    let uploaded = uploader.upload(event, body).await;
+
+    let AnalyticsEvent::ApplicationError {
+        context,
+        context_ref,
+        ..
+    } = &mut event.event
+    else {
+        return;
+    };
+    match uploaded {
+        Ok(reference) => {
+            *context = None;
+            *context_ref = Some(reference);
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Failed to upload analytics context, sending it inline: {}",
+                e
+            );
+            if let Some(context) = context {
+                sanitize::truncate_value(context, max_field_len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AnalyticsError;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Uploader keeping blobs in memory, or failing every upload
+    #[derive(Default)]
+    struct MemoryUploader {
+        fail: bool,
+        blobs: Mutex<Vec<Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl BlobUploader for MemoryUploader {
+        async fn upload(&self, _event: &EnrichedEvent, body: Vec<u8>) -> Result<String> {
+            if self.fail {
+                return Err(AnalyticsError::Http { status: 503 });
+            }
+            let mut blobs = self.blobs.lock().unwrap();
+            blobs.push(body);
+            Ok(format!("errors/{}", blobs.len()))
+        }
+    }
+
+    fn error_event(dump_len: usize) -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::ApplicationError {
+            service: "gateway".to_string(),
+            error_type: "upstream".to_string(),
+            error_message: "bad gateway".to_string(),
+            user_id: None,
+            context: Some(json!({ "response": "x".repeat(dump_len) })),
+            context_ref: None,
+        })
+    }
+
+    fn context_of(event: &EnrichedEvent) -> (Option<serde_json::Value>, Option<String>) {
+        match &event.event {
+            AnalyticsEvent::ApplicationError {
+                context,
+                context_ref,
+                ..
+            } => (context.clone(), context_ref.clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_large_context_is_uploaded() {
+        let uploader = MemoryUploader::default();
+        let mut event = error_event(100 * 1024);
+
+        offload_context(&uploader, &mut event, DEFAULT_BLOB_THRESHOLD, 1024).await;
+
+        assert_eq!(context_of(&event), (None, Some("errors/1".to_string())));
+        let blob: serde_json::Value =
+            serde_json::from_slice(&uploader.blobs.lock().unwrap()[0]).unwrap();
+        assert_eq!(blob["response"].as_str().unwrap().len(), 100 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_small_context_stays_inline() {
+        let uploader = MemoryUploader::default();
+        let mut event = error_event(16);
+
+        offload_context(&uploader, &mut event, DEFAULT_BLOB_THRESHOLD, 1024).await;
+
+        let (context, context_ref) = context_of(&event);
+        assert!(context.is_some());
+        assert_eq!(context_ref, None);
+        assert!(uploader.blobs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_falls_back_to_truncated_inline() {
+        let uploader = MemoryUploader {
+            fail: true,
+            ..Default::default()
+        };
+        let mut event = error_event(100 * 1024);
+
+        offload_context(&uploader, &mut event, DEFAULT_BLOB_THRESHOLD, 1024).await;
+
+        let (context, context_ref) = context_of(&event);
+        assert_eq!(context_ref, None);
+        assert!(context.unwrap().to_string().len() < 2 * 1024);
+    }
+}
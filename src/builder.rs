@@ -0,0 +1,488 @@
+use crate::aggregate::Aggregation;
+use crate::batch::BatchPolicy;
+use crate::client::AnalyticsClient;
+use crate::compression::Compression;
+use crate::config::ClientConfig;
+use crate::environment::Environment;
+use crate::error::{AnalyticsError, Result};
+use crate::events::AnalyticsEvent;
+use crate::filter::EventFilter;
+use crate::middleware::EnrichMiddleware;
+use crate::normalize::ActionNormalizer;
+use crate::sampling::Sampling;
+use crate::sanitize::{ContextFilter, UserIdPolicy};
+use crate::serializer::BatchSerializer;
+use crate::sink::{EventSink, HttpSink, TeeSink};
+use crate::state::SharedState;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for [`AnalyticsClient`], the preferred way to create one
+///
+/// Every [`ClientConfig`] option has a method of the same name, except
+/// `aggregations`, which [`aggregation`](Self::aggregation) adds to one rule
+/// at a time; options not set keep their defaults. Exactly one destination is required: the URL of
+/// the ingestion service or a custom sink.
+///
+/// ```rust,no_run
+/// use lib_analytics_core::AnalyticsClient;
+/// use std::time::Duration;
+///
+/// # fn main() -> lib_analytics_core::Result<()> {
+/// let client = AnalyticsClient::builder()
+///     .url("http://localhost:8094")
+///     .auth_token("secret")
+///     .batch_size(50)
+///     .flush_interval(Duration::from_secs(5))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct AnalyticsClientBuilder {
+    url: Option<String>,
+    sink: Option<Arc<dyn EventSink>>,
+    mirror: Option<Arc<dyn EventSink>>,
+    config: ClientConfig,
+}
+
+impl AnalyticsClientBuilder {
+    /// Builder with no destination and the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Base URL of the analytics ingestion service (e.g. `http://localhost:8094`)
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Deliver batches to a custom sink instead of the ingestion service
+    ///
+    /// HTTP-specific options (e.g. `auth_token`) are ignored.
+    pub fn sink(mut self, sink: impl EventSink) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Also write every batch to `mirror`, e.g. a new pipeline being
+    /// validated before cutover
+    ///
+    /// Mirror failures are logged but never fail a send; see [`TeeSink`].
+    pub fn mirror_to(mut self, mirror: impl EventSink) -> Self {
+        self.mirror = Some(Arc::new(mirror));
+        self
+    }
+
+    /// Replace the whole configuration, e.g. one read with custom logic
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// See [`ClientConfig::max_field_len`]
+    pub fn max_field_len(mut self, max_field_len: usize) -> Self {
+        self.config.max_field_len = max_field_len;
+        self
+    }
+
+    /// See [`ClientConfig::context_filter`]
+    pub fn context_filter(mut self, context_filter: ContextFilter) -> Self {
+        self.config.context_filter = context_filter;
+        self
+    }
+
+    /// See [`ClientConfig::user_id_policy`]
+    pub fn user_id_policy(mut self, user_id_policy: UserIdPolicy) -> Self {
+        self.config.user_id_policy = user_id_policy;
+        self
+    }
+
+    /// Drop events `predicate` returns `false` for (see [`ClientConfig::filter`])
+    pub fn filter(
+        mut self,
+        predicate: impl Fn(&AnalyticsEvent) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.config.filter = Some(EventFilter::new(predicate));
+        self
+    }
+
+    /// See [`ClientConfig::sampling`]
+    pub fn sampling(mut self, sampling: Sampling) -> Self {
+        self.config.sampling = Some(sampling);
+        self
+    }
+
+    /// See [`ClientConfig::slow_query_threshold`]
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.config.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// See [`ClientConfig::action_normalizer`]
+    pub fn action_normalizer(mut self, action_normalizer: ActionNormalizer) -> Self {
+        self.config.action_normalizer = action_normalizer;
+        self
+    }
+
+    /// Append a step to the enrichment chain (see
+    /// [`ClientConfig::enrich_middleware`])
+    pub fn enrich_middleware(mut self, middleware: impl EnrichMiddleware) -> Self {
+        self.config.enrich_middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// See [`ClientConfig::queue_capacity`]
+    pub fn queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.config.queue_capacity = Some(queue_capacity);
+        self
+    }
+
+    /// See [`ClientConfig::batch_size`]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.config.batch_size = batch_size;
+        self
+    }
+
+    /// See [`ClientConfig::flush_interval`]
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.config.flush_interval = flush_interval;
+        self
+    }
+
+    /// See [`ClientConfig::flush_jitter`]
+    pub fn flush_jitter(mut self, flush_jitter: Duration) -> Self {
+        self.config.flush_jitter = flush_jitter;
+        self
+    }
+
+    /// See [`ClientConfig::batch_policy`]
+    pub fn batch_policy(mut self, batch_policy: impl BatchPolicy) -> Self {
+        self.config.batch_policy = Some(Arc::new(batch_policy));
+        self
+    }
+
+    /// See [`ClientConfig::hostname`]
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.config.hostname = Some(hostname.into());
+        self
+    }
+
+    /// See [`ClientConfig::environment`]
+    pub fn environment(mut self, environment: impl Into<Environment>) -> Self {
+        self.config.environment = Some(environment.into());
+        self
+    }
+
+    /// See [`ClientConfig::auth_token`]
+    pub fn auth_token(mut self, auth_token: impl Into<String>) -> Self {
+        self.config.auth_token = Some(auth_token.into());
+        self
+    }
+
+    /// See [`ClientConfig::signing_secret`]
+    pub fn signing_secret(mut self, signing_secret: Vec<u8>) -> Self {
+        self.config.signing_secret = Some(signing_secret);
+        self
+    }
+
+    /// See [`ClientConfig::pool_idle_timeout`]
+    pub fn pool_idle_timeout(mut self, pool_idle_timeout: Option<Duration>) -> Self {
+        self.config.pool_idle_timeout = pool_idle_timeout;
+        self
+    }
+
+    /// See [`ClientConfig::pool_max_idle_per_host`]
+    pub fn pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
+        self.config.pool_max_idle_per_host = pool_max_idle_per_host;
+        self
+    }
+
+    /// See [`ClientConfig::http2_prior_knowledge`]
+    #[cfg(feature = "http2")]
+    pub fn http2_prior_knowledge(mut self, http2_prior_knowledge: bool) -> Self {
+        self.config.http2_prior_knowledge = http2_prior_knowledge;
+        self
+    }
+
+    /// See [`ClientConfig::proxy`]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// See [`ClientConfig::uds_path`]
+    #[cfg(all(unix, feature = "uds"))]
+    pub fn uds_path(mut self, uds_path: impl Into<std::path::PathBuf>) -> Self {
+        self.config.uds_path = Some(uds_path.into());
+        self
+    }
+
+    /// See [`ClientConfig::serializer`]
+    pub fn serializer(mut self, serializer: impl BatchSerializer) -> Self {
+        self.config.serializer = Arc::new(serializer);
+        self
+    }
+
+    /// See [`ClientConfig::batch_path`]
+    pub fn batch_path(mut self, batch_path: impl Into<String>) -> Self {
+        self.config.batch_path = batch_path.into();
+        self
+    }
+
+    /// See [`ClientConfig::compression`]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.config.compression = compression;
+        self
+    }
+
+    /// See [`ClientConfig::compress_min_bytes`]
+    pub fn compress_min_bytes(mut self, compress_min_bytes: usize) -> Self {
+        self.config.compress_min_bytes = compress_min_bytes;
+        self
+    }
+
+    /// See [`ClientConfig::tenant_header`]
+    pub fn tenant_header(mut self, tenant_header: bool) -> Self {
+        self.config.tenant_header = tenant_header;
+        self
+    }
+
+    /// See [`ClientConfig::max_concurrent_sends`]
+    pub fn max_concurrent_sends(mut self, max_concurrent_sends: usize) -> Self {
+        self.config.max_concurrent_sends = max_concurrent_sends;
+        self
+    }
+
+    /// Persist events in a ring file of `size_bytes` at `path` until
+    /// delivered (see [`DurableBuffer`](crate::DurableBuffer))
+    #[cfg(feature = "durable")]
+    pub fn durable_buffer(mut self, path: impl Into<std::path::PathBuf>, size_bytes: u64) -> Self {
+        self.config.durable_buffer = Some(crate::DurableBuffer::new(path, size_bytes));
+        self
+    }
+
+    /// See [`ClientConfig::request_timeout`]
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.config.request_timeout = request_timeout;
+        self
+    }
+
+    /// See [`ClientConfig::fallback_to_stdout_after`]
+    pub fn fallback_to_stdout_after(mut self, failures: u32) -> Self {
+        self.config.fallback_to_stdout_after = Some(failures);
+        self
+    }
+
+    /// See [`ClientConfig::max_event_age`]
+    pub fn max_event_age(mut self, max_event_age: Duration) -> Self {
+        self.config.max_event_age = Some(max_event_age);
+        self
+    }
+
+    /// See [`ClientConfig::clock_skew_threshold`]
+    pub fn clock_skew_threshold(mut self, clock_skew_threshold: Duration) -> Self {
+        self.config.clock_skew_threshold = clock_skew_threshold;
+        self
+    }
+
+    /// See [`ClientConfig::correct_clock_skew`]
+    pub fn correct_clock_skew(mut self, correct_clock_skew: bool) -> Self {
+        self.config.correct_clock_skew = correct_clock_skew;
+        self
+    }
+
+    /// See [`ClientConfig::reject_invalid`]
+    pub fn reject_invalid(mut self, reject_invalid: bool) -> Self {
+        self.config.reject_invalid = reject_invalid;
+        self
+    }
+
+    /// See [`ClientConfig::dry_run`]
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.config.dry_run = dry_run;
+        self
+    }
+
+    /// See [`ClientConfig::dry_run_pretty`]
+    pub fn dry_run_pretty(mut self, dry_run_pretty: bool) -> Self {
+        self.config.dry_run_pretty = dry_run_pretty;
+        self
+    }
+
+    /// See [`ClientConfig::blob_uploader`]
+    #[cfg(feature = "blob-upload")]
+    pub fn blob_uploader(mut self, blob_uploader: impl crate::BlobUploader) -> Self {
+        self.config.blob_uploader = Some(Arc::new(blob_uploader));
+        self
+    }
+
+    /// See [`ClientConfig::blob_threshold`]
+    #[cfg(feature = "blob-upload")]
+    pub fn blob_threshold(mut self, blob_threshold: usize) -> Self {
+        self.config.blob_threshold = blob_threshold;
+        self
+    }
+
+    /// See [`ClientConfig::max_attachment_bytes`]
+    #[cfg(feature = "attachments")]
+    pub fn max_attachment_bytes(mut self, max_attachment_bytes: usize) -> Self {
+        self.config.max_attachment_bytes = max_attachment_bytes;
+        self
+    }
+
+    /// Add an aggregation rule (see [`ClientConfig::aggregations`])
+    pub fn aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.config.aggregations.push(aggregation);
+        self
+    }
+
+    /// See [`ClientConfig::login_compaction_window`]
+    pub fn login_compaction_window(mut self, window: Duration) -> Self {
+        self.config.login_compaction_window = Some(window);
+        self
+    }
+
+    /// See [`ClientConfig::churn_window`]
+    pub fn churn_window(mut self, window: Duration) -> Self {
+        self.config.churn_window = Some(window);
+        self
+    }
+
+    /// See [`ClientConfig::churn_suppress_raw`]
+    pub fn churn_suppress_raw(mut self, suppress_raw: bool) -> Self {
+        self.config.churn_suppress_raw = suppress_raw;
+        self
+    }
+
+    /// See [`ClientConfig::log_throttle`]
+    pub fn log_throttle(mut self, window: Duration) -> Self {
+        self.config.log_throttle = Some(window);
+        self
+    }
+
+    /// See [`ClientConfig::drop_log_level`]
+    pub fn drop_log_level(mut self, level: tracing::Level) -> Self {
+        self.config.drop_log_level = level;
+        self
+    }
+
+    /// See [`ClientConfig::failure_log_level`]
+    pub fn failure_log_level(mut self, level: tracing::Level) -> Self {
+        self.config.failure_log_level = level;
+        self
+    }
+
+    /// See [`ClientConfig::stats_interval`]
+    pub fn stats_interval(mut self, stats_interval: Duration) -> Self {
+        self.config.stats_interval = Some(stats_interval);
+        self
+    }
+
+    /// Create the client and start its background task
+    ///
+    /// Must be called within a tokio runtime, otherwise fails with
+    /// `AnalyticsError::WorkerNotRunning`. Returns `AnalyticsError::Config`
+    /// if neither or both of `url` and `sink` were given, `url` is not an
+    /// absolute http(s) URL (like [`set_url`](AnalyticsClient::set_url)
+    /// checks), or an option is out of range (zero `batch_size` or `flush_interval`, `flush_jitter` not
+    /// below `flush_interval`).
+    pub fn build(self) -> Result<AnalyticsClient> {
+        self.config.validate()?;
+        crate::client::ensure_runtime()?;
+
+        let shared = Arc::new(SharedState::new(&self.config));
+        let config = Arc::new(self.config);
+        let sink: Arc<dyn EventSink> = match (self.url, self.sink) {
+            (Some(url), None) => {
+                crate::client::validate_url(&url)?;
+                Arc::new(HttpSink::new(url, config.clone(), shared.clone()))
+            }
+            (None, Some(sink)) => sink,
+            (Some(_), Some(_)) => {
+                return Err(AnalyticsError::Config(
+                    "url and sink are mutually exclusive".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(AnalyticsError::Config(
+                    "either url or sink is required".to_string(),
+                ))
+            }
+        };
+        let sink = match self.mirror {
+            Some(mirror) => Arc::new(TeeSink::new(sink, mirror)),
+            None => sink,
+        };
+
+        Ok(AnalyticsClient::spawn(sink, config, shared))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::testing::CollectSink;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_build_with_sink() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .hostname("builder-host")
+            .build()
+            .unwrap();
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+
+        assert_eq!(sink.events()[0].hostname.as_deref(), Some("builder-host"));
+    }
+
+    #[tokio::test]
+    async fn test_build_with_mirror() {
+        let sink = CollectSink::default();
+        let mirror = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .mirror_to(mirror.clone())
+            .build()
+            .unwrap();
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+
+        assert_eq!(sink.events().len(), 1);
+        assert_eq!(mirror.events().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_rejects_conflicting_destinations() {
+        let both = AnalyticsClient::builder()
+            .url("http://localhost:8094")
+            .sink(CollectSink::default())
+            .build();
+        assert!(matches!(both, Err(AnalyticsError::Config(_))));
+
+        let neither = AnalyticsClient::builder().build();
+        assert!(matches!(neither, Err(AnalyticsError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_build_rejects_zero_batch_size() {
+        let result = AnalyticsClient::builder()
+            .url("http://localhost:8094")
+            .batch_size(0)
+            .build();
+
+        assert!(matches!(result, Err(AnalyticsError::Config(_))));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::events::{AnalyticsEvent, EnrichedEvent, Enrichment};
+use std::time::{Duration, Instant};
+
+/// Counts Cocoon connects and disconnects into `CocoonChurn` summaries
+///
+/// A window opens when the client starts and again after each summary. The
+/// worker checks it on the flush cadence, so a summary is emitted at the
+/// first flush after `window` has elapsed and covers the whole time since
+/// the previous one. Windows without any connect or disconnect emit nothing.
+pub(crate) struct ChurnTracker {
+    window: Option<Duration>,
+    suppress_raw: bool,
+    opened: Instant,
+    connects: u64,
+    disconnects: u64,
+}
+
+impl ChurnTracker {
+    pub(crate) fn new(window: Option<Duration>, suppress_raw: bool) -> Self {
+        Self {
+            window,
+            suppress_raw,
+            opened: Instant::now(),
+            connects: 0,
+            disconnects: 0,
+        }
+    }
+
+    /// Count a connect or disconnect
+    ///
+    /// Returns `true` if the raw event should not be sent (`churn_suppress_raw`).
+    pub(crate) fn record(&mut self, event: &EnrichedEvent) -> bool {
+        if self.window.is_none() {
+            return false;
+        }
+        match event.event {
+            AnalyticsEvent::CocoonConnected { .. } => self.connects += 1,
+            AnalyticsEvent::CocoonDisconnected { .. } => self.disconnects += 1,
+            _ => return false,
+        }
+        self.suppress_raw
+    }
+
+    /// Whether nothing was counted in the current window
+    pub(crate) fn is_empty(&self) -> bool {
+        self.connects == 0 && self.disconnects == 0
+    }
+
+    /// Emit a summary if the window has closed by `now`
+    pub(crate) fn drain_expired(
+        &mut self,
+        now: Instant,
+        enrichment: &Enrichment,
+    ) -> Option<EnrichedEvent> {
+        let window = self.window?;
+        if now.duration_since(self.opened) < window {
+            return None;
+        }
+        self.summarize(now, enrichment)
+    }
+
+    /// Emit a summary of the current window, however long it has been open
+    pub(crate) fn drain(&mut self, enrichment: &Enrichment) -> Option<EnrichedEvent> {
+        self.summarize(Instant::now(), enrichment)
+    }
+
+    fn summarize(&mut self, now: Instant, enrichment: &Enrichment) -> Option<EnrichedEvent> {
+        let opened = std::mem::replace(&mut self.opened, now);
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(enrichment.enrich(AnalyticsEvent::CocoonChurn {
+            window_seconds: now.duration_since(opened).as_secs(),
+            connects: std::mem::take(&mut self.connects),
+            disconnects: std::mem::take(&mut self.disconnects),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn connected() -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::CocoonConnected {
+            cocoon_id: Uuid::new_v4(),
+            user_id: None,
+        })
+    }
+
+    fn disconnected() -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::CocoonDisconnected {
+            cocoon_id: Uuid::new_v4(),
+            user_id: None,
+            duration_seconds: 30,
+        })
+    }
+
+    #[test]
+    fn test_summary_after_window() {
+        let enrichment = Enrichment::new(None, None);
+        let mut tracker = ChurnTracker::new(Some(Duration::from_secs(60)), false);
+        let start = tracker.opened;
+
+        assert!(!tracker.record(&connected()));
+        assert!(!tracker.record(&connected()));
+        assert!(!tracker.record(&disconnected()));
+        assert!(tracker
+            .drain_expired(start + Duration::from_secs(30), &enrichment)
+            .is_none());
+
+        let summary = tracker
+            .drain_expired(start + Duration::from_secs(70), &enrichment)
+            .unwrap();
+        assert!(matches!(
+            summary.event,
+            AnalyticsEvent::CocoonChurn {
+                window_seconds: 70,
+                connects: 2,
+                disconnects: 1,
+            }
+        ));
+        assert!(tracker.is_empty());
+
+        // Quiet windows emit nothing
+        assert!(tracker
+            .drain_expired(start + Duration::from_secs(140), &enrichment)
+            .is_none());
+    }
+
+    #[test]
+    fn test_suppress_raw() {
+        let mut tracker = ChurnTracker::new(Some(Duration::from_secs(60)), true);
+
+        assert!(tracker.record(&connected()));
+        assert!(
+            !tracker.record(&EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            }))
+        );
+        assert!(!tracker.is_empty());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let enrichment = Enrichment::new(None, None);
+        let mut tracker = ChurnTracker::new(None, true);
+
+        assert!(!tracker.record(&connected()));
+        assert!(tracker.is_empty());
+        assert!(tracker.drain(&enrichment).is_none());
+    }
+}
@@ -1,154 +1,1160 @@
+use crate::backfill::{BackfillOptions, BackfillReport, BulkOptions, ChunkReport, Chunker};
+use crate::builder::AnalyticsClientBuilder;
+use crate::config::{self, ClientConfig};
+use crate::dry_run;
+use crate::error::{AnalyticsError, Result};
 use crate::events::{AnalyticsEvent, EnrichedEvent};
+use crate::metrics::{BatchOutcome, MetricsSnapshot, ShutdownReport};
+use crate::queue::{self, EventSender, Message};
+use crate::recording::RecordingHandle;
+use crate::retention::RetentionClass;
+use crate::scope::{ScopeContext, ScopedClient};
+use crate::sink::{EventSink, FallbackSink, HttpSink};
+use crate::state::SharedState;
+use crate::throttle::LogKind;
+use crate::validation;
+use crate::worker::{self, Worker};
+use chrono::{DateTime, Utc};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{Stream, StreamExt};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
+use uuid::Uuid;
 
 /// Client for tracking analytics events
 ///
-/// Sends events to the analytics ingestion service via HTTP.
-/// All track() calls are non-blocking.
+/// Sends events to the analytics ingestion service via HTTP (or to a custom
+/// [`EventSink`]). All track() calls are non-blocking.
+///
+/// # Runtime
+///
+/// Creating a client spawns its background task, so it must happen inside
+/// a tokio runtime, e.g. in `#[tokio::main]` rather than in a sync `main`
+/// before the runtime starts or in a lazily initialized static.
+/// [`builder`](Self::builder), [`try_new`](Self::try_new) and
+/// [`from_env`](Self::from_env) return `AnalyticsError::WorkerNotRunning`
+/// outside a runtime; [`new`](Self::new), [`with_config`](Self::with_config)
+/// and [`with_sink`](Self::with_sink) panic. Once created, the client can be
+/// used from any thread. [`noop`](Self::noop) needs no runtime at all.
 #[derive(Clone)]
 pub struct AnalyticsClient {
-    http_client: reqwest::Client,
-    analytics_url: Arc<str>,
-    sender: mpsc::UnboundedSender<EnrichedEvent>,
+    sender: EventSender,
+    sink: Option<Arc<dyn EventSink>>,
+    config: Arc<ClientConfig>,
+    shared: Arc<SharedState>,
 }
 
 impl AnalyticsClient {
-    /// Create a new analytics client
+    /// Start configuring a client, the preferred way to create one
+    ///
+    /// See [`AnalyticsClientBuilder`] for the available options.
+    pub fn builder() -> AnalyticsClientBuilder {
+        AnalyticsClientBuilder::new()
+    }
+
+    /// Create a new analytics client with the default configuration
     ///
     /// # Arguments
     /// * `analytics_url` - Base URL of analytics ingestion service (e.g., "http://localhost:8094")
     ///
     /// Events are batched and sent asynchronously in the background.
+    /// Shorthand for `AnalyticsClient::builder().url(analytics_url).build()`.
+    ///
+    /// # Panics
+    ///
+    /// Outside a tokio runtime or if `analytics_url` is not an absolute
+    /// http(s) URL; [`try_new`](Self::try_new) returns an error instead.
     pub fn new(analytics_url: impl Into<String>) -> Self {
-        let (sender, receiver) = mpsc::unbounded_channel();
-        let analytics_url: Arc<str> = analytics_url.into().into();
-        let http_client = reqwest::Client::new();
+        Self::try_new(analytics_url)
+            .expect("AnalyticsClient::new requires a tokio runtime and a valid URL")
+    }
+
+    /// Like [`new`](Self::new), but fails with
+    /// `AnalyticsError::WorkerNotRunning` outside a tokio runtime and
+    /// `AnalyticsError::Config` for a malformed URL instead of panicking
+    pub fn try_new(analytics_url: impl Into<String>) -> Result<Self> {
+        Self::builder().url(analytics_url).build()
+    }
+
+    /// Create a new analytics client with custom configuration
+    ///
+    /// Deprecated in favor of [`builder`](Self::builder) with
+    /// [`config`](AnalyticsClientBuilder::config), which also checks the
+    /// options and the URL.
+    ///
+    /// # Arguments
+    /// * `analytics_url` - Base URL of analytics ingestion service
+    /// * `config` - Client options (see [`ClientConfig`])
+    ///
+    /// Neither the options nor the URL are checked; a zero `batch_size`
+    /// sends every event on its own.
+    ///
+    /// # Panics
+    ///
+    /// Outside a tokio runtime.
+    #[deprecated(note = "use AnalyticsClient::builder()")]
+    pub fn with_config(analytics_url: impl Into<String>, config: ClientConfig) -> Self {
+        let config = with_min_batch_size(config);
+        let analytics_url = analytics_url.into();
+        let shared = Arc::new(SharedState::new(&config));
+        let config = Arc::new(config);
+        let sink = HttpSink::new(analytics_url, config.clone(), shared.clone());
+
+        Self::spawn(Arc::new(sink), config, shared)
+    }
+
+    /// Create a client that delivers batches to a custom sink
+    ///
+    /// Events are still enriched, queued and batched according to `config`,
+    /// but each batch is handed to `sink` instead of the ingestion service.
+    /// HTTP-specific options (e.g. `auth_token`) are ignored. Prefer
+    /// [`builder`](Self::builder) with [`sink`](AnalyticsClientBuilder::sink).
+    /// Like with [`with_config`](Self::with_config), options are not checked.
+    ///
+    /// # Panics
+    ///
+    /// Outside a tokio runtime.
+    pub fn with_sink(sink: impl EventSink, config: ClientConfig) -> Self {
+        let config = with_min_batch_size(config);
+        let shared = Arc::new(SharedState::new(&config));
+        Self::spawn(Arc::new(sink), Arc::new(config), shared)
+    }
+
+    pub(crate) fn spawn(
+        sink: Arc<dyn EventSink>,
+        config: Arc<ClientConfig>,
+        shared: Arc<SharedState>,
+    ) -> Self {
+        let (sender, receiver) = queue::channel(config.queue_capacity);
+
+        let sink: Arc<dyn EventSink> = match config.fallback_to_stdout_after {
+            Some(threshold) => Arc::new(FallbackSink::new(sink, threshold)),
+            None => sink,
+        };
+        #[cfg(feature = "durable")]
+        let sink = with_durable_buffer(sink, &config, &shared);
 
         // Spawn background sender task
-        let url = analytics_url.clone();
-        let client = http_client.clone();
-        tokio::spawn(async move {
-            Self::send_loop(receiver, client, url).await;
-        });
+        let worker = Worker::new(receiver, sink.clone(), config.clone(), shared.clone());
+        tokio::spawn(worker.run());
 
         Self {
-            http_client,
-            analytics_url,
             sender,
+            sink: Some(sink),
+            config,
+            shared,
+        }
+    }
+
+    /// Create a client configured from environment variables
+    ///
+    /// Reads:
+    /// * `ANALYTICS_URL` - Base URL of analytics ingestion service (required)
+    /// * `ANALYTICS_TOKEN` - Bearer token sent with every batch (optional)
+    /// * `ANALYTICS_BATCH_SIZE` - Events per batch (optional, default 100)
+    /// * `ANALYTICS_FLUSH_INTERVAL_SECS` - Seconds between flushes (optional, default 10)
+    /// * `ANALYTICS_DISABLED` - `1`/`true`/`yes` returns a no-op client; `ANALYTICS_URL`
+    ///   is not required in that case
+    ///
+    /// Returns `AnalyticsError::Config` if `ANALYTICS_URL` is missing or a numeric
+    /// variable is malformed, and `AnalyticsError::WorkerNotRunning` outside a
+    /// tokio runtime (unless disabled).
+    pub fn from_env() -> Result<Self> {
+        let lookup = |key: &str| std::env::var(key).ok();
+
+        if config::is_truthy(lookup("ANALYTICS_DISABLED").as_deref()) {
+            return Ok(Self::noop());
+        }
+
+        let analytics_url = lookup("ANALYTICS_URL")
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| AnalyticsError::Config("ANALYTICS_URL is not set".to_string()))?;
+        let config = ClientConfig::from_lookup(lookup)?;
+
+        Self::builder().url(analytics_url).config(config).build()
+    }
+
+    /// Create a client that hands events to the caller instead of sending them
+    ///
+    /// Returns the client together with a stream yielding every enriched event
+    /// tracked through it (or any of its clones). No HTTP sending happens in
+    /// this mode; the caller owns everything downstream of the stream, e.g.
+    /// custom aggregation, filtering or forwarding.
+    ///
+    /// The stream ends once all clones of the client have been dropped.
+    pub fn channel() -> (Self, impl Stream<Item = EnrichedEvent>) {
+        let config = ClientConfig::default();
+        let (sender, receiver) = queue::channel(None);
+        let client = Self {
+            sender,
+            sink: None,
+            shared: Arc::new(SharedState::new(&config)),
+            config: Arc::new(config),
+        };
+
+        let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv_event().await.map(|event| (event, receiver))
+        });
+
+        (client, stream)
+    }
+
+    /// Create a client that stores events in memory instead of sending them
+    ///
+    /// Every tracked event is enriched and appended to the returned
+    /// [`RecordingHandle`] synchronously, with no background task, batching
+    /// or HTTP. Useful in tests and embedded setups where the caller pulls
+    /// events with [`drain`](Self::drain) or through the handle.
+    pub fn recording() -> (Self, RecordingHandle) {
+        let config = ClientConfig::default();
+        let recording = RecordingHandle::default();
+        let client = Self {
+            sender: EventSender::Recording(recording.clone()),
+            sink: None,
+            shared: Arc::new(SharedState::new(&config)),
+            config: Arc::new(config),
+        };
+
+        (client, recording)
+    }
+
+    /// Take all events buffered by a [`recording`](Self::recording) client
+    ///
+    /// Returns the events tracked since the last drain and clears the buffer.
+    /// Unlike [`flush`](Self::flush) nothing is sent anywhere. Clients that
+    /// deliver events themselves (HTTP, custom sinks, [`channel`](Self::channel))
+    /// have no caller-visible buffer and always return an empty `Vec`.
+    pub fn drain(&self) -> Vec<EnrichedEvent> {
+        match &self.sender {
+            EventSender::Recording(recording) => recording.drain(),
+            _ => Vec::new(),
         }
     }
 
     /// Track an analytics event
     ///
     /// This is non-blocking and will not fail even if the service is unavailable.
-    /// Events are enriched with timestamp and metadata before sending.
-    pub fn track(&self, event: AnalyticsEvent) {
-        let enriched = EnrichedEvent::new(event);
-        // Ignore send errors (background task might be shut down)
-        let _ = self.sender.send(enriched);
+    /// Events are enriched with timestamp and metadata before sending, and
+    /// oversized free-form fields are truncated to `max_field_len`.
+    ///
+    /// Like all `track*` methods, this takes anything convertible into an
+    /// [`AnalyticsEvent`], so domain types can describe their own event:
+    ///
+    /// ```rust
+    /// use lib_analytics_core::{AnalyticsClient, AnalyticsEvent};
+    /// use std::time::Duration;
+    /// use uuid::Uuid;
+    ///
+    /// struct TaskResult {
+    ///     task_id: Uuid,
+    ///     user_id: Uuid,
+    ///     elapsed: Duration,
+    /// }
+    ///
+    /// impl From<TaskResult> for AnalyticsEvent {
+    ///     fn from(result: TaskResult) -> Self {
+    ///         AnalyticsEvent::TaskCompleted {
+    ///             task_id: result.task_id,
+    ///             user_id: result.user_id,
+    ///             duration_ms: result.elapsed.into(),
+    ///             exit_code: 0,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let client = AnalyticsClient::noop();
+    /// client.track(TaskResult {
+    ///     task_id: Uuid::new_v4(),
+    ///     user_id: Uuid::new_v4(),
+    ///     elapsed: Duration::from_secs(3),
+    /// });
+    /// ```
+    pub fn track(&self, event: impl Into<AnalyticsEvent>) {
+        // Ignore send errors (background task might be shut down or queue full)
+        let _ = self.try_track(event);
     }
 
-    /// Track an event only if a condition is true
-    pub fn track_if(&self, condition: bool, event: AnalyticsEvent) {
-        if condition {
-            self.track(event);
-        }
+    /// Track an analytics event, reporting whether it was accepted
+    ///
+    /// Returns `Err(AnalyticsError::ChannelClosed)` if the background task is gone,
+    /// or `Err(AnalyticsError::QueueFull)` if a bounded queue (`queue_capacity`)
+    /// is full and the event was dropped. With `reject_invalid` enabled, events
+    /// failing [`AnalyticsEvent::validate`] are dropped with
+    /// `Err(AnalyticsError::Validation)`. Never blocks.
+    pub fn try_track(&self, event: impl Into<AnalyticsEvent>) -> Result<()> {
+        self.enqueue(event.into(), Overrides::default())
     }
 
-    /// Create a no-op client for testing or disabled analytics
-    pub fn noop() -> Self {
-        Self::new("http://localhost:9999")
+    /// Track an event with an explicit retention hint
+    ///
+    /// Overrides the category default from
+    /// [`AnalyticsEvent::default_retention`], e.g. to keep a particular
+    /// `DatabaseQuery` around for a migration post-mortem.
+    pub fn track_with_retention(
+        &self,
+        event: impl Into<AnalyticsEvent>,
+        retention: RetentionClass,
+    ) {
+        let overrides = Overrides {
+            retention: Some(retention),
+            ..Default::default()
+        };
+        let _ = self.enqueue(event.into(), overrides);
+    }
+
+    /// Track an event on behalf of a tenant
+    ///
+    /// Sets [`EnrichedEvent::tenant_id`], which is part of the payload. With
+    /// [`ClientConfig::tenant_header`] enabled, each batch request carries
+    /// the events of a single tenant and names it in the `X-Tenant-Id`
+    /// header, so the ingestion service can authorize per tenant. `track`
+    /// leaves the tenant unset.
+    pub fn track_for_tenant(&self, tenant_id: Uuid, event: impl Into<AnalyticsEvent>) {
+        let overrides = Overrides {
+            tenant_id: Some(tenant_id),
+            ..Default::default()
+        };
+        let _ = self.enqueue(event.into(), overrides);
     }
 
-    /// Background task that batches and sends events
-    async fn send_loop(
-        mut receiver: mpsc::UnboundedReceiver<EnrichedEvent>,
-        client: reqwest::Client,
-        analytics_url: Arc<str>,
+    /// Track an event with a small binary attached, e.g. a minidump or a
+    /// screenshot for a crash-report style `ApplicationError`
+    ///
+    /// The HTTP sink sends batches containing attachments as
+    /// `multipart/form-data` (see [`EnrichedEvent::attachment`]).
+    /// Attachments larger than [`ClientConfig::max_attachment_bytes`] are
+    /// dropped with a warning; the event is tracked either way.
+    #[cfg(feature = "attachments")]
+    pub fn track_with_attachment(
+        &self,
+        event: impl Into<AnalyticsEvent>,
+        name: impl Into<String>,
+        data: Vec<u8>,
     ) {
-        let mut batch = Vec::with_capacity(100);
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
+        let name = name.into();
+        let attachment = if data.len() > self.config.max_attachment_bytes {
+            tracing::warn!(
+                "Dropping analytics attachment '{}' of {} bytes (limit {})",
+                name,
+                data.len(),
+                self.config.max_attachment_bytes
+            );
+            None
+        } else {
+            Some((name, data))
+        };
+
+        let overrides = Overrides {
+            attachment,
+            ..Default::default()
+        };
+        let _ = self.enqueue(event.into(), overrides);
+    }
 
-        // Skip first tick (happens immediately)
-        interval.tick().await;
+    /// Track an `ApplicationError` for `err`, recording where it was tracked
+    ///
+    /// The source location of the call site (file, line and column) is put
+    /// in the event's `context` under `"location"`, so errors can be traced
+    /// back to the code that reported them without assembling that by hand:
+    ///
+    /// ```json
+    /// {"location": {"file": "src/tasks.rs", "line": 42, "column": 13}}
+    /// ```
+    ///
+    /// Called from a function marked `#[track_caller]`, the location is that
+    /// function's caller. The generic `track` methods record no location.
+    #[track_caller]
+    pub fn track_error_here(
+        &self,
+        service: impl Into<String>,
+        error_type: impl Into<String>,
+        err: impl std::fmt::Display,
+    ) {
+        let location = std::panic::Location::caller();
+        self.track(AnalyticsEvent::ApplicationError {
+            service: service.into(),
+            error_type: error_type.into(),
+            error_message: err.to_string(),
+            user_id: None,
+            context: Some(serde_json::json!({
+                "location": {
+                    "file": location.file(),
+                    "line": location.line(),
+                    "column": location.column(),
+                },
+            })),
+            context_ref: None,
+        });
+    }
 
-        loop {
-            tokio::select! {
-                // Receive event
-                Some(event) = receiver.recv() => {
-                    batch.push(event);
+    /// Track an event that occurred at `occurred_at` rather than now
+    ///
+    /// For relayed or replayed events, e.g. parsed from a device log after
+    /// the fact or backfilled. Only the timestamp differs from `track`:
+    /// hostname, environment, retention and sequence are assigned by this
+    /// client as usual (use `track_enriched` to keep all metadata). With
+    /// `reject_invalid` enabled, times more than five minutes in the future
+    /// are dropped as `ValidationError::OutOfRange`.
+    pub fn track_at(&self, occurred_at: DateTime<Utc>, event: impl Into<AnalyticsEvent>) {
+        let overrides = Overrides {
+            timestamp: Some(occurred_at),
+            ..Default::default()
+        };
+        let _ = self.enqueue(event.into(), overrides);
+    }
 
-                    // Send if batch is full
-                    if batch.len() >= 100 {
-                        Self::send_batch(&client, &analytics_url, &mut batch).await;
-                    }
+    fn enqueue(&self, event: AnalyticsEvent, overrides: Overrides) -> Result<()> {
+        if self.is_filtered(&event) || self.is_sampled_out(&event) {
+            return Ok(());
+        }
+        let result = self.prepare_and_send(event, overrides);
+        match &result {
+            Ok(()) => self.shared.counters.record_enqueued(),
+            Err(e) => {
+                self.shared
+                    .log
+                    .log(LogKind::Drop, format!("Dropped analytics event: {}", e));
+                self.shared.counters.record_dropped();
+            }
+        }
+        result
+    }
+
+    /// Whether tracking `event` would get it queued, without tracking it
+    ///
+    /// Applies the client's `filter` and `sampling` the way tracking does,
+    /// so call sites can skip building an expensive `context` or message for
+    /// events that would be left out, like `tracing`'s level checks. Cheap
+    /// and free of side effects: nothing is counted in
+    /// [`metrics`](Self::metrics). Always `false` for a
+    /// [`noop`](Self::noop) client.
+    ///
+    /// Advisory only: `track` checks again, and validation or a full queue
+    /// can still drop the event. Pass the event without the expensive parts;
+    /// filters and sampling usually only look at its type and ids.
+    ///
+    /// ```rust
+    /// use lib_analytics_core::{AnalyticsClient, AnalyticsEvent};
+    ///
+    /// # fn expensive_context() -> serde_json::Value { serde_json::json!({}) }
+    /// let client = AnalyticsClient::noop();
+    /// let mut event = AnalyticsEvent::ApplicationError {
+    ///     service: "api".to_string(),
+    ///     error_type: "timeout".to_string(),
+    ///     error_message: "upstream timed out".to_string(),
+    ///     user_id: None,
+    ///     context: None,
+    ///     context_ref: None,
+    /// };
+    /// if client.would_record(&event) {
+    ///     if let AnalyticsEvent::ApplicationError { context, .. } = &mut event {
+    ///         *context = Some(expensive_context());
+    ///     }
+    ///     client.track(event);
+    /// }
+    /// ```
+    pub fn would_record(&self, event: &AnalyticsEvent) -> bool {
+        !matches!(self.sender, EventSender::Discard)
+            && !self.filter_rejects(event)
+            && !self.sampling_drops(event)
+    }
+
+    fn filter_rejects(&self, event: &AnalyticsEvent) -> bool {
+        self.config
+            .filter
+            .as_ref()
+            .is_some_and(|filter| !filter.allows(event))
+    }
+
+    fn sampling_drops(&self, event: &AnalyticsEvent) -> bool {
+        self.config
+            .sampling
+            .as_ref()
+            .is_some_and(|sampling| !sampling.keeps(event))
+    }
+
+    /// Whether the configured filter rejects the event, counting it if so
+    fn is_filtered(&self, event: &AnalyticsEvent) -> bool {
+        let filtered = self.filter_rejects(event);
+        if filtered {
+            self.shared.counters.record_filtered();
+        }
+        filtered
+    }
+
+    /// Whether the configured sampling leaves the event out, counting it if so
+    fn is_sampled_out(&self, event: &AnalyticsEvent) -> bool {
+        let sampled_out = self.sampling_drops(event);
+        if sampled_out {
+            self.shared.counters.record_sampled_out();
+        }
+        sampled_out
+    }
+
+    fn prepare_and_send(&self, event: AnalyticsEvent, overrides: Overrides) -> Result<()> {
+        let event = self.prepare(event, overrides)?;
+        self.send_event(event)
+    }
+
+    /// Validate an event and run it through the enrichment chain
+    fn prepare(&self, event: AnalyticsEvent, overrides: Overrides) -> Result<EnrichedEvent> {
+        if self.config.reject_invalid {
+            event.validate()?;
+            if let Some(occurred_at) = overrides.timestamp {
+                validation::validate_occurred_at(occurred_at, Utc::now())?;
+            }
+        }
+
+        let mut enriched = self.shared.enrichment.enrich(event);
+        // Before the overrides: `track_at` times don't come from this clock
+        if let Some(correction) = worker::clock_correction(&self.config, &self.shared) {
+            enriched.timestamp += correction;
+        }
+        overrides.apply(&mut enriched);
+        for middleware in &self.shared.middleware {
+            middleware.enrich(&mut enriched);
+        }
+        Ok(enriched)
+    }
+
+    /// Track many events at once, e.g. a backfill of historical data
+    ///
+    /// Each event is validated, truncated and enriched like with
+    /// [`try_track`](Self::try_track), but instead of being dropped when a
+    /// bounded queue (`queue_capacity`) is full, this waits for room, so a
+    /// large import neither fails nor floods memory. Events are batched as
+    /// usual, so requests never carry more than `batch_size` events.
+    ///
+    /// Returns the number of events enqueued. Events rejected by the
+    /// client's `filter` or left out by its `sampling` are skipped; with
+    /// `reject_invalid`, so are invalid events, which are counted as
+    /// dropped. Use [`track_bulk_with`](Self::track_bulk_with) and
+    /// [`BulkOptions::bypass_sampling`] to keep imports complete. Fails with
+    /// `AnalyticsError::ChannelClosed` if the client was shut down.
+    pub async fn track_bulk<I>(&self, events: I) -> Result<usize>
+    where
+        I: IntoIterator,
+        I::Item: Into<AnalyticsEvent>,
+    {
+        self.track_bulk_with(events, BulkOptions::new()).await
+    }
+
+    /// [`track_bulk`](Self::track_bulk) with the given options
+    ///
+    /// ```rust,no_run
+    /// # use lib_analytics_core::{AnalyticsClient, AnalyticsEvent, BulkOptions};
+    /// # async fn example(client: AnalyticsClient, events: Vec<AnalyticsEvent>) {
+    /// let options = BulkOptions::new().bypass_sampling(true);
+    /// let enqueued = client.track_bulk_with(events, options).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn track_bulk_with<I>(&self, events: I, options: BulkOptions) -> Result<usize>
+    where
+        I: IntoIterator,
+        I::Item: Into<AnalyticsEvent>,
+    {
+        let mut enqueued = 0;
+        for event in events {
+            let event = event.into();
+            if self.is_filtered(&event) || (!options.bypass_sampling && self.is_sampled_out(&event))
+            {
+                continue;
+            }
+            let event = match self.prepare(event, Overrides::default()) {
+                Ok(event) => event,
+                Err(e) => {
+                    self.shared.log.log(
+                        LogKind::Drop,
+                        format!("Skipping invalid analytics event in bulk import: {}", e),
+                    );
+                    self.shared.counters.record_dropped();
+                    continue;
                 }
+            };
 
-                // Periodic flush
-                _ = interval.tick() => {
-                    if !batch.is_empty() {
-                        Self::send_batch(&client, &analytics_url, &mut batch).await;
-                    }
+            if let Err(e) = self.sender.send_wait(event).await {
+                self.shared.counters.record_dropped();
+                return Err(e);
+            }
+            if self.sink.is_some() {
+                self.shared.counters.record_queued();
+            }
+            self.shared.counters.record_enqueued();
+            enqueued += 1;
+        }
+        Ok(enqueued)
+    }
+
+    /// Send a large import of historical events straight to the sink
+    ///
+    /// For imports of tens of thousands of events, not steady-state
+    /// tracking: events bypass the queue and the background task, so they
+    /// don't interleave with live batches, `max_event_age` and the batching
+    /// policy don't apply, and nothing is held back for aggregation. The
+    /// iterator is consumed lazily and cut into chunks of at most
+    /// `batch_size` events and [`BackfillOptions::max_chunk_bytes`]; up to
+    /// `max_concurrent_sends` chunks are in flight while the next one is
+    /// prepared, so memory stays bounded by that many chunks. Sends share
+    /// the `max_concurrent_sends` slots with the client's regular batches.
+    ///
+    /// Events are validated, truncated and enriched like with
+    /// [`track_bulk`](Self::track_bulk); those the `filter` rejects or, with
+    /// `reject_invalid`, invalid ones are skipped. A failed chunk is not
+    /// retried beyond what the sink does, so check the report and re-import
+    /// the failed ranges if needed. With `dry_run`, chunks are encoded and
+    /// logged like the client's batches and counted as sent. Clients without a sink (see
+    /// [`recording`](Self::recording)) hand the events to their queue.
+    ///
+    /// ```rust,no_run
+    /// # use lib_analytics_core::{AnalyticsClient, AnalyticsEvent, BackfillOptions};
+    /// # async fn import(client: AnalyticsClient, rows: Vec<AnalyticsEvent>) {
+    /// let options = BackfillOptions::new().on_progress(|progress| {
+    ///     tracing::info!("Imported {} events", progress.events_sent);
+    /// });
+    /// let report = client.backfill(rows, options).await;
+    /// for (chunk, error) in report.errors() {
+    ///     tracing::error!("Chunk {} failed: {}", chunk, error);
+    /// }
+    /// # }
+    /// ```
+    pub async fn backfill<I>(&self, events: I, options: BackfillOptions) -> BackfillReport
+    where
+        I: IntoIterator,
+        I::Item: Into<AnalyticsEvent>,
+    {
+        let mut report = BackfillReport::default();
+        let mut chunker = Chunker::new(self.config.batch_size, options.max_chunk_bytes);
+        let max_in_flight = self.config.max_concurrent_sends.max(1);
+        let mut in_flight = FuturesUnordered::new();
+
+        for event in events {
+            let event = event.into();
+            if self.is_filtered(&event) {
+                report.skip();
+                continue;
+            }
+            let event = match self.prepare(event, Overrides::default()) {
+                Ok(event) => event,
+                Err(e) => {
+                    self.shared.log.log(
+                        LogKind::Drop,
+                        format!("Skipping invalid analytics event in backfill: {}", e),
+                    );
+                    self.shared.counters.record_dropped();
+                    report.skip();
+                    continue;
+                }
+            };
+
+            let Some((index, chunk)) = chunker.push(event) else {
+                continue;
+            };
+            if in_flight.len() >= max_in_flight {
+                if let Some(done) = in_flight.next().await {
+                    report.complete(done, &options);
                 }
             }
+            in_flight.push(self.send_backfill_chunk(index, chunk));
         }
+
+        if let Some((index, chunk)) = chunker.finish() {
+            in_flight.push(self.send_backfill_chunk(index, chunk));
+        }
+        while let Some(done) = in_flight.next().await {
+            report.complete(done, &options);
+        }
+        report.finish()
     }
 
-    /// Send a batch of events to the analytics service
-    async fn send_batch(
-        client: &reqwest::Client,
-        analytics_url: &str,
-        batch: &mut Vec<EnrichedEvent>,
-    ) {
-        let count = batch.len();
-        if count == 0 {
+    /// Deliver one backfill chunk, counting it like a regular batch
+    async fn send_backfill_chunk(&self, index: usize, chunk: Vec<EnrichedEvent>) -> ChunkReport {
+        let events = chunk.len();
+        let result = if self.config.dry_run {
+            dry_run::log(&self.config, &chunk)
+        } else {
+            match &self.sink {
+                Some(sink) => worker::deliver(sink.as_ref(), &self.shared, &chunk).await,
+                None => chunk
+                    .into_iter()
+                    .try_for_each(|event| self.sender.send(event)),
+            }
+        };
+
+        match &result {
+            Ok(()) => {
+                self.shared.counters.record_sent(events);
+                if !self.config.dry_run {
+                    self.shared.counters.record_success(Utc::now());
+                }
+            }
+            Err(e) => {
+                self.shared.log.log(
+                    LogKind::Failure,
+                    format!("Failed to send backfilled analytics events: {}", e),
+                );
+                self.shared.counters.record_failed(events);
+            }
+        }
+        ChunkReport {
+            index,
+            events,
+            result,
+        }
+    }
+
+    /// Track an already enriched event as-is
+    ///
+    /// For relaying events that were enriched elsewhere (e.g. buffered by a
+    /// subprocess): the event's timestamp, hostname and environment are kept
+    /// instead of being filled from this host. The caller is responsible for
+    /// setting that metadata. The event is batched like any other; validation
+    /// and truncation are skipped. Only `sequence` (this client's next
+    /// sequence number) and `schema_version` (derived from the event) are
+    /// overwritten. The client's `filter` and `sampling` still apply.
+    pub fn track_enriched(&self, mut event: EnrichedEvent) {
+        if self.is_filtered(&event.event) || self.is_sampled_out(&event.event) {
             return;
         }
+        event.sequence = self.shared.enrichment.next_sequence();
+        event.schema_version = event.event.schema_version();
+        let _ = self.send_event(event);
+    }
 
-        let url = format!("{}/events/batch", analytics_url);
+    /// Put an event on the queue, counting it for idle tracking
+    fn send_event(&self, event: EnrichedEvent) -> Result<()> {
+        self.sender.send(event)?;
+        // Only a background task takes events off the queue again
+        if self.sink.is_some() {
+            self.shared.counters.record_queued();
+        }
+        Ok(())
+    }
 
-        match client.post(&url).json(&batch).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    tracing::debug!("Sent {} analytics events", count);
-                } else {
-                    tracing::warn!(
-                        "Failed to send analytics events: HTTP {}",
-                        response.status()
-                    );
+    /// Track an event only if a condition is true
+    pub fn track_if(&self, condition: bool, event: impl Into<AnalyticsEvent>) {
+        if condition {
+            self.track(event);
+        }
+    }
+
+    /// Send all events tracked so far without waiting for the flush interval
+    ///
+    /// Resolves once the current batch has been handed to the sink, returning
+    /// the sink's result. Events tracked concurrently from other clones may or
+    /// may not be included. While the client is [paused](Self::pause), sends
+    /// nothing and returns `AnalyticsError::Paused`.
+    pub async fn flush(&self) -> Result<()> {
+        let (reply, response) = oneshot::channel();
+        self.sender.send_control(Message::Flush(reply)).await?;
+        response
+            .await
+            .map_err(|_| AnalyticsError::WorkerNotRunning)?
+    }
+
+    /// Stop sending events until [`resume`](Self::resume) is called
+    ///
+    /// For maintenance windows, e.g. a noisy migration of the ingestion
+    /// service: tracking continues as usual, but batches are kept instead of
+    /// sent. Nothing is sent on the flush interval, and `flush` and
+    /// `track_and_flush` return `AnalyticsError::Paused` without sending
+    /// (the event of `track_and_flush` is still tracked). Affects all
+    /// clones of the client. `shutdown` still sends the backlog, so pausing
+    /// never loses events by itself.
+    ///
+    /// The backlog lives in memory. With `queue_capacity` set, it holds at
+    /// most that many events and later ones are dropped (and counted as
+    /// such); without it, a long pause under heavy traffic grows memory
+    /// without bound.
+    pub fn pause(&self) {
+        self.shared.set_paused(true);
+    }
+
+    /// Resume sending after [`pause`](Self::pause) and flush the backlog
+    ///
+    /// The backlog is sent right away in batches of `batch_size`; the result
+    /// is that of the flush.
+    pub async fn resume(&self) -> Result<()> {
+        self.shared.set_paused(false);
+        self.flush().await
+    }
+
+    /// Whether sending is paused
+    pub fn is_paused(&self) -> bool {
+        self.shared.is_paused()
+    }
+
+    /// Track an event and send it right away
+    ///
+    /// For the occasional event that must not wait for the flush interval
+    /// (e.g. a security alert), without changing the configuration of the
+    /// whole client. Equivalent to [`try_track`](Self::try_track) followed by
+    /// [`flush`](Self::flush): the event goes out together with everything
+    /// tracked before it, and the future resolves once that send attempt
+    /// completes.
+    ///
+    /// The batch is sent like any other, so it gets whatever retry/backoff
+    /// the sink applies and may still resolve with the sink's error. While
+    /// the client is [paused](Self::pause), the event is kept with the
+    /// backlog and this returns `AnalyticsError::Paused`.
+    pub async fn track_and_flush(&self, event: impl Into<AnalyticsEvent>) -> Result<()> {
+        self.try_track(event)?;
+        self.flush().await
+    }
+
+    /// Flush remaining events and stop the background task
+    ///
+    /// Waits at most `timeout` for the final batch to be sent, returning
+    /// `AnalyticsError::FlushTimeout` otherwise. Clones of a client share one
+    /// background task, so this stops delivery for all of them; events
+    /// tracked afterwards are rejected with `AnalyticsError::ChannelClosed`.
+    ///
+    /// Returns a [`ShutdownReport`] of how many events were delivered and
+    /// lost over the client's lifetime. A failed final send is counted in the
+    /// report rather than returned as an error.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<ShutdownReport> {
+        let started = Instant::now();
+        let shutdown = async {
+            let (reply, response) = oneshot::channel();
+            self.sender.send_control(Message::Shutdown(reply)).await?;
+            response.await.map_err(|_| AnalyticsError::WorkerNotRunning)
+        };
+
+        tokio::time::timeout(timeout, shutdown)
+            .await
+            .map_err(|_| AnalyticsError::FlushTimeout)??;
+
+        Ok(self.shared.counters.shutdown_report(started.elapsed()))
+    }
+
+    /// Check that the analytics destination is reachable
+    ///
+    /// For the HTTP sink this sends `GET {analytics_url}/health` with the
+    /// configured auth token and `request_timeout`, which also warms up the
+    /// connection used by the first batch. Call it from startup probes to fail
+    /// fast on a wrong URL or token; a non-success response is returned as an
+    /// error carrying the status code. Custom sinks answer via
+    /// [`EventSink::health_check`]; clients created with
+    /// [`channel`](Self::channel) always succeed.
+    pub async fn health_check(&self) -> Result<()> {
+        match &self.sink {
+            Some(sink) => sink.health_check().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Send one event to the ingestion service's debug endpoint and return
+    /// the event as the service parsed it
+    ///
+    /// For diagnosing serialization mismatches: compare the result with the
+    /// event you sent, and a field the server misreads or ignores shows up
+    /// right away instead of as missing data downstream. The event is
+    /// prepared like with `track` (validation, truncation, enrichment) but
+    /// bypasses the queue and is not stored by the service.
+    ///
+    /// Server contract: `POST {analytics_url}/events/debug` carries a batch
+    /// of exactly one event, serialized and authenticated like regular
+    /// batches. The service parses it as it would a batch, stores nothing,
+    /// and answers `200` with a JSON body holding the parsed event as an
+    /// [`EnrichedEvent`] object. Non-2xx responses surface as
+    /// `AnalyticsError::Http`.
+    ///
+    /// Only supported by clients sending to an ingestion URL; others fail
+    /// with `AnalyticsError::Config`.
+    pub async fn debug_roundtrip(&self, event: impl Into<AnalyticsEvent>) -> Result<EnrichedEvent> {
+        let event = self.prepare(event.into(), Overrides::default())?;
+        match &self.sink {
+            Some(sink) => sink.debug_roundtrip(&event).await,
+            None => Err(AnalyticsError::Config(
+                "client has no sink to round-trip through".to_string(),
+            )),
+        }
+    }
+
+    /// Create a lightweight client that fills fixed ids into every event
+    ///
+    /// The returned [`ScopedClient`] shares this client's queue. Optional
+    /// `user_id`, `project_id` and `cocoon_id` fields left as `None` are
+    /// filled from `scope`; ids the event already specifies are kept. Useful
+    /// for middleware that establishes per-request context once.
+    pub fn scoped(&self, scope: ScopeContext) -> ScopedClient {
+        ScopedClient::new(self.clone(), scope)
+    }
+
+    /// Point the client at a different ingestion service URL
+    ///
+    /// Takes effect with the next request, including batches already
+    /// queued, for this client and all its clones; a send in progress
+    /// finishes against the old URL. Lets a control plane move clients
+    /// without restarting services.
+    ///
+    /// Fails with `AnalyticsError::Config` if `url` is not an absolute
+    /// `http` or `https` URL, or if the client delivers to a custom sink
+    /// rather than the ingestion service.
+    pub fn set_url(&self, url: impl Into<String>) -> Result<()> {
+        let url = url.into();
+        validate_url(&url)?;
+        if self.shared.analytics_url.load().is_none() {
+            return Err(AnalyticsError::Config(
+                "client has no ingestion service URL to change".to_string(),
+            ));
+        }
+
+        tracing::info!("Analytics ingestion URL changed to {}", url);
+        self.shared.analytics_url.store(Some(Arc::new(url)));
+        Ok(())
+    }
+
+    /// Clock offset between this host and the analytics service
+    ///
+    /// Measured from the `Date` header of the most recent batch response as
+    /// `server_time - local_time` (positive means the local clock is behind).
+    /// Returns `None` until a response with a `Date` header has been received.
+    /// The header has one-second resolution, so small offsets are noise.
+    pub fn clock_offset(&self) -> Option<chrono::Duration> {
+        self.shared.clock_skew.offset()
+    }
+
+    /// Current state of the delivery pipeline (e.g. in-flight sends)
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.shared.counters.snapshot()
+    }
+
+    /// Stream of the outcome of every batch handed to the sink from now on
+    ///
+    /// Each subscriber gets its own copy of every outcome, for dashboards or
+    /// tests observing actual delivery. Outcomes are broadcast: the worker
+    /// never waits for subscribers. One that falls more than 256 outcomes
+    /// behind skips the oldest ones and continues with the rest. Dry runs
+    /// produce no outcomes. The stream ends once all clones of the client
+    /// and its background task are gone.
+    pub fn subscribe_outcomes(&self) -> impl Stream<Item = BatchOutcome> {
+        let receiver = self.shared.outcomes.subscribe();
+        futures_util::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(outcome) => return Some((outcome, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Outcome subscriber lagged, skipped {} outcomes", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
                 }
             }
-            Err(e) => {
-                tracing::warn!("Failed to send analytics events: {}", e);
+        })
+    }
+
+    /// Wait until every tracked event has been handed to the sink
+    ///
+    /// Resolves once nothing is queued, batched or being sent (see
+    /// [`MetricsSnapshot::is_idle`]), so tests can assert on a sink's
+    /// contents without sleeping. Events only leave the batch when it is
+    /// sent, so use a small `batch_size` or call `flush` first if the flush
+    /// interval is long. Returns `AnalyticsError::FlushTimeout` if the
+    /// pipeline isn't idle within `timeout`. Requires the `test-util`
+    /// feature.
+    #[cfg(feature = "test-util")]
+    pub async fn wait_idle(&self, timeout: Duration) -> Result<()> {
+        let idle = async {
+            while !self.metrics().is_idle() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
             }
+        };
+        tokio::time::timeout(timeout, idle)
+            .await
+            .map_err(|_| AnalyticsError::FlushTimeout)
+    }
+
+    /// When a batch was last delivered successfully
+    ///
+    /// For liveness checks, e.g. "analytics last delivered N seconds ago".
+    /// `None` if no batch has been delivered yet (dry runs don't count). Set
+    /// whenever the sink accepts a batch, i.e. on a 2xx response for HTTP.
+    pub fn last_successful_send(&self) -> Option<DateTime<Utc>> {
+        self.shared.counters.last_success()
+    }
+
+    /// Create a no-op client for testing or disabled analytics
+    ///
+    /// Tracked events are accepted and dropped right away; `flush` and
+    /// `shutdown` succeed immediately. No background task is spawned and
+    /// nothing is sent or logged, so this also works outside a tokio runtime.
+    pub fn noop() -> Self {
+        let config = ClientConfig::default();
+        Self {
+            sender: EventSender::Discard,
+            sink: None,
+            shared: Arc::new(SharedState::new(&config)),
+            config: Arc::new(config),
+        }
+    }
+}
+
+/// `config` with a zero `batch_size` raised to 1, for the constructors that
+/// skip validation
+fn with_min_batch_size(config: ClientConfig) -> ClientConfig {
+    ClientConfig {
+        batch_size: config.batch_size.max(1),
+        ..config
+    }
+}
+
+/// `AnalyticsError::WorkerNotRunning` unless called within a tokio runtime,
+/// where the background task can be spawned
+pub(crate) fn ensure_runtime() -> Result<()> {
+    tokio::runtime::Handle::try_current()
+        .map(|_| ())
+        .map_err(|_| AnalyticsError::WorkerNotRunning)
+}
+
+/// Check that `url` can serve as the ingestion service's base URL
+pub(crate) fn validate_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| AnalyticsError::Config(format!("invalid analytics URL '{}': {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+        return Err(AnalyticsError::Config(format!(
+            "analytics URL '{}' must be an absolute http(s) URL",
+            url
+        )));
+    }
+    Ok(())
+}
+
+/// Per-call changes to the metadata `track` would assign
+#[derive(Default)]
+struct Overrides {
+    timestamp: Option<DateTime<Utc>>,
+    retention: Option<RetentionClass>,
+    tenant_id: Option<Uuid>,
+    #[cfg(feature = "attachments")]
+    attachment: Option<(String, Vec<u8>)>,
+}
+
+impl Overrides {
+    fn apply(self, event: &mut EnrichedEvent) {
+        if let Some(timestamp) = self.timestamp {
+            event.timestamp = timestamp;
         }
+        if self.retention.is_some() {
+            event.retention = self.retention;
+        }
+        if self.tenant_id.is_some() {
+            event.tenant_id = self.tenant_id;
+        }
+        #[cfg(feature = "attachments")]
+        if self.attachment.is_some() {
+            event.attachment = self.attachment;
+        }
+    }
+}
+
+/// Wrap `sink` in the configured durable buffer, if any
+///
+/// If the buffer can't be opened the error is logged and events are sent
+/// without it.
+#[cfg(feature = "durable")]
+fn with_durable_buffer(
+    sink: Arc<dyn EventSink>,
+    config: &ClientConfig,
+    shared: &Arc<SharedState>,
+) -> Arc<dyn EventSink> {
+    let Some(buffer) = &config.durable_buffer else {
+        return sink;
+    };
+
+    match crate::sink::DurableSink::open(buffer, sink.clone(), config.batch_size, shared.clone()) {
+        Ok(durable) => {
+            let durable: Arc<dyn EventSink> = Arc::new(durable);
 
-        batch.clear();
+            // Deliver events left from a previous run without waiting for new ones
+            let replay = durable.clone();
+            tokio::spawn(async move {
+                if let Err(e) = replay.send(&[]).await {
+                    tracing::warn!("Failed to resend buffered analytics events: {}", e);
+                }
+            });
+
+            durable
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to open durable analytics buffer {}, sending without it: {}",
+                buffer.path().display(),
+                e
+            );
+            sink
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::duration::DurationMs;
+    use crate::environment::Environment;
+    use crate::error::{AnalyticsError, ValidationError};
+    use crate::sampling::{SampleKey, Sampling};
+    use crate::sanitize::UserIdPolicy;
+    use crate::sink::testing::CollectSink;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::{span, Event, Level, Metadata, Subscriber};
     use uuid::Uuid;
 
-    #[test]
-    fn test_client_creation() {
-        let client = AnalyticsClient::new("http://localhost:8094");
+    /// Subscriber capturing the level and fields of every event
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Captured>>>);
 
-        // Should not panic
-        client.track(AnalyticsEvent::AuthLoginAttempt {
-            user_id: Some(Uuid::new_v4()),
-            email: "test@example.com".to_string(),
-            success: true,
-            error: None,
-        });
+    struct Captured {
+        level: Level,
+        /// Field values formatted with `Debug`
+        fields: BTreeMap<&'static str, String>,
     }
 
-    #[test]
-    fn test_noop_client() {
-        let client = AnalyticsClient::noop();
+    impl Captured {
+        fn field(&self, name: &str) -> Option<String> {
+            self.fields.get(name).cloned()
+        }
+    }
+
+    impl Capture {
+        fn events(&self) -> Vec<Captured> {
+            std::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    impl Visit for Captured {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.fields.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut captured = Captured {
+                level: *event.metadata().level(),
+                fields: BTreeMap::new(),
+            };
+            event.record(&mut captured);
+            self.0.lock().unwrap().push(captured);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    // `new` spawns the background task, so it needs a runtime (see
+    // `test_try_new_without_runtime` for what happens without one)
+    #[tokio::test]
+    async fn test_client_creation() {
+        let client = AnalyticsClient::new("http://localhost:8094");
 
         // Should not panic
         client.track(AnalyticsEvent::AuthLoginAttempt {
@@ -158,4 +1164,1595 @@ mod tests {
             error: None,
         });
     }
+
+    #[test]
+    fn test_noop_client() {
+        let client = AnalyticsClient::noop();
+
+        // Should not panic
+        client.track(AnalyticsEvent::AuthLoginAttempt {
+            user_id: Some(Uuid::new_v4()),
+            email: "test@example.com".to_string(),
+            success: true,
+            error: None,
+        });
+    }
+
+    #[test]
+    fn test_sent_batch_logs_counts_by_type() {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let client =
+                    AnalyticsClient::with_sink(CollectSink::default(), ClientConfig::default());
+                for _ in 0..2 {
+                    client.track(AnalyticsEvent::ProjectUpdated {
+                        project_id: Uuid::new_v4(),
+                        user_id: Uuid::new_v4(),
+                    });
+                }
+                client.track(AnalyticsEvent::ProjectDeleted {
+                    project_id: Uuid::new_v4(),
+                    user_id: Uuid::new_v4(),
+                });
+                client.flush().await.unwrap();
+            });
+        });
+
+        // One record for the batch, carrying the counts by type
+        let counts: Vec<_> = capture
+            .events()
+            .into_iter()
+            .filter(|event| event.level == Level::DEBUG)
+            .filter_map(|event| Some((event.field("count")?, event.field("types")?)))
+            .collect();
+        assert_eq!(
+            counts,
+            [(
+                "3".to_string(),
+                r#"{"project_deleted": 1, "project_updated": 2}"#.to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_url_redirects_next_send() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        /// Accept one batch, answer 200 and return the request line
+        async fn answer_one(listener: tokio::net::TcpListener) -> String {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request.ends_with(b"]") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let request = String::from_utf8(request).unwrap();
+            request.lines().next().unwrap().to_string()
+        }
+
+        let old = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let new = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let new_addr = new.local_addr().unwrap();
+        let client = AnalyticsClient::new(format!("http://{}", old.local_addr().unwrap()));
+        let server = tokio::spawn(answer_one(new));
+
+        let clone = client.clone();
+        assert!(matches!(
+            clone.set_url("localhost:8094"),
+            Err(AnalyticsError::Config(_))
+        ));
+        clone
+            .set_url(format!("http://{}/analytics", new_addr))
+            .unwrap();
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+        assert_eq!(
+            server.await.unwrap(),
+            "POST /analytics/events/batch HTTP/1.1"
+        );
+
+        // Clients with a custom sink have no URL to change
+        let custom = AnalyticsClient::with_sink(CollectSink::default(), ClientConfig::default());
+        assert!(matches!(
+            custom.set_url("http://localhost:8094"),
+            Err(AnalyticsError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_batch_policy_flush_every_event() {
+        use crate::batch::{BatchPolicy, BatchState};
+
+        struct EveryEvent;
+
+        impl BatchPolicy for EveryEvent {
+            fn should_flush(&self, _: &BatchState) -> bool {
+                true
+            }
+            fn max_delay(&self) -> Duration {
+                Duration::from_secs(3600)
+            }
+        }
+
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .batch_policy(EveryEvent)
+            .build()
+            .unwrap();
+
+        for _ in 0..3 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+        while !client.metrics().is_idle() {
+            tokio::task::yield_now().await;
+        }
+
+        let sizes: Vec<usize> = sink.batches().iter().map(Vec::len).collect();
+        assert_eq!(sizes, [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_try_new_without_runtime() {
+        assert!(matches!(
+            AnalyticsClient::try_new("http://localhost:8094"),
+            Err(AnalyticsError::WorkerNotRunning)
+        ));
+        assert!(matches!(
+            AnalyticsClient::builder()
+                .sink(CollectSink::default())
+                .build(),
+            Err(AnalyticsError::WorkerNotRunning)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_try_new_rejects_malformed_url() {
+        for url in ["not a url", "ftp://example.com", "localhost:8094"] {
+            assert!(
+                matches!(
+                    AnalyticsClient::try_new(url),
+                    Err(AnalyticsError::Config(_))
+                ),
+                "{}",
+                url
+            );
+        }
+        assert!(AnalyticsClient::try_new("http://localhost:8094").is_ok());
+    }
+
+    // No runtime: spawning a background task would panic
+    #[test]
+    fn test_noop_client_is_inert() {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            let client = AnalyticsClient::noop();
+            for _ in 0..3 {
+                client.track(AnalyticsEvent::ProjectUpdated {
+                    project_id: Uuid::new_v4(),
+                    user_id: Uuid::new_v4(),
+                });
+            }
+
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .start_paused(true)
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                client.flush().await.unwrap();
+                // Several flush intervals of a real client
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                client.shutdown(Duration::from_secs(1)).await.unwrap();
+            });
+
+            assert!(client.sink.is_none());
+            assert!(client.metrics().is_idle());
+        });
+
+        let warnings = capture
+            .events()
+            .into_iter()
+            .filter(|event| event.level <= Level::WARN)
+            .count();
+        assert_eq!(warnings, 0);
+    }
+
+    #[tokio::test]
+    async fn test_try_track_queue_full() {
+        let config = ClientConfig {
+            queue_capacity: Some(1),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::builder()
+            .url("http://localhost:8094")
+            .config(config)
+            .build()
+            .unwrap();
+        let event = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        // Background task hasn't run yet on the current-thread runtime
+        assert!(client.try_track(event.clone()).is_ok());
+        assert!(matches!(
+            client.try_track(event),
+            Err(AnalyticsError::QueueFull)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_channel_yields_events() {
+        use futures_util::StreamExt;
+
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+
+        let user_id = Uuid::new_v4();
+        client.track(AnalyticsEvent::AuthSessionValidated {
+            user_id,
+            valid: true,
+        });
+        drop(client);
+
+        let event = stream.next().await.unwrap();
+        assert_eq!(event.event.event_type(), "auth_session_validated");
+        assert_eq!(event.event.user_id(), Some(user_id));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_clock_correction_above_threshold_only() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .correct_clock_skew(true)
+            .clock_skew_threshold(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let event = || AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+        let skew = |seconds| {
+            let now = Utc::now();
+            client.shared.clock_skew.record(
+                now + chrono::Duration::seconds(seconds),
+                now,
+                Duration::from_secs(60),
+                &client.shared.log,
+            );
+        };
+
+        // Within the threshold: left alone
+        skew(30);
+        let before = Utc::now();
+        client.track(event());
+        client.flush().await.unwrap();
+        assert!(sink.events()[0].timestamp - before < chrono::Duration::seconds(10));
+
+        skew(3600);
+        let before = Utc::now();
+        client.track(event());
+        // Explicit and relayed times are not from this clock
+        let occurred_at = before - chrono::Duration::hours(2);
+        client.track_at(occurred_at, event());
+        let mut relayed = EnrichedEvent::new(event());
+        relayed.timestamp = occurred_at;
+        client.track_enriched(relayed);
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert!(events[1].timestamp - before >= chrono::Duration::seconds(3600));
+        assert_eq!(events[2].timestamp, occurred_at);
+        assert_eq!(events[3].timestamp, occurred_at);
+    }
+
+    #[tokio::test]
+    async fn test_track_enriched_keeps_metadata() {
+        use futures_util::StreamExt;
+
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+
+        let timestamp = chrono::Utc::now() - chrono::Duration::hours(1);
+        client.track_enriched(EnrichedEvent {
+            timestamp,
+            event: AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            },
+            hostname: Some("worker-1".to_string()),
+            environment: Some(Environment::Staging),
+            retention: None,
+            sequence: 0,
+            monotonic_ns: 0,
+            schema_version: 0,
+            tenant_id: None,
+            canonical_action: None,
+            #[cfg(feature = "attachments")]
+            attachment: None,
+        });
+
+        let event = stream.next().await.unwrap();
+        assert_eq!(event.timestamp, timestamp);
+        assert_eq!(event.hostname.as_deref(), Some("worker-1"));
+        assert_eq!(event.environment, Some(Environment::Staging));
+    }
+
+    #[tokio::test]
+    async fn test_track_with_retention_overrides_default() {
+        use futures_util::StreamExt;
+
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+
+        let event = AnalyticsEvent::DatabaseQuery {
+            service: "platform".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs(3),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow: None,
+        };
+        client.track(event.clone());
+        client.track_with_retention(event, RetentionClass::Long);
+
+        let first = stream.next().await.unwrap();
+        let second = stream.next().await.unwrap();
+        assert_eq!(first.retention, Some(RetentionClass::Short));
+        assert_eq!(second.retention, Some(RetentionClass::Long));
+    }
+
+    #[tokio::test]
+    async fn test_debug_roundtrip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Echo the single event of the batch, dropping `hostname` as a
+        // server with an outdated schema would
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request.ends_with(b"]") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("POST /events/debug HTTP/1.1"));
+
+            let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+            let mut batch: Vec<serde_json::Value> = serde_json::from_str(body).unwrap();
+            let mut event = batch.remove(0);
+            event.as_object_mut().unwrap().remove("hostname");
+            let echo = event.to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                echo.len(),
+                echo
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let client = AnalyticsClient::builder()
+            .url(format!("http://{}", addr))
+            .hostname("debug-host")
+            .build()
+            .unwrap();
+        let project_id = Uuid::new_v4();
+        let parsed = client
+            .debug_roundtrip(AnalyticsEvent::ProjectUpdated {
+                project_id,
+                user_id: Uuid::new_v4(),
+            })
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(matches!(
+            parsed.event,
+            AnalyticsEvent::ProjectUpdated { project_id: id, .. } if id == project_id
+        ));
+        assert_eq!(parsed.hostname, None);
+
+        let (recording, _) = AnalyticsClient::recording();
+        assert!(matches!(
+            recording
+                .debug_roundtrip(AnalyticsEvent::ProjectDeleted {
+                    project_id,
+                    user_id: Uuid::new_v4(),
+                })
+                .await,
+            Err(AnalyticsError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_unreachable() {
+        let client = AnalyticsClient::new("http://127.0.0.1:1");
+
+        assert!(matches!(
+            client.health_check().await,
+            Err(AnalyticsError::Connect(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hostname_environment_overrides() {
+        let sink = CollectSink::default();
+        let config = ClientConfig {
+            hostname: Some("sim-host-1".to_string()),
+            environment: Some("Prod".into()),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(events[0].hostname.as_deref(), Some("sim-host-1"));
+        assert_eq!(events[0].environment, Some(Environment::Production));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_shared_across_clones() {
+        use futures_util::StreamExt;
+
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+        let clone = client.clone();
+        let event = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track(event.clone());
+        clone.track(event.clone());
+        client.track(event);
+
+        for expected in 0..3 {
+            assert_eq!(stream.next().await.unwrap().sequence, expected);
+        }
+    }
+
+    #[test]
+    fn test_monotonic_ns_orders_same_millisecond_events() {
+        let (client, recording) = AnalyticsClient::recording();
+
+        for _ in 0..100 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+
+        let events = recording.events();
+        let ticks: Vec<u64> = events.iter().map(|e| e.monotonic_ns).collect();
+        assert!(ticks.windows(2).all(|w| w[0] <= w[1]));
+        assert!(ticks[99] > ticks[0]);
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(json["monotonic_ns"], ticks[0]);
+    }
+
+    #[test]
+    fn test_recording_drain() {
+        let (client, recording) = AnalyticsClient::recording();
+
+        for _ in 0..5 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+
+        assert_eq!(recording.len(), 5);
+        assert_eq!(client.drain().len(), 5);
+        assert!(client.drain().is_empty());
+        assert!(recording.is_empty());
+    }
+
+    #[test]
+    fn test_track_error_here_records_location() {
+        let (client, recording) = AnalyticsClient::recording();
+
+        let line = line!() + 1;
+        client.track_error_here("api", "io", std::io::Error::other("disk full"));
+
+        let events = recording.drain();
+        let AnalyticsEvent::ApplicationError {
+            error_message,
+            context: Some(context),
+            ..
+        } = &events[0].event
+        else {
+            panic!("unexpected event: {:?}", events[0].event);
+        };
+        assert_eq!(error_message, "disk full");
+        assert_eq!(context["location"]["file"], file!());
+        assert_eq!(context["location"]["line"], line);
+    }
+
+    #[test]
+    fn test_recording_summary() {
+        let (client, recording) = AnalyticsClient::recording();
+        let (task_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..2 {
+            client.track(AnalyticsEvent::TaskFailed {
+                task_id,
+                user_id,
+                duration_ms: None,
+                exit_code: Some(1),
+                error: "boom".to_string(),
+            });
+        }
+        client.track(AnalyticsEvent::TaskCompleted {
+            task_id,
+            user_id,
+            duration_ms: DurationMs::from_std(Duration::from_secs(1)),
+            exit_code: 0,
+        });
+
+        assert_eq!(
+            recording.summary(),
+            BTreeMap::from([("task_completed", 1), ("task_failed", 2)])
+        );
+        assert_eq!(recording.count_of("task_failed"), 2);
+        assert_eq!(recording.count_of("task_cancelled"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drain_http_client_is_empty() {
+        let client = AnalyticsClient::new("http://localhost:8094");
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+
+        assert!(client.drain().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        let config = ClientConfig {
+            proxy: Some(reqwest::Proxy::all("http://127.0.0.1:1").unwrap()),
+            ..Default::default()
+        };
+        // The target is resolved by the proxy, so this fails connecting to the proxy
+        let client = AnalyticsClient::builder()
+            .url("http://analytics.invalid")
+            .config(config)
+            .build()
+            .unwrap();
+
+        let err = client.health_check().await.unwrap_err();
+        let AnalyticsError::Connect(err) = err else {
+            panic!("unexpected error: {:?}", err);
+        };
+        assert!(err.is_connect());
+    }
+
+    #[tokio::test]
+    async fn test_reject_invalid() {
+        let config = ClientConfig {
+            reject_invalid: true,
+            ..Default::default()
+        };
+        let client = AnalyticsClient::builder()
+            .url("http://localhost:8094")
+            .config(config)
+            .build()
+            .unwrap();
+
+        let result = client.try_track(AnalyticsEvent::TaskCompleted {
+            task_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            duration_ms: DurationMs(-1),
+            exit_code: 0,
+        });
+        assert!(matches!(
+            result,
+            Err(AnalyticsError::Validation(
+                ValidationError::NegativeDuration { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_flush_sends_pending_events() {
+        let sink = CollectSink::default();
+        let config = ClientConfig {
+            flush_interval: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+
+        for _ in 0..3 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+        client.flush().await.unwrap();
+
+        assert_eq!(sink.events().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_track_at_keeps_occurrence_time() {
+        let (client, recording) = AnalyticsClient::recording();
+        let occurred_at = Utc::now() - chrono::Duration::days(3);
+
+        client.track_at(
+            occurred_at,
+            AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            },
+        );
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+
+        let events = recording.drain();
+        assert_eq!(events[0].timestamp, occurred_at);
+        assert_eq!((events[0].sequence, events[1].sequence), (0, 1));
+        assert!(events[1].timestamp > occurred_at);
+    }
+
+    #[tokio::test]
+    async fn test_track_for_tenant() {
+        let (client, recording) = AnalyticsClient::recording();
+        let tenant_id = Uuid::new_v4();
+
+        client.track_for_tenant(
+            tenant_id,
+            AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            },
+        );
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+
+        let events = recording.drain();
+        assert_eq!(events[0].tenant_id, Some(tenant_id));
+        assert_eq!(events[1].tenant_id, None);
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(json["tenant_id"], tenant_id.to_string());
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_wait_idle_with_recording_sink() {
+        use crate::recording::RecordingSink;
+
+        let sink = RecordingSink::new();
+        let config = ClientConfig {
+            batch_size: 2,
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+
+        for _ in 0..4 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+        client.wait_idle(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(sink.handle().len(), 4);
+
+        // A partial batch waits for the flush interval
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        assert!(matches!(
+            client.wait_idle(Duration::from_millis(50)).await,
+            Err(AnalyticsError::FlushTimeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_last_successful_send() {
+        let client = AnalyticsClient::with_sink(CollectSink::default(), ClientConfig::default());
+        assert_eq!(client.last_successful_send(), None);
+
+        let before = Utc::now() - chrono::Duration::milliseconds(1);
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+
+        let last = client.last_successful_send().unwrap();
+        assert!(last >= before && last <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_serializes_without_sending() {
+        use crate::serializer::{BatchSerializer, JsonSerializer};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// JSON serializer counting the batches it encodes
+        #[derive(Default)]
+        struct CountingSerializer {
+            batches: AtomicUsize,
+        }
+
+        impl BatchSerializer for CountingSerializer {
+            fn content_type(&self) -> &str {
+                JsonSerializer.content_type()
+            }
+
+            fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>> {
+                self.batches.fetch_add(1, Ordering::SeqCst);
+                JsonSerializer.serialize(batch)
+            }
+        }
+
+        let serializer = Arc::new(CountingSerializer::default());
+        let config = ClientConfig {
+            dry_run: true,
+            serializer: serializer.clone(),
+            ..Default::default()
+        };
+        // Nothing listens here, so any real request would fail the flush
+        let client = AnalyticsClient::builder()
+            .url("http://127.0.0.1:1")
+            .config(config)
+            .build()
+            .unwrap();
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        client.flush().await.unwrap();
+        assert_eq!(serializer.batches.load(Ordering::SeqCst), 1);
+
+        // Backfills take the same path
+        let events = (0..3).map(|_| AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        let report = client.backfill(events, BackfillOptions::new()).await;
+        assert!(report.is_complete());
+        assert_eq!(serializer.batches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_client_flushes_remaining_events() {
+        let sink = CollectSink::default();
+        let config = ClientConfig {
+            flush_interval: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while sink.events().is_empty() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("remaining batch was not flushed");
+        assert_eq!(sink.events().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pipeline_stats_reported() {
+        let sink = CollectSink::default();
+        let config = ClientConfig {
+            batch_size: 1,
+            queue_capacity: Some(1),
+            stats_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+        let event = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track(event.clone());
+        client.track(event); // queue full, dropped
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        client.flush().await.unwrap();
+
+        let stats: Vec<_> = sink
+            .events()
+            .into_iter()
+            .filter_map(|e| match e.event {
+                AnalyticsEvent::PipelineStats {
+                    enqueued,
+                    dropped,
+                    sent,
+                    ..
+                } => Some((enqueued, dropped, sent)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stats, [(1, 1, 1)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_keeps_events_until_resumed() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .batch_size(2)
+            .build()
+            .unwrap();
+
+        client.pause();
+        for _ in 0..5 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+        assert!(matches!(client.flush().await, Err(AnalyticsError::Paused)));
+        let event = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+        assert!(matches!(
+            client.track_and_flush(event).await,
+            Err(AnalyticsError::Paused)
+        ));
+        tokio::time::sleep(crate::config::DEFAULT_FLUSH_INTERVAL * 3).await;
+        assert!(sink.batches().is_empty());
+        assert_eq!(client.metrics().buffered_events, 6);
+
+        client.resume().await.unwrap();
+        let sizes: Vec<usize> = sink.batches().iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 2, 2]);
+        assert!(!client.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_zero_batch_size_sends_events_one_by_one() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::with_sink(
+            sink.clone(),
+            ClientConfig {
+                batch_size: 0,
+                ..ClientConfig::default()
+            },
+        );
+
+        client.pause();
+        for _ in 0..3 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+        }
+        tokio::time::timeout(Duration::from_secs(3), client.resume())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let sizes: Vec<usize> = sink.batches().iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![1, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_paused_backlog_is_bounded_by_queue_capacity() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .queue_capacity(3)
+            .build()
+            .unwrap();
+
+        client.pause();
+        for _ in 0..5 {
+            client.track(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            });
+            tokio::task::yield_now().await;
+        }
+        client.resume().await.unwrap();
+
+        assert_eq!(sink.events().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_filter_drops_rejected_events() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .filter(|event| match event {
+                AnalyticsEvent::ApiRequest { status_code, .. } => *status_code >= 300,
+                _ => true,
+            })
+            .build()
+            .unwrap();
+        let request = |status_code| AnalyticsEvent::ApiRequest {
+            service: "api".to_string(),
+            user_id: None,
+            endpoint: "/projects".to_string(),
+            method: "GET".to_string(),
+            status_code,
+            duration_ms: DurationMs::from_std(Duration::from_millis(5)),
+        };
+
+        assert!(client.try_track(request(200)).is_ok());
+        client.track(request(404));
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0].event,
+            AnalyticsEvent::ApiRequest {
+                status_code: 404,
+                ..
+            }
+        ));
+        assert_eq!(client.metrics().filtered_events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_id_policy_strips_user_id() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .user_id_policy(UserIdPolicy::allow(["application_error"]))
+            .build()
+            .unwrap();
+        let user_id = Uuid::new_v4();
+
+        // The scope would fill in the user of every event that can carry one
+        let scoped = client.scoped(ScopeContext {
+            user_id: Some(user_id),
+            ..Default::default()
+        });
+        scoped.track(AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs::from_std(Duration::from_millis(3)),
+            rows_affected: Some(1),
+            statement_fingerprint: None,
+            is_slow: None,
+        });
+        scoped.track(AnalyticsEvent::ApiRequest {
+            service: "api".to_string(),
+            endpoint: "/projects".to_string(),
+            method: "GET".to_string(),
+            status_code: 200,
+            duration_ms: DurationMs::from_std(Duration::from_millis(5)),
+            user_id: Some(user_id),
+        });
+        scoped.track(AnalyticsEvent::ApplicationError {
+            service: "api".to_string(),
+            error_type: "timeout".to_string(),
+            error_message: "upstream timed out".to_string(),
+            user_id: None,
+            context: None,
+            context_ref: None,
+        });
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(events.len(), 3);
+        for event in &events[..2] {
+            assert_eq!(event.event.user_id(), None);
+            let json = serde_json::to_string(event).unwrap();
+            assert!(!json.contains(&user_id.to_string()), "{}", json);
+        }
+        assert_eq!(events[2].event.user_id(), Some(user_id));
+        assert_eq!(client.metrics().stripped_user_ids, 1);
+    }
+
+    #[tokio::test]
+    async fn test_seeded_sampling_is_reproducible() {
+        let users: Vec<_> = (0..100).map(|_| Uuid::new_v4()).collect();
+        let run = || async {
+            let sink = CollectSink::default();
+            let client = AnalyticsClient::builder()
+                .sink(sink.clone())
+                .sampling(Sampling::new(SampleKey::UserId, 0.3).seed(395))
+                .build()
+                .unwrap();
+            for &user_id in &users {
+                client.track(AnalyticsEvent::ProjectUpdated {
+                    project_id: Uuid::nil(),
+                    user_id,
+                });
+            }
+            client.flush().await.unwrap();
+            let kept: Vec<_> = sink
+                .events()
+                .iter()
+                .filter_map(|event| event.event.user_id())
+                .collect();
+            (kept, client.metrics().sampled_out_events)
+        };
+
+        let (first, first_dropped) = run().await;
+        let (second, second_dropped) = run().await;
+        assert_eq!(first, second);
+        assert_eq!(first_dropped, second_dropped);
+        assert!(!first.is_empty() && first.len() < users.len());
+    }
+
+    #[tokio::test]
+    async fn test_sampling_keeps_users_whole() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .sampling(Sampling::new(SampleKey::UserId, 0.5))
+            .build()
+            .unwrap();
+        let users: Vec<_> = (0..40).map(|_| Uuid::new_v4()).collect();
+
+        for _ in 0..3 {
+            for &user_id in &users {
+                client.track(AnalyticsEvent::ProjectUpdated {
+                    project_id: Uuid::new_v4(),
+                    user_id,
+                });
+            }
+        }
+        // No user_id to sample on
+        client.track(AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs::from_std(Duration::from_millis(3)),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow: None,
+        });
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        let kept: Vec<_> = users
+            .iter()
+            .map(|user_id| {
+                events
+                    .iter()
+                    .filter(|event| event.event.user_id() == Some(*user_id))
+                    .count()
+            })
+            .collect();
+        assert!(
+            kept.iter().all(|&count| count == 0 || count == 3),
+            "{:?}",
+            kept
+        );
+        let kept_users = kept.iter().filter(|&&count| count == 3).count();
+        assert!(0 < kept_users && kept_users < users.len());
+        assert!(events
+            .iter()
+            .any(|event| event.event.event_type() == "database_query"));
+        assert_eq!(
+            client.metrics().sampled_out_events,
+            3 * (users.len() - kept_users) as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seed_selects_another_sample() {
+        let users: Vec<_> = (0..200).map(|_| Uuid::new_v4()).collect();
+        let recorded = |sampling: Sampling| {
+            let users = users.clone();
+            async move {
+                let sink = CollectSink::default();
+                let client = AnalyticsClient::builder()
+                    .sink(sink.clone())
+                    .sampling(sampling)
+                    .build()
+                    .unwrap();
+                for &user_id in &users {
+                    client.track(AnalyticsEvent::ProjectUpdated {
+                        project_id: Uuid::new_v4(),
+                        user_id,
+                    });
+                }
+                client.flush().await.unwrap();
+                let mut kept: Vec<_> = sink
+                    .events()
+                    .iter()
+                    .filter_map(|event| event.event.user_id())
+                    .collect();
+                kept.sort();
+                kept
+            }
+        };
+
+        let seeded = recorded(Sampling::new(SampleKey::UserId, 0.5).seed(7)).await;
+        assert!(!seeded.is_empty() && seeded.len() < users.len());
+        assert_eq!(
+            recorded(Sampling::new(SampleKey::UserId, 0.5).seed(7)).await,
+            seeded
+        );
+        assert_ne!(
+            recorded(Sampling::new(SampleKey::UserId, 0.5)).await,
+            seeded
+        );
+        assert_ne!(
+            recorded(Sampling::new(SampleKey::UserId, 0.5).seed(8)).await,
+            seeded
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backfill_reports_chunks() {
+        use std::sync::Mutex;
+
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .batch_size(10)
+            .max_concurrent_sends(3)
+            .filter(|event| event.event_type() != "database_query")
+            .build()
+            .unwrap();
+        let mut events: Vec<_> = (0..95)
+            .map(|_| AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            })
+            .collect();
+        events.push(AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs::from_std(Duration::from_millis(3)),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow: None,
+        });
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let seen = progress.clone();
+        let options = BackfillOptions::new()
+            .on_progress(move |progress| seen.lock().unwrap().push(progress.events_sent));
+
+        let report = client.backfill(events, options).await;
+
+        assert!(report.is_complete());
+        let chunks: Vec<_> = report.chunks.iter().map(|c| (c.index, c.events)).collect();
+        assert_eq!(
+            chunks,
+            (0..10)
+                .map(|i| (i, if i < 9 { 10 } else { 5 }))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(report.progress.events_sent, 95);
+        assert_eq!(report.progress.events_skipped, 1);
+        assert_eq!(sink.events().len(), 95);
+        assert!(sink.batches().iter().all(|batch| batch.len() <= 10));
+
+        let progress = progress.lock().unwrap();
+        assert_eq!(progress.len(), 10);
+        assert_eq!(progress.last(), Some(&95));
+    }
+
+    #[tokio::test]
+    async fn test_backfill_reports_failed_chunks() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Fails the second batch it receives
+        struct FailSecondSink(AtomicUsize);
+
+        #[async_trait::async_trait]
+        impl EventSink for FailSecondSink {
+            async fn send(&self, _: &[EnrichedEvent]) -> Result<()> {
+                match self.0.fetch_add(1, Ordering::SeqCst) {
+                    1 => Err(AnalyticsError::Http { status: 503 }),
+                    _ => Ok(()),
+                }
+            }
+        }
+
+        let client = AnalyticsClient::builder()
+            .sink(FailSecondSink(AtomicUsize::new(0)))
+            .batch_size(4)
+            .max_concurrent_sends(1)
+            .build()
+            .unwrap();
+        let events = (0..10).map(|_| AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+
+        let report = client.backfill(events, BackfillOptions::new()).await;
+
+        assert!(!report.is_complete());
+        assert_eq!(report.progress.chunks_sent, 2);
+        assert_eq!(report.progress.events_sent, 6);
+        assert_eq!(report.progress.events_failed, 4);
+        let errors: Vec<_> = report.errors().map(|(index, _)| index).collect();
+        assert_eq!(errors, [1]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_threshold_flags_queries() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .slow_query_threshold(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let query = |duration_ms| AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs(duration_ms),
+            rows_affected: None,
+            statement_fingerprint: Some(crate::sql_fingerprint("SELECT 1")),
+            is_slow: None,
+        };
+
+        client.track(query(20));
+        client.track(query(250));
+        client.flush().await.unwrap();
+
+        let flags: Vec<_> = sink
+            .events()
+            .into_iter()
+            .map(|event| match event.event {
+                AnalyticsEvent::DatabaseQuery { is_slow, .. } => is_slow,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(flags, [Some(false), Some(true)]);
+    }
+
+    #[tokio::test]
+    async fn test_would_record() {
+        let client = AnalyticsClient::builder()
+            .sink(CollectSink::default())
+            .sampling(Sampling::new(SampleKey::UserId, 0.0))
+            .filter(|event| event.event_type() != "database_query")
+            .build()
+            .unwrap();
+        let project = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+        let query = AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs::from_std(Duration::from_millis(3)),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow: None,
+        };
+        let login = AnalyticsEvent::AuthLoginAttempt {
+            user_id: None,
+            email: "test@example.com".to_string(),
+            success: true,
+            error: None,
+        };
+
+        assert!(!client.would_record(&project));
+        assert!(!client.would_record(&query));
+        assert!(client.would_record(&login));
+        let metrics = client.metrics();
+        assert_eq!(metrics.sampled_out_events, 0);
+        assert_eq!(metrics.filtered_events, 0);
+
+        assert!(!AnalyticsClient::noop().would_record(&login));
+    }
+
+    #[tokio::test]
+    async fn test_track_bulk_waits_for_room() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .queue_capacity(10)
+            .batch_size(100)
+            .sampling(Sampling::new(SampleKey::UserId, 0.5))
+            .build()
+            .unwrap();
+        let events = || {
+            (0..5_000).map(|_| AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            })
+        };
+
+        let options = BulkOptions::new().bypass_sampling(true);
+        assert_eq!(
+            client.track_bulk_with(events(), options).await.unwrap(),
+            5_000
+        );
+        client.flush().await.unwrap();
+
+        let batches = sink.batches();
+        assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 5_000);
+        assert!(batches.iter().all(|batch| batch.len() <= 100));
+        let sequences: Vec<u64> = sink.events().iter().map(|e| e.sequence).collect();
+        assert!(sequences.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(client.metrics().sampled_out_events, 0);
+
+        // Sampled like `track` by default
+        let enqueued = client.track_bulk(events()).await.unwrap();
+        client.flush().await.unwrap();
+        assert!((2_000..3_000).contains(&enqueued), "{} enqueued", enqueued);
+        assert_eq!(sink.events().len(), 5_000 + enqueued);
+        assert_eq!(
+            client.metrics().sampled_out_events,
+            (5_000 - enqueued) as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_event_age_discards_stale_events() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .max_event_age(Duration::from_secs(3600))
+            .build()
+            .unwrap();
+        let event = AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track_at(Utc::now() - chrono::Duration::hours(2), event.clone());
+        client.track(event);
+        client.flush().await.unwrap();
+
+        let sent = sink.events();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].timestamp > Utc::now() - chrono::Duration::minutes(1));
+        assert_eq!(client.metrics().dropped_stale_events, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_track_and_flush_sends_immediately() {
+        let sink = CollectSink::default();
+        let config = ClientConfig {
+            flush_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+        let started = tokio::time::Instant::now();
+
+        client
+            .track_and_flush(AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(sink.events().len(), 1);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_timer_only_runs_with_pending_events() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Sink counting `send` calls, including empty batches
+        #[derive(Default)]
+        struct CountingSink {
+            sends: AtomicUsize,
+        }
+
+        #[async_trait::async_trait]
+        impl EventSink for CountingSink {
+            async fn send(&self, _batch: &[EnrichedEvent]) -> Result<()> {
+                self.sends.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let sink = Arc::new(CountingSink::default());
+        let config = ClientConfig {
+            flush_interval: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(sink.sends.load(Ordering::SeqCst), 0);
+
+        // The first event arms the timer, the flush disarms it again
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        tokio::time::sleep(Duration::from_secs(11)).await;
+        assert_eq!(sink.sends.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(sink.sends.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_report_in_flight_send() {
+        use tokio::sync::Notify;
+
+        /// Sink that blocks until released
+        #[derive(Default)]
+        struct GateSink {
+            entered: Notify,
+            release: Notify,
+        }
+
+        #[async_trait::async_trait]
+        impl EventSink for GateSink {
+            async fn send(&self, _batch: &[EnrichedEvent]) -> Result<()> {
+                self.entered.notify_one();
+                self.release.notified().await;
+                Ok(())
+            }
+        }
+
+        let sink = Arc::new(GateSink::default());
+        let config = ClientConfig {
+            batch_size: 1,
+            ..Default::default()
+        };
+        let client = AnalyticsClient::with_sink(sink.clone(), config);
+        assert_eq!(client.metrics().in_flight_sends, 0);
+
+        client.track(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        sink.entered.notified().await;
+        assert_eq!(client.metrics().in_flight_sends, 1);
+
+        sink.release.notify_one();
+        client.flush().await.unwrap();
+        assert_eq!(client.metrics().in_flight_sends, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_flushes_and_closes() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::with_sink(sink.clone(), ClientConfig::default());
+        let event = AnalyticsEvent::ProjectDeleted {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track(event.clone());
+        let report = client.shutdown(Duration::from_secs(1)).await.unwrap();
+
+        assert_eq!(sink.events().len(), 1);
+        assert_eq!((report.flushed, report.dropped), (1, 0));
+        assert!(matches!(
+            client.try_track(event),
+            Err(AnalyticsError::ChannelClosed)
+        ));
+    }
+
+    /// Sink accepting the first batch and failing every later one
+    #[derive(Default)]
+    struct FailAfterFirstSink {
+        sends: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EventSink for FailAfterFirstSink {
+        async fn send(&self, _batch: &[EnrichedEvent]) -> Result<()> {
+            match self.sends.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => Ok(()),
+                _ => Err(AnalyticsError::Http { status: 503 }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_outcomes() {
+        use futures_util::StreamExt;
+
+        let client =
+            AnalyticsClient::with_sink(FailAfterFirstSink::default(), ClientConfig::default());
+        let mut outcomes = Box::pin(client.subscribe_outcomes());
+        let event = || AnalyticsEvent::ProjectDeleted {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track(event());
+        client.track(event());
+        client.flush().await.unwrap();
+        client.track(event());
+        assert!(client.flush().await.is_err());
+
+        let first = outcomes.next().await.unwrap();
+        assert_eq!((first.count, first.success, first.status), (2, true, None));
+        let second = outcomes.next().await.unwrap();
+        assert_eq!(
+            (second.count, second.success, second.status),
+            (1, false, Some(503))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_report_counts_failed_final_batch() {
+        let client =
+            AnalyticsClient::with_sink(FailAfterFirstSink::default(), ClientConfig::default());
+        let event = AnalyticsEvent::ProjectDeleted {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        client.track(event.clone());
+        client.track(event.clone());
+        client.flush().await.unwrap();
+        for _ in 0..3 {
+            client.track(event.clone());
+        }
+        let report = client.shutdown(Duration::from_secs(1)).await.unwrap();
+
+        assert_eq!(report.flushed, 2);
+        assert_eq!(report.dropped, 3);
+        assert_eq!(report.failed_batches, 1);
+    }
+
+    #[cfg(feature = "attachments")]
+    #[tokio::test]
+    async fn test_track_with_attachment_caps_size() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .max_attachment_bytes(16)
+            .build()
+            .unwrap();
+        let event = AnalyticsEvent::ApplicationError {
+            service: "cocoon".to_string(),
+            error_type: "crash".to_string(),
+            error_message: "segfault".to_string(),
+            user_id: None,
+            context: None,
+            context_ref: None,
+        };
+
+        client.track_with_attachment(event.clone(), "minidump.dmp", vec![1; 16]);
+        client.track_with_attachment(event, "screenshot.png", vec![2; 17]);
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(
+            events[0].attachment,
+            Some(("minidump.dmp".to_string(), vec![1; 16]))
+        );
+        assert_eq!(events[1].attachment, None);
+    }
 }
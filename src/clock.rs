@@ -0,0 +1,144 @@
+use crate::throttle::{LogKind, LogThrottle};
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Nanoseconds on a monotonic clock shared by the whole process
+///
+/// Counts from the first call in the process, so values are only
+/// comparable within one process. Unlike the wall clock, it never goes
+/// backwards, e.g. when NTP steps the system time.
+pub(crate) fn monotonic_ns() -> u64 {
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    let elapsed = ORIGIN.get_or_init(Instant::now).elapsed();
+    // Saturates after 584 years of uptime
+    u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Sentinel meaning "no offset measured yet"
+const UNMEASURED: i64 = i64::MIN;
+
+/// Clock offset between this host and the ingestion service
+///
+/// Measured from the `Date` header of batch responses. The offset is
+/// `server_time - local_time`, so a positive value means the local clock
+/// is behind the server.
+#[derive(Debug)]
+pub(crate) struct ClockSkew {
+    offset_ms: AtomicI64,
+}
+
+impl ClockSkew {
+    pub(crate) fn new() -> Self {
+        Self {
+            offset_ms: AtomicI64::new(UNMEASURED),
+        }
+    }
+
+    /// Last measured offset, if any response carried a usable `Date` header
+    pub(crate) fn offset(&self) -> Option<chrono::Duration> {
+        match self.offset_ms.load(Ordering::Relaxed) {
+            UNMEASURED => None,
+            ms => Some(chrono::Duration::milliseconds(ms)),
+        }
+    }
+
+    /// Last measured offset if it exceeds `threshold`
+    ///
+    /// Smaller offsets are within what the `Date` header's one second
+    /// resolution and the request latency can explain, so they are not
+    /// worth correcting.
+    pub(crate) fn correction(&self, threshold: Duration) -> Option<chrono::Duration> {
+        self.offset().filter(|offset| exceeds(*offset, threshold))
+    }
+
+    /// Record a new measurement, warning through `log` if it exceeds
+    /// `threshold`
+    pub(crate) fn record(
+        &self,
+        server_time: DateTime<Utc>,
+        local_time: DateTime<Utc>,
+        threshold: Duration,
+        log: &LogThrottle,
+    ) -> chrono::Duration {
+        let offset = server_time - local_time;
+        self.offset_ms
+            .store(offset.num_milliseconds(), Ordering::Relaxed);
+
+        // Without the exact offset, so repeats coalesce under `log_throttle`
+        if exceeds(offset, threshold) {
+            log.log(
+                LogKind::Warning,
+                format!(
+                    "Clock skew detected: local clock is more than {:?} {} the analytics service",
+                    threshold,
+                    if offset.num_milliseconds() > 0 {
+                        "behind"
+                    } else {
+                        "ahead of"
+                    }
+                ),
+            );
+        }
+
+        offset
+    }
+}
+
+/// Whether `offset` is larger than `threshold` either way
+fn exceeds(offset: chrono::Duration, threshold: Duration) -> bool {
+    offset.abs().to_std().unwrap_or_default() > threshold
+}
+
+/// Parse an HTTP `Date` header value (IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`)
+pub(crate) fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_http_date() {
+        let date = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(date, Utc.with_ymd_and_hms(1994, 11, 6, 8, 49, 37).unwrap());
+        assert!(parse_http_date("not a date").is_none());
+    }
+
+    #[test]
+    fn test_record_offset() {
+        let skew = ClockSkew::new();
+        assert!(skew.offset().is_none());
+
+        let log = LogThrottle::new(&crate::config::ClientConfig::default());
+        let threshold = Duration::from_secs(60);
+        let local = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+
+        skew.record(
+            local + chrono::Duration::seconds(90),
+            local,
+            threshold,
+            &log,
+        );
+        assert_eq!(skew.offset(), Some(chrono::Duration::seconds(90)));
+        assert_eq!(
+            skew.correction(threshold),
+            Some(chrono::Duration::seconds(90))
+        );
+
+        // Measurement noise is not corrected
+        skew.record(
+            local - chrono::Duration::milliseconds(800),
+            local,
+            threshold,
+            &log,
+        );
+        assert_eq!(skew.offset(), Some(chrono::Duration::milliseconds(-800)));
+        assert_eq!(skew.correction(threshold), None);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::events::{AnalyticsEvent, EnrichedEvent};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Login attempts for one email within the current window
+struct PendingLogins {
+    first: EnrichedEvent,
+    opened: Instant,
+    attempts: u32,
+    failures: u32,
+    user_id: Option<uuid::Uuid>,
+    last_error: Option<String>,
+    last_attempt_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Merges repeated `AuthLoginAttempt` events per email
+pub(crate) struct LoginCompactor {
+    window: Option<Duration>,
+    pending: HashMap<String, PendingLogins>,
+}
+
+impl LoginCompactor {
+    pub(crate) fn new(window: Option<Duration>) -> Self {
+        Self {
+            window,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Hold back a login attempt until its window closes
+    ///
+    /// Returns the event unchanged if compaction is disabled or it isn't a
+    /// login attempt.
+    pub(crate) fn record(&mut self, event: EnrichedEvent) -> Option<EnrichedEvent> {
+        if self.window.is_none() {
+            return Some(event);
+        }
+        let AnalyticsEvent::AuthLoginAttempt {
+            user_id,
+            email,
+            success,
+            error,
+        } = &event.event
+        else {
+            return Some(event);
+        };
+
+        if let Some(pending) = self.pending.get_mut(email) {
+            pending.attempts += 1;
+            if !success {
+                pending.failures += 1;
+                pending.last_error = error.clone().or(pending.last_error.take());
+            }
+            pending.user_id = user_id.or(pending.user_id);
+            pending.last_attempt_at = event.timestamp;
+            return None;
+        }
+
+        let email = email.clone();
+        let pending = PendingLogins {
+            opened: Instant::now(),
+            attempts: 1,
+            failures: u32::from(!success),
+            user_id: *user_id,
+            last_error: error.clone(),
+            last_attempt_at: event.timestamp,
+            first: event,
+        };
+        self.pending.insert(email, pending);
+        None
+    }
+
+    /// Emit attempts whose window has closed by `now`
+    pub(crate) fn drain_expired(&mut self, now: Instant) -> Vec<EnrichedEvent> {
+        let Some(window) = self.window else {
+            return Vec::new();
+        };
+
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.opened) >= window)
+            .map(|(email, _)| email.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|email| self.pending.remove_entry(&email))
+            .map(|(email, pending)| pending.into_event(email))
+            .collect()
+    }
+
+    /// Whether no attempts are held back
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Emit all held back attempts
+    pub(crate) fn drain(&mut self) -> Vec<EnrichedEvent> {
+        self.pending
+            .drain()
+            .map(|(email, pending)| pending.into_event(email))
+            .collect()
+    }
+}
+
+impl PendingLogins {
+    fn into_event(self, email: String) -> EnrichedEvent {
+        if self.attempts == 1 {
+            return self.first;
+        }
+
+        let mut event = self.first;
+        let first_attempt_at = event.timestamp;
+        event.event = AnalyticsEvent::AuthLoginAttemptsCompacted {
+            user_id: self.user_id,
+            email,
+            attempt_count: self.attempts,
+            failure_count: self.failures,
+            last_error: self.last_error,
+            first_attempt_at,
+            last_attempt_at: self.last_attempt_at,
+        };
+        event.schema_version = event.event.schema_version();
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(email: &str, success: bool) -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::AuthLoginAttempt {
+            user_id: None,
+            email: email.to_string(),
+            success,
+            error: (!success).then(|| "invalid code".to_string()),
+        })
+    }
+
+    #[test]
+    fn test_disabled_passes_through() {
+        let mut compactor = LoginCompactor::new(None);
+        assert!(compactor.record(attempt("a@example.com", false)).is_some());
+        assert!(compactor.drain().is_empty());
+    }
+
+    #[test]
+    fn test_merges_attempts_per_email() {
+        let mut compactor = LoginCompactor::new(Some(Duration::from_secs(60)));
+
+        for _ in 0..4 {
+            assert!(compactor.record(attempt("a@example.com", false)).is_none());
+        }
+        assert!(compactor.record(attempt("a@example.com", true)).is_none());
+        assert!(compactor.record(attempt("b@example.com", true)).is_none());
+
+        // Nothing has expired yet
+        assert!(compactor.drain_expired(Instant::now()).is_empty());
+
+        let later = Instant::now() + Duration::from_secs(61);
+        let mut events = compactor.drain_expired(later);
+        events.sort_by_key(|e| e.event.event_type());
+        assert_eq!(events.len(), 2);
+
+        assert!(matches!(
+            &events[0].event,
+            AnalyticsEvent::AuthLoginAttempt { email, .. } if email == "b@example.com"
+        ));
+        let AnalyticsEvent::AuthLoginAttemptsCompacted {
+            attempt_count,
+            failure_count,
+            last_error,
+            ..
+        } = &events[1].event
+        else {
+            panic!("unexpected event: {:?}", events[1].event);
+        };
+        assert_eq!(*attempt_count, 5);
+        assert_eq!(*failure_count, 4);
+        assert_eq!(last_error.as_deref(), Some("invalid code"));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::Result;
+
+/// Content encoding applied to batch request bodies
+///
+/// Set as [`ClientConfig::compression`](crate::ClientConfig::compression).
+/// The ingestion service must accept the matching `Content-Encoding` header
+/// and decode the body before parsing it; a service without support answers
+/// with an error (typically 415), which fails the batch. Only the HTTP sink
+/// compresses, and requests carrying attachments are sent uncompressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Send bodies as serialized (the default)
+    #[default]
+    None,
+
+    /// Zstandard at `level` (1-22; 1-3 keep CPU cost low on edge devices).
+    /// Sent with `Content-Encoding: zstd`. Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// Value of the `Content-Encoding` header, `None` if uncompressed
+    pub(crate) fn content_encoding(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { .. } => Some("zstd"),
+        }
+    }
+
+    /// Encode a serialized batch
+    pub(crate) fn compress(&self, body: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(body),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd { level } => Ok(zstd::encode_all(body.as_slice(), *level)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_none_passes_through() {
+        let body = br#"[{"type":"project_updated"}]"#.to_vec();
+        assert_eq!(Compression::None.compress(body.clone()).unwrap(), body);
+        assert_eq!(Compression::None.content_encoding(), None);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        use crate::events::{AnalyticsEvent, EnrichedEvent};
+        use crate::serializer::{BatchSerializer, JsonSerializer};
+
+        let batch: Vec<_> = (0..50)
+            .map(|_| {
+                EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+                    project_id: uuid::Uuid::new_v4(),
+                    user_id: uuid::Uuid::new_v4(),
+                })
+            })
+            .collect();
+        let body = JsonSerializer.serialize(&batch).unwrap();
+        let compression = Compression::Zstd { level: 3 };
+
+        let compressed = compression.compress(body.clone()).unwrap();
+        assert!(compressed.len() < body.len());
+        assert_eq!(zstd::decode_all(compressed.as_slice()).unwrap(), body);
+        assert_eq!(compression.content_encoding(), Some("zstd"));
+    }
+}
@@ -0,0 +1,667 @@
+use crate::aggregate::Aggregation;
+use crate::batch::{BatchPolicy, CountOrInterval};
+use crate::compression::Compression;
+use crate::environment::Environment;
+use crate::error::{AnalyticsError, Result};
+use crate::filter::EventFilter;
+use crate::middleware::EnrichMiddleware;
+use crate::normalize::ActionNormalizer;
+use crate::sampling::Sampling;
+use crate::sanitize::{ContextFilter, UserIdPolicy};
+use crate::serializer::{BatchSerializer, JsonSerializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default cap for free-form string fields (8 KiB)
+pub const DEFAULT_MAX_FIELD_LEN: usize = 8 * 1024;
+
+/// Default number of events per batch
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Default interval between periodic flushes
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default clock skew above which a warning is logged
+pub const DEFAULT_CLOCK_SKEW_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// Default limit of concurrent batch requests per client
+pub const DEFAULT_MAX_CONCURRENT_SENDS: usize = 4;
+
+/// Default size cap for event attachments (1 MiB)
+#[cfg(feature = "attachments")]
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 1024 * 1024;
+
+/// Default timeout for requests to the ingestion service
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default path of the batch endpoint, relative to the analytics URL
+pub const DEFAULT_BATCH_PATH: &str = "/events/batch";
+
+/// Default time an idle pooled connection is kept open (reqwest's default)
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Default body size below which batches are sent uncompressed (1 KiB)
+pub const DEFAULT_COMPRESS_MIN_BYTES: usize = 1024;
+
+/// Analytics client configuration
+///
+/// All options have sensible defaults, so only the ones that matter
+/// need to be overridden:
+///
+/// ```rust
+/// use lib_analytics_core::ClientConfig;
+///
+/// let config = ClientConfig {
+///     max_field_len: 4 * 1024,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct ClientConfig {
+    /// Maximum length (in bytes) of free-form string fields such as error
+    /// messages and `context` values. Longer values are truncated during
+    /// enrichment and suffixed with `"...(truncated)"`.
+    pub max_field_len: usize,
+
+    /// Top-level `context` keys removed (or kept) before sending, e.g.
+    /// `ContextFilter::deny(["password", "token", "authorization"])`.
+    /// Context is sent unchanged by default.
+    pub context_filter: ContextFilter,
+
+    /// Event types allowed to carry a `user_id`; it is removed from all
+    /// others before queueing, e.g. `UserIdPolicy::allow(["task_created"])`
+    /// keeps request and query telemetry unlinked from users. Removals are
+    /// counted in `MetricsSnapshot::stripped_user_ids`. Every type may carry
+    /// one by default (see [`UserIdPolicy`]).
+    pub user_id_policy: UserIdPolicy,
+
+    /// Drop events the filter rejects, e.g. all `DatabaseQuery` events in
+    /// production (see [`EventFilter`]). Evaluated first when an event is
+    /// tracked, before validation, so rejected events never count against
+    /// the queue. `None` (the default) tracks everything.
+    pub filter: Option<EventFilter>,
+
+    /// Keep only a share of events, decided by a hash of one of their
+    /// fields so whole sessions or users are kept or dropped together (see
+    /// [`Sampling`]). Applied right after `filter`; dropped events are
+    /// counted in `MetricsSnapshot::sampled_out_events`. `None` (the
+    /// default) keeps every event.
+    pub sampling: Option<Sampling>,
+
+    /// Maps provider-specific `IntegrationUsed` actions and `WebhookReceived`
+    /// event types to a [`CanonicalAction`](crate::CanonicalAction) stored in
+    /// `EnrichedEvent::canonical_action`. Defaults to the built-in GitHub and
+    /// Slack mappings (see [`ActionNormalizer`]).
+    pub action_normalizer: ActionNormalizer,
+
+    /// Custom enrichment steps run in order on every tracked event, after
+    /// the built-in steps configured above (`context_filter`,
+    /// `user_id_policy`, `slow_query_threshold`, `max_field_len` and
+    /// `action_normalizer`); see [`EnrichMiddleware`] for the full order and
+    /// cost. Empty by default.
+    pub enrich_middleware: Vec<Arc<dyn EnrichMiddleware>>,
+
+    /// `DatabaseQuery` events taking at least this long get `is_slow:
+    /// Some(true)`, faster ones `Some(false)`, unless the caller already set
+    /// `is_slow`. Together with `statement_fingerprint`, this feeds
+    /// slow-query dashboards. `None` (the default) leaves `is_slow` as
+    /// tracked.
+    pub slow_query_threshold: Option<Duration>,
+
+    /// Maximum number of events waiting to be sent. `None` (the default)
+    /// means unbounded; with a bound, events tracked while the queue is
+    /// full are dropped and `try_track` returns `AnalyticsError::QueueFull`.
+    pub queue_capacity: Option<usize>,
+
+    /// Number of buffered events that triggers an immediate send
+    pub batch_size: usize,
+
+    /// Maximum time a partially filled batch waits before it is sent,
+    /// counted from the first event entering it. No timer runs while
+    /// nothing is pending.
+    pub flush_interval: Duration,
+
+    /// Random spread applied to each `flush_interval`, which then lies
+    /// anywhere within `flush_interval ± flush_jitter`. Keeps a fleet of
+    /// clients started together (e.g. by a deploy) from flushing in
+    /// lockstep. 10-20% of `flush_interval` is usually enough; it must stay
+    /// below `flush_interval`. Zero (the default) disables jitter.
+    pub flush_jitter: Duration,
+
+    /// Decides when a batch is sent (see [`BatchPolicy`]). `None` (the
+    /// default) sends at `batch_size` events or `flush_interval` (randomized
+    /// by `flush_jitter`) after the first one, as
+    /// [`CountOrInterval`](crate::CountOrInterval) does. `batch_size` still
+    /// caps the events per request with a custom policy.
+    pub batch_policy: Option<Arc<dyn BatchPolicy>>,
+
+    /// Hostname attached to events. Falls back to the `HOSTNAME` environment
+    /// variable when `None`.
+    pub hostname: Option<String>,
+
+    /// Environment attached to events. Falls back to the `ENVIRONMENT`
+    /// environment variable when `None`, parsed leniently (see
+    /// [`Environment`]).
+    pub environment: Option<Environment>,
+
+    /// Bearer token sent in the `Authorization` header of every batch
+    pub auth_token: Option<String>,
+
+    /// Shared secret for signing request bodies. When set, every batch
+    /// carries `X-Signature: sha256=<hex>`, the HMAC-SHA256 of exactly the
+    /// body bytes sent (after compression), so the ingestion service can
+    /// verify both the sender and the body's integrity. Batches with
+    /// attachments sign the `events` part. `None` (the default) disables
+    /// signing.
+    pub signing_secret: Option<Vec<u8>>,
+
+    /// How long idle connections to the ingestion service are kept for reuse.
+    /// `None` keeps them indefinitely. Raise it when flushes are infrequent
+    /// to avoid reconnecting for every batch.
+    pub pool_idle_timeout: Option<Duration>,
+
+    /// Maximum idle connections kept per host (unlimited by default)
+    pub pool_max_idle_per_host: usize,
+
+    /// Speak HTTP/2 without negotiation, multiplexing concurrent sends over
+    /// one connection. Only enable it when the ingestion service (and any
+    /// proxy in front of it) accepts HTTP/2 prior knowledge; HTTP/1-only
+    /// servers reject every request. Requires the `http2` feature, which
+    /// also lets TLS connections negotiate HTTP/2 via ALPN.
+    #[cfg(feature = "http2")]
+    pub http2_prior_knowledge: bool,
+
+    /// Proxy for all requests to the ingestion service, e.g.
+    /// `reqwest::Proxy::all("http://proxy.corp:3128")` or, with the `socks`
+    /// feature, `reqwest::Proxy::all("socks5://proxy.corp:1080")`.
+    ///
+    /// When `None`, the `HTTP_PROXY`/`HTTPS_PROXY`/`ALL_PROXY` environment
+    /// variables are honored (with `NO_PROXY` exclusions). Setting a proxy
+    /// here replaces them; add `.no_proxy(reqwest::NoProxy::from_env())` to
+    /// keep the `NO_PROXY` exclusions.
+    pub proxy: Option<reqwest::Proxy>,
+
+    /// Send requests over this Unix domain socket instead of TCP, e.g. to
+    /// an on-device collector. The analytics URL still provides the scheme,
+    /// `Host` header and path prefix (e.g. `http://localhost`), but its host
+    /// is not resolved, and `proxy` is ignored. Connections are opened as
+    /// needed, so a collector that restarts and recreates the socket is
+    /// reached again by the next send. Unix only; requires the `uds` feature.
+    #[cfg(all(unix, feature = "uds"))]
+    pub uds_path: Option<std::path::PathBuf>,
+
+    /// Wire format of batch request bodies (JSON array by default). The
+    /// ingestion service must understand the configured format.
+    pub serializer: Arc<dyn BatchSerializer>,
+
+    /// Path batches are POSTed to, appended to the analytics URL. Any path
+    /// prefix on the URL (e.g. `https://gateway/api/v2/analytics`) is kept,
+    /// and slashes at the join are normalized.
+    pub batch_path: String,
+
+    /// Content encoding of batch request bodies (see [`Compression`]).
+    /// Uncompressed by default.
+    pub compression: Compression,
+
+    /// Serialized batches smaller than this are sent uncompressed, without
+    /// a `Content-Encoding` header, even when `compression` is set.
+    /// Compressing a body of a few events costs CPU and can make it larger.
+    /// Defaults to [`DEFAULT_COMPRESS_MIN_BYTES`]; 0 compresses every batch.
+    pub compress_min_bytes: usize,
+
+    /// Split each batch by [`EnrichedEvent::tenant_id`](crate::EnrichedEvent::tenant_id)
+    /// and send one request per tenant with an `X-Tenant-Id: <uuid>` header
+    /// (omitted for events without a tenant). Off by default: batches mix
+    /// tenants and no header is sent; the tenant id is always in the payload.
+    pub tenant_header: bool,
+
+    /// Maximum number of batches handed to the sink at once across the
+    /// client and its clones; further sends wait for a free slot. Values
+    /// below 1 are treated as 1.
+    pub max_concurrent_sends: usize,
+
+    /// Persist every event to an on-disk ring until the sink accepted it
+    /// (see [`DurableBuffer`](crate::DurableBuffer) for the tradeoffs). Off
+    /// (`None`) by default. Requires the `durable` feature.
+    #[cfg(feature = "durable")]
+    pub durable_buffer: Option<crate::DurableBuffer>,
+
+    /// Timeout for each request to the ingestion service (batch sends and
+    /// health checks)
+    pub request_timeout: Duration,
+
+    /// After this many batches failed in a row, print events to stdout as
+    /// JSON lines instead of sending them, e.g. on laptops and CI without an
+    /// ingestion service. A one-time notice is logged on the switch. While
+    /// in this mode the destination's health check is probed every 30
+    /// seconds and sending resumes once it succeeds. Printed events count as
+    /// sent. `None` (the default) keeps sending and logging every failure.
+    ///
+    /// This consecutive-failure count is the client's only circuit breaker:
+    /// every failed send (retryable or not) increments it, every successful
+    /// send resets it, and once tripped only a successful health check
+    /// resets it. The batch whose failure reaches the threshold still fails
+    /// as usual; printing starts with the next batch.
+    pub fallback_to_stdout_after: Option<u32>,
+
+    /// Discard events whose `timestamp` is older than this when their batch
+    /// is about to be sent, counting them in
+    /// [`MetricsSnapshot::dropped_stale_events`](crate::MetricsSnapshot::dropped_stale_events).
+    /// Keeps a backlog built up during an outage from flooding the backend
+    /// with stale data once it recovers. Unlike `queue_capacity`, which drops
+    /// new events when the queue is full, this drops old ones whatever the
+    /// queue size. Also applies to events tracked with an explicit
+    /// occurrence time. `None` (the default) sends events of any age.
+    pub max_event_age: Option<Duration>,
+
+    /// Clock skew (measured from the server's `Date` header) above which a
+    /// warning is logged
+    pub clock_skew_threshold: Duration,
+
+    /// Shift event timestamps by the measured clock offset once it exceeds
+    /// `clock_skew_threshold`. Only timestamps taken from this host's clock
+    /// when tracking are corrected, not explicit times (`track_at`) or
+    /// relayed events (`track_enriched`). Off by default; only useful on
+    /// hosts with unreliable clocks (e.g. edge Cocoons).
+    pub correct_clock_skew: bool,
+
+    /// Drop events that fail [`AnalyticsEvent::validate`](crate::AnalyticsEvent::validate)
+    /// instead of sending them. `try_track` reports the reason as
+    /// `AnalyticsError::Validation`.
+    pub reject_invalid: bool,
+
+    /// Serialize each batch with `serializer`, compress it as configured and
+    /// log it at `info` instead of sending it. Nothing reaches the sink, but
+    /// events are counted as sent. Applies to `backfill` too. For staging
+    /// and debugging; surfaces encoding bugs without hitting the ingestion
+    /// service. Binary bodies (e.g. protobuf) are logged as hex.
+    pub dry_run: bool,
+
+    /// Log dry-run batches pretty-printed instead of the exact serialized
+    /// body, when the serializer produces JSON or NDJSON
+    pub dry_run_pretty: bool,
+
+    /// Upload `ApplicationError` context larger than `blob_threshold` out of
+    /// band and send a `context_ref` instead (see [`BlobUploader`](crate::BlobUploader)).
+    /// `None` (the default) truncates large context inline. Requires the
+    /// `blob-upload` feature.
+    #[cfg(feature = "blob-upload")]
+    pub blob_uploader: Option<Arc<dyn crate::BlobUploader>>,
+
+    /// Serialized context size in bytes above which it is uploaded
+    /// (default [`DEFAULT_BLOB_THRESHOLD`](crate::DEFAULT_BLOB_THRESHOLD)).
+    /// Larger than `max_field_len` means contexts in between are truncated.
+    #[cfg(feature = "blob-upload")]
+    pub blob_threshold: usize,
+
+    /// Largest attachment in bytes kept by
+    /// [`track_with_attachment`](crate::AnalyticsClient::track_with_attachment)
+    /// (default [`DEFAULT_MAX_ATTACHMENT_BYTES`]). Larger attachments are
+    /// dropped and the event is sent without them. Requires the
+    /// `attachments` feature.
+    #[cfg(feature = "attachments")]
+    pub max_attachment_bytes: usize,
+
+    /// Event types sent as periodic per-key summaries instead of individual
+    /// events (see [`Aggregation`]). Empty by default.
+    pub aggregations: Vec<Aggregation>,
+
+    /// Merge repeated `AuthLoginAttempt` events for the same email within this
+    /// window into one `AuthLoginAttemptsCompacted` event. `None` (the
+    /// default) sends every attempt as-is. When enabled, login attempts are
+    /// held back until their window closes; a lone attempt is then sent
+    /// unchanged.
+    pub login_compaction_window: Option<Duration>,
+
+    /// Count `CocoonConnected` and `CocoonDisconnected` events and emit a
+    /// `CocoonChurn` summary once per window, checked on the flush cadence.
+    /// `None` (the default) disables the summaries.
+    pub churn_window: Option<Duration>,
+
+    /// Send only the `CocoonChurn` summaries, not the counted connect and
+    /// disconnect events themselves. Ignored without `churn_window`.
+    pub churn_suppress_raw: bool,
+
+    /// Emit a `PipelineStats` event with enqueued/sent/dropped/failed counts
+    /// at this interval, so the analytics backend can track the client's own
+    /// delivery health. Off (`None`) by default; intervals without any
+    /// activity are skipped.
+    pub stats_interval: Option<Duration>,
+
+    /// Log repeats of an identical drop or failure message at most once per
+    /// window: the first is logged, later ones are counted and reported with
+    /// the next one logged after the window ("... (suppressed N similar
+    /// messages)"), so an outage doesn't flood the logs with one warning per
+    /// batch. `None` (the default) logs every message.
+    pub log_throttle: Option<Duration>,
+
+    /// Level of messages about events dropped before being sent: queue full,
+    /// invalid events, stale events discarded by `max_event_age`. `DEBUG` by
+    /// default, as drops are counted in the metrics anyway.
+    pub drop_log_level: tracing::Level,
+
+    /// Level of messages about batches the sink failed to deliver. `WARN` by
+    /// default.
+    pub failure_log_level: tracing::Level,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            max_field_len: DEFAULT_MAX_FIELD_LEN,
+            context_filter: ContextFilter::KeepAll,
+            user_id_policy: UserIdPolicy::AllowAll,
+            filter: None,
+            sampling: None,
+            action_normalizer: ActionNormalizer::default(),
+            enrich_middleware: Vec::new(),
+            slow_query_threshold: None,
+            queue_capacity: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            flush_jitter: Duration::ZERO,
+            batch_policy: None,
+            hostname: None,
+            environment: None,
+            auth_token: None,
+            signing_secret: None,
+            proxy: None,
+            serializer: Arc::new(JsonSerializer),
+            batch_path: DEFAULT_BATCH_PATH.to_string(),
+            compression: Compression::None,
+            compress_min_bytes: DEFAULT_COMPRESS_MIN_BYTES,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            pool_max_idle_per_host: usize::MAX,
+            #[cfg(feature = "http2")]
+            http2_prior_knowledge: false,
+            #[cfg(all(unix, feature = "uds"))]
+            uds_path: None,
+            tenant_header: false,
+            max_concurrent_sends: DEFAULT_MAX_CONCURRENT_SENDS,
+            #[cfg(feature = "durable")]
+            durable_buffer: None,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            fallback_to_stdout_after: None,
+            max_event_age: None,
+            clock_skew_threshold: DEFAULT_CLOCK_SKEW_THRESHOLD,
+            correct_clock_skew: false,
+            reject_invalid: false,
+            dry_run: false,
+            #[cfg(feature = "blob-upload")]
+            blob_uploader: None,
+            #[cfg(feature = "blob-upload")]
+            blob_threshold: crate::blob::DEFAULT_BLOB_THRESHOLD,
+            #[cfg(feature = "attachments")]
+            max_attachment_bytes: DEFAULT_MAX_ATTACHMENT_BYTES,
+            dry_run_pretty: false,
+            aggregations: Vec::new(),
+            login_compaction_window: None,
+            churn_window: None,
+            churn_suppress_raw: false,
+            stats_interval: None,
+            log_throttle: None,
+            drop_log_level: tracing::Level::DEBUG,
+            failure_log_level: tracing::Level::WARN,
+        }
+    }
+}
+
+/// Secrets (`auth_token`, `signing_secret`) are printed as `"<redacted>"`
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ClientConfig");
+        debug
+            .field("max_field_len", &self.max_field_len)
+            .field("context_filter", &self.context_filter)
+            .field("user_id_policy", &self.user_id_policy)
+            .field("filter", &self.filter)
+            .field("sampling", &self.sampling)
+            .field("action_normalizer", &self.action_normalizer)
+            .field("enrich_middleware", &self.enrich_middleware)
+            .field("slow_query_threshold", &self.slow_query_threshold)
+            .field("queue_capacity", &self.queue_capacity)
+            .field("batch_size", &self.batch_size)
+            .field("flush_interval", &self.flush_interval)
+            .field("flush_jitter", &self.flush_jitter)
+            .field("batch_policy", &self.batch_policy)
+            .field("hostname", &self.hostname)
+            .field("environment", &self.environment)
+            .field(
+                "auth_token",
+                &self.auth_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field(
+                "signing_secret",
+                &self.signing_secret.as_ref().map(|_| "<redacted>"),
+            )
+            .field("pool_idle_timeout", &self.pool_idle_timeout)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host);
+        #[cfg(feature = "http2")]
+        debug.field("http2_prior_knowledge", &self.http2_prior_knowledge);
+        debug.field("proxy", &self.proxy);
+        #[cfg(all(unix, feature = "uds"))]
+        debug.field("uds_path", &self.uds_path);
+        debug
+            .field("serializer", &self.serializer)
+            .field("batch_path", &self.batch_path)
+            .field("compression", &self.compression)
+            .field("compress_min_bytes", &self.compress_min_bytes)
+            .field("tenant_header", &self.tenant_header)
+            .field("max_concurrent_sends", &self.max_concurrent_sends);
+        #[cfg(feature = "durable")]
+        debug.field("durable_buffer", &self.durable_buffer);
+        debug
+            .field("request_timeout", &self.request_timeout)
+            .field("fallback_to_stdout_after", &self.fallback_to_stdout_after)
+            .field("max_event_age", &self.max_event_age)
+            .field("clock_skew_threshold", &self.clock_skew_threshold)
+            .field("correct_clock_skew", &self.correct_clock_skew)
+            .field("reject_invalid", &self.reject_invalid)
+            .field("dry_run", &self.dry_run)
+            .field("dry_run_pretty", &self.dry_run_pretty);
+        #[cfg(feature = "blob-upload")]
+        debug.field("blob_uploader", &self.blob_uploader);
+        #[cfg(feature = "blob-upload")]
+        debug.field("blob_threshold", &self.blob_threshold);
+        #[cfg(feature = "attachments")]
+        debug.field("max_attachment_bytes", &self.max_attachment_bytes);
+        debug
+            .field("aggregations", &self.aggregations)
+            .field("login_compaction_window", &self.login_compaction_window)
+            .field("churn_window", &self.churn_window)
+            .field("churn_suppress_raw", &self.churn_suppress_raw)
+            .field("stats_interval", &self.stats_interval)
+            .field("log_throttle", &self.log_throttle)
+            .field("drop_log_level", &self.drop_log_level)
+            .field("failure_log_level", &self.failure_log_level);
+        debug.finish()
+    }
+}
+
+impl ClientConfig {
+    /// Compression of a serialized body of `len` bytes: none below
+    /// `compress_min_bytes`
+    pub(crate) fn compression_for(&self, len: usize) -> Compression {
+        if len < self.compress_min_bytes {
+            Compression::None
+        } else {
+            self.compression
+        }
+    }
+
+    /// Build a configuration from `ANALYTICS_*` variables returned by `lookup`
+    ///
+    /// Unset variables keep their defaults; malformed numeric values are errors.
+    pub(crate) fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut config = Self {
+            auth_token: lookup("ANALYTICS_TOKEN").filter(|token| !token.is_empty()),
+            ..Default::default()
+        };
+
+        if let Some(batch_size) = parse_positive::<usize>(&lookup, "ANALYTICS_BATCH_SIZE")? {
+            config.batch_size = batch_size;
+        }
+        if let Some(secs) = parse_positive::<u64>(&lookup, "ANALYTICS_FLUSH_INTERVAL_SECS")? {
+            config.flush_interval = Duration::from_secs(secs);
+        }
+
+        Ok(config)
+    }
+
+    /// The configured `batch_policy`, or the count and interval policy
+    pub(crate) fn batch_policy(&self) -> Arc<dyn BatchPolicy> {
+        match &self.batch_policy {
+            Some(policy) => policy.clone(),
+            None => Arc::new(
+                CountOrInterval::new(self.batch_size, self.flush_interval)
+                    .jitter(self.flush_jitter),
+            ),
+        }
+    }
+
+    /// Check options that would leave the client unable to send
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.batch_size == 0 {
+            return Err(AnalyticsError::Config(
+                "batch_size must be at least 1".to_string(),
+            ));
+        }
+        if self.flush_interval.is_zero() {
+            return Err(AnalyticsError::Config(
+                "flush_interval must be greater than zero".to_string(),
+            ));
+        }
+        if self.flush_jitter >= self.flush_interval {
+            return Err(AnalyticsError::Config(
+                "flush_jitter must be less than flush_interval".to_string(),
+            ));
+        }
+        if self.queue_capacity == Some(0) {
+            return Err(AnalyticsError::Config(
+                "queue_capacity must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether an environment flag is set to a truthy value (`1`, `true`, `yes`)
+pub(crate) fn is_truthy(value: Option<&str>) -> bool {
+    matches!(
+        value.map(|v| v.trim().to_ascii_lowercase()).as_deref(),
+        Some("1" | "true" | "yes")
+    )
+}
+
+fn parse_positive<T>(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Result<Option<T>>
+where
+    T: FromStr + PartialOrd + Default,
+{
+    let Some(raw) = lookup(key) else {
+        return Ok(None);
+    };
+
+    match raw.trim().parse::<T>() {
+        Ok(value) if value > T::default() => Ok(Some(value)),
+        _ => Err(AnalyticsError::Config(format!(
+            "{} must be a positive integer, got '{}'",
+            key, raw
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_from_lookup_defaults() {
+        let config = ClientConfig::from_lookup(lookup(&[])).unwrap();
+        assert_eq!(config.batch_size, DEFAULT_BATCH_SIZE);
+        assert_eq!(config.flush_interval, DEFAULT_FLUSH_INTERVAL);
+        assert!(config.auth_token.is_none());
+    }
+
+    #[test]
+    fn test_from_lookup_overrides() {
+        let config = ClientConfig::from_lookup(lookup(&[
+            ("ANALYTICS_TOKEN", "secret"),
+            ("ANALYTICS_BATCH_SIZE", "50"),
+            ("ANALYTICS_FLUSH_INTERVAL_SECS", "5"),
+        ]))
+        .unwrap();
+        assert_eq!(config.batch_size, 50);
+        assert_eq!(config.flush_interval, Duration::from_secs(5));
+        assert_eq!(config.auth_token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_from_lookup_rejects_malformed_numbers() {
+        for (key, value) in [
+            ("ANALYTICS_BATCH_SIZE", "lots"),
+            ("ANALYTICS_BATCH_SIZE", "0"),
+            ("ANALYTICS_FLUSH_INTERVAL_SECS", "-1"),
+        ] {
+            let result = ClientConfig::from_lookup(lookup(&[(key, value)]));
+            assert!(
+                matches!(result, Err(AnalyticsError::Config(_))),
+                "{key}={value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_jittered_flush_interval() {
+        let mut config = ClientConfig::default();
+        assert_eq!(config.batch_policy().max_delay(), DEFAULT_FLUSH_INTERVAL);
+
+        config.flush_jitter = Duration::from_secs(2);
+        let policy = config.batch_policy();
+        let intervals: Vec<Duration> = (0..100).map(|_| policy.max_delay()).collect();
+        assert!(intervals
+            .iter()
+            .all(|i| (Duration::from_secs(8)..=Duration::from_secs(12)).contains(i)));
+        assert!(intervals.iter().any(|i| *i != intervals[0]));
+
+        config.flush_jitter = DEFAULT_FLUSH_INTERVAL;
+        assert!(matches!(config.validate(), Err(AnalyticsError::Config(_))));
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let config = ClientConfig {
+            auth_token: Some("tok-hunter2".to_string()),
+            signing_secret: Some(b"sig-hunter2".to_vec()),
+            ..ClientConfig::default()
+        };
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("hunter2"), "{}", debug);
+        assert!(
+            debug.contains(r#"auth_token: Some("<redacted>")"#),
+            "{}",
+            debug
+        );
+        assert!(debug.contains("signing_secret: Some"), "{}", debug);
+        assert!(format!("{:?}", ClientConfig::default()).contains("auth_token: None"));
+    }
+
+    #[test]
+    fn test_is_truthy() {
+        assert!(is_truthy(Some("1")));
+        assert!(is_truthy(Some("TRUE")));
+        assert!(is_truthy(Some("yes")));
+        assert!(!is_truthy(Some("0")));
+        assert!(!is_truthy(None));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::ClientConfig;
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use serde_json::Value;
+
+/// A batch encoded like the HTTP sink would send it
+struct Body {
+    /// Output of the configured serializer
+    serialized: Vec<u8>,
+    /// The request body, after compression
+    sent: Vec<u8>,
+    /// `Content-Encoding` of `sent`, if compressed
+    content_encoding: Option<&'static str>,
+}
+
+/// Serialize and compress `batch` with the configured `serializer`,
+/// `compression` and `compress_min_bytes`
+fn encode(config: &ClientConfig, batch: &[EnrichedEvent]) -> Result<Body> {
+    let serialized = config.serializer.serialize(batch)?;
+    let compression = config.compression_for(serialized.len());
+    let sent = compression.compress(serialized.clone())?;
+    Ok(Body {
+        serialized,
+        sent,
+        content_encoding: compression.content_encoding(),
+    })
+}
+
+/// Encode a batch exactly like a real send and log it instead (`dry_run`)
+///
+/// Serialization and compression errors are returned like from a send.
+/// The logged body is the serializer's output, pretty-printed with
+/// `dry_run_pretty` when it is JSON or NDJSON; binary bodies (e.g.
+/// protobuf) are logged as hex.
+pub(crate) fn log(config: &ClientConfig, batch: &[EnrichedEvent]) -> Result<()> {
+    let body = encode(config, batch)?;
+    let shown = if config.dry_run_pretty {
+        pretty(&body.serialized)
+    } else {
+        readable(&body.serialized)
+    };
+    tracing::info!(
+        "Dry run, not sending {} analytics events ({}, {} bytes, {} bytes {}):{}{}",
+        batch.len(),
+        config.serializer.content_type(),
+        body.serialized.len(),
+        body.sent.len(),
+        body.content_encoding.unwrap_or("uncompressed"),
+        if config.dry_run_pretty { "\n" } else { " " },
+        shown
+    );
+    Ok(())
+}
+
+/// The body as text, hex-encoded unless it is UTF-8
+fn readable(body: &[u8]) -> String {
+    match std::str::from_utf8(body) {
+        Ok(text) => text.to_string(),
+        Err(_) => hex::encode(body),
+    }
+}
+
+/// The body pretty-printed as a JSON document or as JSON lines, as-is
+/// otherwise
+fn pretty(body: &[u8]) -> String {
+    if let Ok(document) = serde_json::from_slice::<Value>(body) {
+        if let Ok(pretty) = serde_json::to_string_pretty(&document) {
+            return pretty;
+        }
+    }
+    let Ok(text) = std::str::from_utf8(body) else {
+        return hex::encode(body);
+    };
+    let lines: Option<Vec<String>> = text
+        .lines()
+        .map(|line| {
+            let value: Value = serde_json::from_str(line).ok()?;
+            serde_json::to_string_pretty(&value).ok()
+        })
+        .collect();
+    lines.map_or_else(|| text.to_string(), |lines| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+    use crate::serializer::{Envelope, EnvelopeSerializer, FieldCase};
+    use std::sync::Arc;
+
+    fn batch(len: usize) -> Vec<EnrichedEvent> {
+        (0..len)
+            .map(|_| {
+                EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+                    project_id: uuid::Uuid::new_v4(),
+                    user_id: uuid::Uuid::new_v4(),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pretty_keeps_the_serializer_output() {
+        let config = ClientConfig {
+            serializer: Arc::new(EnvelopeSerializer::ndjson(Envelope::Flat, FieldCase::Camel)),
+            ..ClientConfig::default()
+        };
+        let body = encode(&config, &batch(2)).unwrap();
+        let shown = pretty(&body.serialized);
+        assert!(shown.contains("\"schemaVersion\""), "{}", shown);
+        assert_eq!(shown.matches("\"type\": \"project_updated\"").count(), 2);
+
+        assert_eq!(readable(&[0xff, 0x00]), "ff00");
+        assert_eq!(pretty(&[0xff, 0x00]), "ff00");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_dry_run_compresses() {
+        use crate::compression::Compression;
+
+        let config = ClientConfig {
+            compression: Compression::Zstd { level: 3 },
+            compress_min_bytes: 0,
+            ..ClientConfig::default()
+        };
+        let body = encode(&config, &batch(50)).unwrap();
+        assert_eq!(body.content_encoding, Some("zstd"));
+        assert!(body.sent.len() < body.serialized.len());
+        assert_eq!(
+            zstd::decode_all(body.sent.as_slice()).unwrap(),
+            body.serialized
+        );
+        assert!(log(&config, &batch(50)).is_ok());
+    }
+}
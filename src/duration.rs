@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Duration in milliseconds, serialized as a plain integer
+///
+/// Used for all `duration_ms` event fields so call sites convert from
+/// [`Duration`] instead of doing unit arithmetic by hand:
+///
+/// ```rust
+/// use lib_analytics_core::DurationMs;
+/// use std::time::Instant;
+///
+/// let started = Instant::now();
+/// let duration_ms = DurationMs::from(started.elapsed());
+/// ```
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct DurationMs(pub i64);
+
+impl DurationMs {
+    /// Convert from a [`Duration`], saturating at `i64::MAX` milliseconds
+    pub fn from_std(duration: Duration) -> Self {
+        Self(i64::try_from(duration.as_millis()).unwrap_or(i64::MAX))
+    }
+
+    /// Number of milliseconds
+    pub fn as_millis(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<Duration> for DurationMs {
+    fn from(duration: Duration) -> Self {
+        Self::from_std(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_std() {
+        assert_eq!(DurationMs::from(Duration::from_secs(2)).as_millis(), 2000);
+        assert_eq!(DurationMs::from_std(Duration::MAX), DurationMs(i64::MAX));
+    }
+
+    #[test]
+    fn test_serializes_as_integer() {
+        assert_eq!(serde_json::to_string(&DurationMs(1500)).unwrap(), "1500");
+        assert_eq!(
+            serde_json::from_str::<DurationMs>("42").unwrap(),
+            DurationMs(42)
+        );
+    }
+}
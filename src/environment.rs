@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// Deployment environment attached to events as `EnrichedEvent::environment`
+///
+/// Serialized as `"development"`, `"staging"` or `"production"`, so services
+/// spelling their environment differently still land on the same dashboard
+/// label. Parsing is case-insensitive and ignores surrounding whitespace:
+///
+/// * `Development`: `dev`, `develop`, `development`, `local`
+/// * `Staging`: `stage`, `staging`, `stg`
+/// * `Production`: `prod`, `production`, `prd`, `live`
+///
+/// Anything else is kept verbatim in `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum Environment {
+    Development,
+    Staging,
+    Production,
+    Other(String),
+}
+
+impl Environment {
+    /// Canonical wire name of the environment
+    pub fn as_str(&self) -> &str {
+        match self {
+            Environment::Development => "development",
+            Environment::Staging => "staging",
+            Environment::Production => "production",
+            Environment::Other(name) => name,
+        }
+    }
+
+    /// Parse the `ENVIRONMENT` environment variable, `None` if unset or empty
+    pub(crate) fn from_env() -> Option<Self> {
+        std::env::var("ENVIRONMENT")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(Environment::from)
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Environment {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "dev" | "develop" | "development" | "local" => Environment::Development,
+            "stage" | "staging" | "stg" => Environment::Staging,
+            "prod" | "production" | "prd" | "live" => Environment::Production,
+            _ => Environment::Other(s.to_string()),
+        })
+    }
+}
+
+impl From<&str> for Environment {
+    fn from(s: &str) -> Self {
+        let Ok(environment) = s.parse();
+        environment
+    }
+}
+
+impl From<String> for Environment {
+    fn from(s: String) -> Self {
+        Environment::from(s.as_str())
+    }
+}
+
+impl From<Environment> for String {
+    fn from(environment: Environment) -> Self {
+        match environment {
+            Environment::Other(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_map_to_canonical_variants() {
+        for (alias, expected) in [
+            ("dev", Environment::Development),
+            ("Development", Environment::Development),
+            ("local", Environment::Development),
+            ("stage", Environment::Staging),
+            ("STAGING", Environment::Staging),
+            ("stg", Environment::Staging),
+            ("prod", Environment::Production),
+            ("Prod", Environment::Production),
+            (" production\n", Environment::Production),
+            ("live", Environment::Production),
+        ] {
+            assert_eq!(Environment::from(alias), expected, "{:?}", alias);
+        }
+    }
+
+    #[test]
+    fn test_unknown_environment_kept_verbatim() {
+        let environment = Environment::from("QA-2");
+        assert_eq!(environment, Environment::Other("QA-2".to_string()));
+        assert_eq!(environment.to_string(), "QA-2");
+    }
+
+    #[test]
+    fn test_serde_is_canonical() {
+        let parsed: Environment = serde_json::from_str("\"Prod\"").unwrap();
+        assert_eq!(parsed, Environment::Production);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), "\"production\"");
+        assert_eq!(
+            serde_json::to_string(&Environment::Other("qa".to_string())).unwrap(),
+            "\"qa\""
+        );
+    }
+}
@@ -9,11 +9,306 @@ pub enum AnalyticsError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[error("Request error: {0}")]
+    Request(#[source] reqwest::Error),
+
+    #[error("TLS error: {0}")]
+    Tls(#[source] reqwest::Error),
+
+    #[error("Connection error: {0}")]
+    Connect(#[source] reqwest::Error),
+
+    #[error("Request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
+
+    #[error("Redirect error: {0}")]
+    Redirect(#[source] reqwest::Error),
+
+    #[error("Response decoding error: {0}")]
+    Decode(#[source] reqwest::Error),
+
+    #[error("Ingestion service responded with HTTP {status}")]
+    Http { status: u16 },
+
     #[error("Event channel closed")]
     ChannelClosed,
 
+    #[error("Event queue full")]
+    QueueFull,
+
     #[error("Worker not running")]
     WorkerNotRunning,
+
+    #[error("Timed out waiting for events to be flushed")]
+    FlushTimeout,
+
+    #[error("Sending is paused")]
+    Paused,
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Global analytics client already initialized")]
+    AlreadyInitialized,
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Durable buffer full")]
+    DurableBufferFull,
+
+    #[error("Invalid event: {0}")]
+    Validation(#[from] ValidationError),
+
+    #[cfg(feature = "kafka")]
+    #[error("Kafka error: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
+
+    #[cfg(feature = "protobuf")]
+    #[error("Invalid protobuf batch: {0}")]
+    Protobuf(String),
+}
+
+/// Reason an event failed validation
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("Field '{field}' must not be empty")]
+    EmptyField { field: &'static str },
+
+    #[error("Field '{field}' must not be negative (got {value})")]
+    NegativeDuration { field: &'static str, value: i64 },
+
+    #[error("Field '{field}' is out of range")]
+    OutOfRange { field: &'static str },
+}
+
+impl AnalyticsError {
+    /// Whether the same operation may succeed if tried again later
+    ///
+    /// Transport failures, timeouts, 5xx/408/429 responses, full queues
+    /// or buffers and flushes of a paused client are retryable.
+    /// Serialization, validation, configuration and other 4xx errors are
+    /// permanent, as is a closed client.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AnalyticsError::Database(e) => matches!(
+                e,
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::Tls(_)
+            ),
+            AnalyticsError::Request(e) => match e.status() {
+                Some(status) => is_retryable_status(status.as_u16()),
+                None => !(e.is_builder() || e.is_decode() || e.is_redirect()),
+            },
+            AnalyticsError::Tls(_) | AnalyticsError::Connect(_) | AnalyticsError::Timeout(_) => {
+                true
+            }
+            AnalyticsError::Redirect(_) | AnalyticsError::Decode(_) => false,
+            #[cfg(feature = "kafka")]
+            AnalyticsError::Kafka(e) => is_retryable_kafka_error(e),
+            #[cfg(feature = "protobuf")]
+            AnalyticsError::Protobuf(_) => false,
+            AnalyticsError::Http { status } => is_retryable_status(*status),
+            AnalyticsError::Io(e) => !matches!(
+                e.kind(),
+                std::io::ErrorKind::InvalidInput | std::io::ErrorKind::InvalidData
+            ),
+            AnalyticsError::QueueFull
+            | AnalyticsError::FlushTimeout
+            | AnalyticsError::Paused
+            | AnalyticsError::DurableBufferFull => true,
+            AnalyticsError::Serialization(_)
+            | AnalyticsError::ChannelClosed
+            | AnalyticsError::WorkerNotRunning
+            | AnalyticsError::Config(_)
+            | AnalyticsError::AlreadyInitialized
+            | AnalyticsError::Validation(_) => false,
+        }
+    }
+}
+
+/// Sort request failures by cause, so logs tell a TLS handshake failure
+/// from a refused connection or a redirect loop
+impl From<reqwest::Error> for AnalyticsError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AnalyticsError::Timeout(e)
+        } else if e.is_connect() && is_tls_failure(&e) {
+            AnalyticsError::Tls(e)
+        } else if e.is_connect() {
+            AnalyticsError::Connect(e)
+        } else if e.is_redirect() {
+            AnalyticsError::Redirect(e)
+        } else if e.is_decode() {
+            AnalyticsError::Decode(e)
+        } else {
+            AnalyticsError::Request(e)
+        }
+    }
+}
+
+/// Whether a connect error was raised by the TLS handshake
+///
+/// rustls reports handshake failures (bad certificates, a peer not speaking
+/// TLS) as `InvalidData` I/O errors, unlike socket-level connect failures.
+fn is_tls_failure(e: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(e);
+    while let Some(error) = source {
+        source = match error.downcast_ref::<std::io::Error>() {
+            Some(io) if io.kind() == std::io::ErrorKind::InvalidData => return true,
+            // `io::Error::source` skips the wrapped error itself
+            Some(io) => io
+                .get_ref()
+                .map(|inner| inner as &(dyn std::error::Error + 'static)),
+            None => error.source(),
+        };
+    }
+    false
+}
+
+/// Producer failures are transient unless the record or credentials are bad
+#[cfg(feature = "kafka")]
+fn is_retryable_kafka_error(e: &rdkafka::error::KafkaError) -> bool {
+    use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+
+    match e {
+        KafkaError::ClientConfig(..) | KafkaError::ClientCreation(_) => false,
+        _ => !matches!(
+            e.rdkafka_error_code(),
+            Some(
+                RDKafkaErrorCode::InvalidMessage
+                    | RDKafkaErrorCode::MessageSizeTooLarge
+                    | RDKafkaErrorCode::TopicAuthorizationFailed
+                    | RDKafkaErrorCode::ClusterAuthorizationFailed
+            )
+        ),
+    }
+}
+
+/// Server errors, request timeouts and rate limiting are worth retrying
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500..=599)
 }
 
 pub type Result<T> = std::result::Result<T, AnalyticsError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_status_classification() {
+        for status in [500, 502, 503, 504, 408, 429] {
+            assert!(AnalyticsError::Http { status }.is_retryable(), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 413, 422] {
+            assert!(
+                !AnalyticsError::Http { status }.is_retryable(),
+                "{}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_permanent_errors() {
+        let serialization = serde_json::from_str::<u32>("x").unwrap_err();
+        let permanent = [
+            AnalyticsError::Serialization(serialization),
+            AnalyticsError::ChannelClosed,
+            AnalyticsError::WorkerNotRunning,
+            AnalyticsError::Config("bad".to_string()),
+            AnalyticsError::AlreadyInitialized,
+            AnalyticsError::Validation(ValidationError::EmptyField { field: "email" }),
+            AnalyticsError::Database(sqlx::Error::RowNotFound),
+            AnalyticsError::Io(std::io::ErrorKind::InvalidData.into()),
+        ];
+        for error in permanent {
+            assert!(!error.is_retryable(), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let transient = [
+            AnalyticsError::QueueFull,
+            AnalyticsError::FlushTimeout,
+            AnalyticsError::Paused,
+            AnalyticsError::DurableBufferFull,
+            AnalyticsError::Database(sqlx::Error::PoolTimedOut),
+            AnalyticsError::Io(std::io::ErrorKind::ConnectionReset.into()),
+        ];
+        for error in transient {
+            assert!(error.is_retryable(), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_errors() {
+        let client = reqwest::Client::new();
+
+        let builder = client.get("not a url").send().await.unwrap_err();
+        assert!(!AnalyticsError::Request(builder).is_retryable());
+
+        let connect = client.get("http://127.0.0.1:1").send().await.unwrap_err();
+        assert!(AnalyticsError::Request(connect).is_retryable());
+    }
+
+    /// Serve every connection with the same raw bytes, returning the address
+    async fn serve(response: &'static [u8]) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(response).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_request_error_mapping() {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let connect = client.get("http://127.0.0.1:1").send().await.unwrap_err();
+        assert!(matches!(connect.into(), AnalyticsError::Connect(_)));
+
+        let addr = serve(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let tls = client
+            .get(format!("https://{}", addr))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(tls.into(), AnalyticsError::Tls(_)));
+
+        // Connections queue in the backlog but are never answered
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let timeout = client
+            .get(format!("http://{}", silent.local_addr().unwrap()))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(timeout.into(), AnalyticsError::Timeout(_)));
+
+        let addr = serve(b"HTTP/1.1 302 Found\r\nLocation: /\r\nContent-Length: 0\r\n\r\n").await;
+        let redirect = client
+            .get(format!("http://{}", addr))
+            .send()
+            .await
+            .unwrap_err();
+        let redirect: AnalyticsError = redirect.into();
+        assert!(matches!(redirect, AnalyticsError::Redirect(_)));
+        assert!(!redirect.is_retryable());
+
+        let addr = serve(b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n{").await;
+        let response = client.get(format!("http://{}", addr)).send().await.unwrap();
+        let decode = response.json::<serde_json::Value>().await.unwrap_err();
+        assert!(matches!(decode.into(), AnalyticsError::Decode(_)));
+    }
+}
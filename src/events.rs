@@ -1,9 +1,17 @@
+use crate::duration::DurationMs;
+use crate::environment::Environment;
+use crate::normalize::CanonicalAction;
+use crate::provider::Provider;
+use crate::retention::RetentionClass;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
 
 /// Analytics event types covering all ADI services
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AnalyticsEvent {
     // ===== Authentication Events =====
@@ -29,9 +37,45 @@ pub enum AnalyticsEvent {
         error: Option<String>,
     },
 
+    /// Token will expire soon (emitted ahead of expiry for proactive refresh)
+    AuthTokenExpiring {
+        user_id: Uuid,
+        /// Issuer of the token (e.g. `github`), `None` for ADI's own tokens
+        provider: Option<String>,
+        expires_in_seconds: i64,
+    },
+
     /// Session validation
     AuthSessionValidated { user_id: Uuid, valid: bool },
 
+    /// Session started (e.g. on login)
+    SessionStarted { session_id: Uuid, user_id: Uuid },
+
+    /// Session ended
+    SessionEnded {
+        session_id: Uuid,
+        user_id: Uuid,
+        duration_seconds: i64,
+        /// Why the session ended, e.g. `logout`, `timeout` or `revoked`
+        reason: Option<String>,
+    },
+
+    /// Repeated login attempts for one email, merged by the client
+    ///
+    /// Emitted in place of the individual `AuthLoginAttempt` events when
+    /// `ClientConfig::login_compaction_window` is set and more than one
+    /// attempt arrived within the window.
+    AuthLoginAttemptsCompacted {
+        user_id: Option<Uuid>,
+        email: String,
+        attempt_count: u32,
+        failure_count: u32,
+        /// Error of the most recent failed attempt
+        last_error: Option<String>,
+        first_attempt_at: DateTime<Utc>,
+        last_attempt_at: DateTime<Utc>,
+    },
+
     // ===== Task Events =====
     /// Task created
     TaskCreated {
@@ -49,11 +93,24 @@ pub enum AnalyticsEvent {
         cocoon_id: Option<Uuid>,
     },
 
+    /// Intermediate progress of a running task
+    ///
+    /// Can be frequent for long tasks; consider aggregating them
+    /// (`ClientConfig::aggregations`) if only trends matter.
+    TaskProgress {
+        task_id: Uuid,
+        user_id: Uuid,
+        /// Completion in percent (0-100)
+        percent: Option<u8>,
+        stage: Option<String>,
+        message: Option<String>,
+    },
+
     /// Task completed successfully
     TaskCompleted {
         task_id: Uuid,
         user_id: Uuid,
-        duration_ms: i64,
+        duration_ms: DurationMs,
         exit_code: i32,
     },
 
@@ -61,7 +118,7 @@ pub enum AnalyticsEvent {
     TaskFailed {
         task_id: Uuid,
         user_id: Uuid,
-        duration_ms: Option<i64>,
+        duration_ms: Option<DurationMs>,
         exit_code: Option<i32>,
         error: String,
     },
@@ -70,7 +127,7 @@ pub enum AnalyticsEvent {
     TaskCancelled {
         task_id: Uuid,
         user_id: Uuid,
-        duration_ms: Option<i64>,
+        duration_ms: Option<DurationMs>,
     },
 
     // ===== Integration Events =====
@@ -78,7 +135,7 @@ pub enum AnalyticsEvent {
     IntegrationConnected {
         integration_id: Uuid,
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         project_id: Option<Uuid>,
     },
 
@@ -86,15 +143,22 @@ pub enum AnalyticsEvent {
     IntegrationDisconnected {
         integration_id: Uuid,
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         reason: Option<String>,
     },
 
+    /// Integration access re-granted, e.g. after its token was revoked
+    IntegrationReauthorized {
+        integration_id: Uuid,
+        user_id: Uuid,
+        provider: Provider,
+    },
+
     /// Integration used
     IntegrationUsed {
         integration_id: Uuid,
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         action: String,
     },
 
@@ -102,21 +166,21 @@ pub enum AnalyticsEvent {
     IntegrationError {
         integration_id: Uuid,
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         error: String,
     },
 
     /// OAuth flow started
     OAuthFlowStarted {
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         state: String,
     },
 
     /// OAuth flow completed
     OAuthFlowCompleted {
         user_id: Uuid,
-        provider: String,
+        provider: Provider,
         success: bool,
         error: Option<String>,
     },
@@ -125,7 +189,7 @@ pub enum AnalyticsEvent {
     /// Webhook received
     WebhookReceived {
         integration_id: Option<Uuid>,
-        provider: String,
+        provider: Provider,
         event_type: String,
         delivery_id: String,
     },
@@ -133,12 +197,23 @@ pub enum AnalyticsEvent {
     /// Webhook processing completed
     WebhookProcessed {
         integration_id: Option<Uuid>,
-        provider: String,
+        provider: Provider,
         event_type: String,
         delivery_id: String,
         success: bool,
-        duration_ms: i64,
+        duration_ms: DurationMs,
         error: Option<String>,
+        /// Number of earlier failed attempts for this delivery
+        #[serde(default)]
+        retry_count: u32,
+    },
+
+    /// Webhook processing failed and another attempt was scheduled
+    WebhookRetryScheduled {
+        delivery_id: String,
+        provider: Provider,
+        attempt: u32,
+        next_attempt_at: DateTime<Utc>,
     },
 
     // ===== Cocoon/Device Events =====
@@ -159,9 +234,23 @@ pub enum AnalyticsEvent {
     CocoonDisconnected {
         cocoon_id: Uuid,
         user_id: Option<Uuid>,
+        /// Connection length in whole seconds (not milliseconds)
         duration_seconds: i64,
     },
 
+    /// Periodic Cocoon health report
+    ///
+    /// Naturally high-volume; consider aggregating them
+    /// (`ClientConfig::aggregations`) when per-device detail isn't needed.
+    CocoonHeartbeat {
+        cocoon_id: Uuid,
+        user_id: Option<Uuid>,
+        uptime_seconds: i64,
+        /// CPU usage in percent (0-100)
+        cpu_percent: Option<f32>,
+        mem_bytes: Option<u64>,
+    },
+
     /// Cocoon claimed by user
     CocoonClaimed {
         cocoon_id: Uuid,
@@ -197,6 +286,12 @@ pub enum AnalyticsEvent {
     /// Project deleted
     ProjectDeleted { project_id: Uuid, user_id: Uuid },
 
+    /// Project archived (hidden but kept, can be restored)
+    ProjectArchived { project_id: Uuid, user_id: Uuid },
+
+    /// Archived project restored
+    ProjectRestored { project_id: Uuid, user_id: Uuid },
+
     // ===== API Request Events =====
     /// API request made
     ApiRequest {
@@ -204,7 +299,7 @@ pub enum AnalyticsEvent {
         endpoint: String,
         method: String,
         status_code: u16,
-        duration_ms: i64,
+        duration_ms: DurationMs,
         user_id: Option<Uuid>,
     },
 
@@ -238,8 +333,16 @@ pub enum AnalyticsEvent {
     DatabaseQuery {
         service: String,
         query_type: String,
-        duration_ms: i64,
+        duration_ms: DurationMs,
         rows_affected: Option<i64>,
+        /// [`sql_fingerprint`](crate::sql_fingerprint) of the statement,
+        /// grouping executions of the same query without sending its SQL
+        #[serde(default)]
+        statement_fingerprint: Option<String>,
+        /// Whether the query took at least `ClientConfig::slow_query_threshold`;
+        /// filled in by the client when that is set and this is `None`
+        #[serde(default)]
+        is_slow: Option<bool>,
     },
 
     // ===== Error Events =====
@@ -250,6 +353,10 @@ pub enum AnalyticsEvent {
         error_message: String,
         user_id: Option<Uuid>,
         context: Option<serde_json::Value>,
+        /// Reference to `context` stored out of band (e.g. an object storage
+        /// key), set instead of `context` when it was too large to inline
+        #[serde(default)]
+        context_ref: Option<String>,
     },
 
     // ===== Balance Events =====
@@ -279,38 +386,136 @@ pub enum AnalyticsEvent {
         current_balance: i64,
         reference_type: Option<String>,
     },
+
+    // ===== Aggregation Events =====
+    /// Summary of events aggregated by the client over one flush window
+    ///
+    /// Emitted instead of the raw events for event types configured in
+    /// `ClientConfig::aggregations`. Durations are `None` when the
+    /// aggregated events carry no duration.
+    EventSummary {
+        /// Type of the aggregated events (e.g. `api_request`)
+        event_type: String,
+        /// Grouping key produced by the aggregation's key function
+        key: String,
+        count: u64,
+        p50_duration_ms: Option<DurationMs>,
+        p95_duration_ms: Option<DurationMs>,
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    },
+
+    /// Cocoon connects and disconnects counted by the client over a window
+    ///
+    /// Emitted when `ClientConfig::churn_window` is set, at the first flush
+    /// after the window has elapsed. `window_seconds` is the time actually
+    /// covered, so `(connects + disconnects) / window_seconds` is the churn
+    /// rate.
+    CocoonChurn {
+        window_seconds: u64,
+        connects: u64,
+        disconnects: u64,
+    },
+
+    // ===== Pipeline Events =====
+    /// Health of the client's own delivery pipeline since the previous report
+    ///
+    /// Emitted every `ClientConfig::stats_interval` when enabled. Counts never
+    /// include `PipelineStats` events themselves.
+    PipelineStats {
+        /// Events accepted by `track`
+        enqueued: u64,
+        /// Events rejected by `track` (invalid, queue full or client shut down)
+        dropped: u64,
+        /// Events delivered to the sink
+        sent: u64,
+        /// Events in batches the sink failed to deliver
+        failed: u64,
+        interval_secs: u64,
+    },
 }
 
 impl AnalyticsEvent {
+    /// Build an `ApiRequest` from the parts of an [`http::Request`] and the
+    /// response status
+    ///
+    /// `endpoint` is the URI path without the query string and `method` the
+    /// uppercase method name, the same values
+    /// [`AnalyticsLayer`](crate::AnalyticsLayer) records without a `route`.
+    ///
+    /// ```rust
+    /// use lib_analytics_core::AnalyticsEvent;
+    /// use std::time::Duration;
+    ///
+    /// let (parts, _body) = http::Request::get("/projects?page=2")
+    ///     .body(())
+    ///     .unwrap()
+    ///     .into_parts();
+    /// let event = AnalyticsEvent::api_request_from_parts(
+    ///     "api",
+    ///     &parts,
+    ///     http::StatusCode::OK,
+    ///     Duration::from_millis(12),
+    ///     None,
+    /// );
+    /// ```
+    #[cfg(feature = "http")]
+    pub fn api_request_from_parts(
+        service: impl Into<String>,
+        parts: &http::request::Parts,
+        status: http::StatusCode,
+        duration: impl Into<DurationMs>,
+        user_id: Option<Uuid>,
+    ) -> Self {
+        AnalyticsEvent::ApiRequest {
+            service: service.into(),
+            endpoint: parts.uri.path().to_string(),
+            method: parts.method.to_string(),
+            status_code: status.as_u16(),
+            duration_ms: duration.into(),
+            user_id,
+        }
+    }
+
     /// Get the event type as a string
     pub fn event_type(&self) -> &'static str {
         match self {
             AnalyticsEvent::AuthLoginAttempt { .. } => "auth_login_attempt",
             AnalyticsEvent::AuthCodeVerified { .. } => "auth_code_verified",
             AnalyticsEvent::AuthTokenRefresh { .. } => "auth_token_refresh",
+            AnalyticsEvent::AuthTokenExpiring { .. } => "auth_token_expiring",
             AnalyticsEvent::AuthSessionValidated { .. } => "auth_session_validated",
+            AnalyticsEvent::SessionStarted { .. } => "session_started",
+            AnalyticsEvent::SessionEnded { .. } => "session_ended",
+            AnalyticsEvent::AuthLoginAttemptsCompacted { .. } => "auth_login_attempts_compacted",
             AnalyticsEvent::TaskCreated { .. } => "task_created",
             AnalyticsEvent::TaskStarted { .. } => "task_started",
+            AnalyticsEvent::TaskProgress { .. } => "task_progress",
             AnalyticsEvent::TaskCompleted { .. } => "task_completed",
             AnalyticsEvent::TaskFailed { .. } => "task_failed",
             AnalyticsEvent::TaskCancelled { .. } => "task_cancelled",
             AnalyticsEvent::IntegrationConnected { .. } => "integration_connected",
             AnalyticsEvent::IntegrationDisconnected { .. } => "integration_disconnected",
+            AnalyticsEvent::IntegrationReauthorized { .. } => "integration_reauthorized",
             AnalyticsEvent::IntegrationUsed { .. } => "integration_used",
             AnalyticsEvent::IntegrationError { .. } => "integration_error",
             AnalyticsEvent::OAuthFlowStarted { .. } => "oauth_flow_started",
             AnalyticsEvent::OAuthFlowCompleted { .. } => "oauth_flow_completed",
             AnalyticsEvent::WebhookReceived { .. } => "webhook_received",
             AnalyticsEvent::WebhookProcessed { .. } => "webhook_processed",
+            AnalyticsEvent::WebhookRetryScheduled { .. } => "webhook_retry_scheduled",
             AnalyticsEvent::CocoonRegistered { .. } => "cocoon_registered",
             AnalyticsEvent::CocoonConnected { .. } => "cocoon_connected",
             AnalyticsEvent::CocoonDisconnected { .. } => "cocoon_disconnected",
+            AnalyticsEvent::CocoonHeartbeat { .. } => "cocoon_heartbeat",
             AnalyticsEvent::CocoonClaimed { .. } => "cocoon_claimed",
             AnalyticsEvent::CocoonSetupTokenCreated { .. } => "cocoon_setup_token_created",
             AnalyticsEvent::CocoonSetupTokenUsed { .. } => "cocoon_setup_token_used",
             AnalyticsEvent::ProjectCreated { .. } => "project_created",
             AnalyticsEvent::ProjectUpdated { .. } => "project_updated",
             AnalyticsEvent::ProjectDeleted { .. } => "project_deleted",
+            AnalyticsEvent::ProjectArchived { .. } => "project_archived",
+            AnalyticsEvent::ProjectRestored { .. } => "project_restored",
             AnalyticsEvent::ApiRequest { .. } => "api_request",
             AnalyticsEvent::ProxyRequest { .. } => "proxy_request",
             AnalyticsEvent::DatabaseQuery { .. } => "database_query",
@@ -319,6 +524,9 @@ impl AnalyticsEvent {
             AnalyticsEvent::BalanceDeposit { .. } => "balance_deposit",
             AnalyticsEvent::BalanceDebit { .. } => "balance_debit",
             AnalyticsEvent::BalanceInsufficient { .. } => "balance_insufficient",
+            AnalyticsEvent::EventSummary { .. } => "event_summary",
+            AnalyticsEvent::CocoonChurn { .. } => "cocoon_churn",
+            AnalyticsEvent::PipelineStats { .. } => "pipeline_stats",
         }
     }
 
@@ -332,20 +540,66 @@ impl AnalyticsEvent {
         }
     }
 
+    /// Get the Cocoon ID if available
+    pub fn cocoon_id(&self) -> Option<Uuid> {
+        match self {
+            AnalyticsEvent::TaskCreated { cocoon_id, .. } => *cocoon_id,
+            AnalyticsEvent::TaskStarted { cocoon_id, .. } => *cocoon_id,
+            AnalyticsEvent::CocoonRegistered { cocoon_id, .. } => Some(*cocoon_id),
+            AnalyticsEvent::CocoonConnected { cocoon_id, .. } => Some(*cocoon_id),
+            AnalyticsEvent::CocoonDisconnected { cocoon_id, .. } => Some(*cocoon_id),
+            AnalyticsEvent::CocoonHeartbeat { cocoon_id, .. } => Some(*cocoon_id),
+            AnalyticsEvent::CocoonClaimed { cocoon_id, .. } => Some(*cocoon_id),
+            AnalyticsEvent::CocoonSetupTokenUsed { cocoon_id, .. } => Some(*cocoon_id),
+            _ => None,
+        }
+    }
+
+    /// Get the session ID for session lifecycle events
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self {
+            AnalyticsEvent::SessionStarted { session_id, .. } => Some(*session_id),
+            AnalyticsEvent::SessionEnded { session_id, .. } => Some(*session_id),
+            _ => None,
+        }
+    }
+
+    /// Get the duration in milliseconds if the event measures one
+    pub fn duration_ms(&self) -> Option<DurationMs> {
+        match self {
+            AnalyticsEvent::TaskCompleted { duration_ms, .. } => Some(*duration_ms),
+            AnalyticsEvent::TaskFailed { duration_ms, .. } => *duration_ms,
+            AnalyticsEvent::TaskCancelled { duration_ms, .. } => *duration_ms,
+            AnalyticsEvent::WebhookProcessed { duration_ms, .. } => Some(*duration_ms),
+            AnalyticsEvent::ApiRequest { duration_ms, .. } => Some(*duration_ms),
+            AnalyticsEvent::ProxyRequest { latency_ms, .. } => {
+                Some(DurationMs(i64::from(*latency_ms)))
+            }
+            AnalyticsEvent::DatabaseQuery { duration_ms, .. } => Some(*duration_ms),
+            _ => None,
+        }
+    }
+
     /// Get the user ID if available
     pub fn user_id(&self) -> Option<Uuid> {
         match self {
             AnalyticsEvent::AuthLoginAttempt { user_id, .. } => *user_id,
             AnalyticsEvent::AuthCodeVerified { user_id, .. } => Some(*user_id),
             AnalyticsEvent::AuthTokenRefresh { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::AuthTokenExpiring { user_id, .. } => Some(*user_id),
             AnalyticsEvent::AuthSessionValidated { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::SessionStarted { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::SessionEnded { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::AuthLoginAttemptsCompacted { user_id, .. } => *user_id,
             AnalyticsEvent::TaskCreated { user_id, .. } => Some(*user_id),
             AnalyticsEvent::TaskStarted { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::TaskProgress { user_id, .. } => Some(*user_id),
             AnalyticsEvent::TaskCompleted { user_id, .. } => Some(*user_id),
             AnalyticsEvent::TaskFailed { user_id, .. } => Some(*user_id),
             AnalyticsEvent::TaskCancelled { user_id, .. } => Some(*user_id),
             AnalyticsEvent::IntegrationConnected { user_id, .. } => Some(*user_id),
             AnalyticsEvent::IntegrationDisconnected { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::IntegrationReauthorized { user_id, .. } => Some(*user_id),
             AnalyticsEvent::IntegrationUsed { user_id, .. } => Some(*user_id),
             AnalyticsEvent::IntegrationError { user_id, .. } => Some(*user_id),
             AnalyticsEvent::OAuthFlowStarted { user_id, .. } => Some(*user_id),
@@ -353,12 +607,15 @@ impl AnalyticsEvent {
             AnalyticsEvent::CocoonRegistered { user_id, .. } => Some(*user_id),
             AnalyticsEvent::CocoonConnected { user_id, .. } => *user_id,
             AnalyticsEvent::CocoonDisconnected { user_id, .. } => *user_id,
+            AnalyticsEvent::CocoonHeartbeat { user_id, .. } => *user_id,
             AnalyticsEvent::CocoonClaimed { user_id, .. } => Some(*user_id),
             AnalyticsEvent::CocoonSetupTokenCreated { user_id, .. } => Some(*user_id),
             AnalyticsEvent::CocoonSetupTokenUsed { user_id, .. } => Some(*user_id),
             AnalyticsEvent::ProjectCreated { user_id, .. } => Some(*user_id),
             AnalyticsEvent::ProjectUpdated { user_id, .. } => Some(*user_id),
             AnalyticsEvent::ProjectDeleted { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::ProjectArchived { user_id, .. } => Some(*user_id),
+            AnalyticsEvent::ProjectRestored { user_id, .. } => Some(*user_id),
             AnalyticsEvent::ApiRequest { user_id, .. } => *user_id,
             AnalyticsEvent::ProxyRequest { user_id, .. } => Some(*user_id),
             AnalyticsEvent::ApplicationError { user_id, .. } => *user_id,
@@ -377,16 +634,610 @@ pub struct EnrichedEvent {
     pub timestamp: DateTime<Utc>,
     pub event: AnalyticsEvent,
     pub hostname: Option<String>,
-    pub environment: Option<String>,
+    /// Deployment environment, canonicalized so `prod` and `Production`
+    /// are sent alike
+    pub environment: Option<Environment>,
+    /// Storage retention hint, `None` leaves the choice to the ingestion service
+    #[serde(default)]
+    pub retention: Option<RetentionClass>,
+    /// Position in the order events were tracked, starting at 0
+    ///
+    /// Counted per client instance (shared by its clones), not globally.
+    /// Assigned before queueing, so events dropped on a full queue or failed
+    /// batches leave gaps.
+    /// Lets the backend restore tracking order where timestamps tie or
+    /// delivery reorders batches.
+    ///
+    /// The HTTP sink also numbers its batch requests in the `X-Batch-Seq`
+    /// header, from 0 per client and once per request actually sent
+    /// (retries get a new number). Together they locate loss: a missing
+    /// batch number means a request never arrived, while a `sequence` gap
+    /// without one means the events were dropped in the client before
+    /// being sent (e.g. a full queue or `max_event_age`).
+    #[serde(default)]
+    pub sequence: u64,
+    /// When the event was tracked, in nanoseconds on a monotonic clock
+    /// shared by the tracking process
+    ///
+    /// Breaks ties between events with the same `timestamp` (which has
+    /// microsecond resolution at best and can be overridden, e.g. with
+    /// `track_at`): among events from the same `hostname` that tie on
+    /// `timestamp`, the smaller value was tracked first. The clock counts
+    /// from the process start, so values are not comparable across
+    /// processes or restarts. Prefer `sequence` for events of one client.
+    #[serde(default)]
+    pub monotonic_ns: u64,
+    /// [`AnalyticsEvent::schema_version`] of `event`, 0 if unknown
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Tenant the event belongs to, set by
+    /// [`AnalyticsClient::track_for_tenant`](crate::AnalyticsClient::track_for_tenant)
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    /// Provider-independent meaning of an `IntegrationUsed` action or
+    /// `WebhookReceived` event type, set by the client from
+    /// [`ClientConfig::action_normalizer`](crate::ClientConfig::action_normalizer)
+    #[serde(default)]
+    pub canonical_action: Option<CanonicalAction>,
+    /// File name and content of a binary sent alongside the event, set by
+    /// [`AnalyticsClient::track_with_attachment`](crate::AnalyticsClient::track_with_attachment)
+    ///
+    /// Not part of the serialized event: the HTTP sink sends batches with
+    /// attachments as `multipart/form-data`. Other sinks, including the
+    /// durable buffer, drop attachments.
+    #[cfg(feature = "attachments")]
+    #[serde(skip)]
+    pub attachment: Option<(String, Vec<u8>)>,
 }
 
 impl EnrichedEvent {
+    /// Enrich an event with the current time and this host's metadata
+    ///
+    /// `hostname` and `environment` come from the `HOSTNAME` and
+    /// `ENVIRONMENT` environment variables, `retention` from
+    /// [`AnalyticsEvent::default_retention`].
     pub fn new(event: AnalyticsEvent) -> Self {
+        Enrichment::new(None, None).enrich(event)
+    }
+
+    /// Length in bytes of the event serialized as JSON
+    ///
+    /// Counts the serializer's output without buffering it, so it costs one
+    /// serialization pass and no allocation for the output. The result is
+    /// exact for the JSON serializer; other batch serializers and
+    /// compression change the bytes on the wire, and an attachment is not
+    /// included. Useful to keep oversized events (e.g. huge `context`
+    /// values) out of the queue. Returns 0 if the event can't be serialized.
+    pub fn estimated_size(&self) -> usize {
+        struct Counter(usize);
+
+        impl io::Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0 += buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut counter = Counter(0);
+        match serde_json::to_writer(&mut counter, self) {
+            Ok(()) => counter.0,
+            Err(_) => 0,
+        }
+    }
+}
+
+/// Host metadata and sequence numbers attached to events tracked by a client
+#[derive(Debug)]
+pub(crate) struct Enrichment {
+    hostname: Option<String>,
+    environment: Option<Environment>,
+    sequence: AtomicU64,
+}
+
+impl Enrichment {
+    /// Use the given values, falling back to the `HOSTNAME` and `ENVIRONMENT`
+    /// environment variables for those that are `None`
+    pub(crate) fn new(hostname: Option<String>, environment: Option<Environment>) -> Self {
         Self {
+            hostname: hostname.or_else(|| std::env::var("HOSTNAME").ok()),
+            environment: environment.or_else(Environment::from_env),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn next_sequence(&self) -> u64 {
+        self.sequence.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub(crate) fn enrich(&self, event: AnalyticsEvent) -> EnrichedEvent {
+        EnrichedEvent {
             timestamp: Utc::now(),
+            retention: Some(event.default_retention()),
+            schema_version: event.schema_version(),
             event,
-            hostname: std::env::var("HOSTNAME").ok(),
-            environment: std::env::var("ENVIRONMENT").ok(),
+            hostname: self.hostname.clone(),
+            environment: self.environment.clone(),
+            sequence: self.next_sequence(),
+            monotonic_ns: crate::clock::monotonic_ns(),
+            tenant_id: None,
+            canonical_action: None,
+            #[cfg(feature = "attachments")]
+            attachment: None,
+        }
+    }
+}
+
+/// One instance of every event variant, for tests covering all of them
+#[cfg(test)]
+pub(crate) mod samples {
+    use super::*;
+
+    pub(crate) fn all_variants() -> Vec<AnalyticsEvent> {
+        let id = Uuid::nil;
+        let now = Utc::now();
+        let text = || "sample".to_string();
+
+        vec![
+            AnalyticsEvent::AuthLoginAttempt {
+                user_id: None,
+                email: "user@example.com".to_string(),
+                success: true,
+                error: None,
+            },
+            AnalyticsEvent::AuthCodeVerified {
+                user_id: id(),
+                success: true,
+                error: None,
+            },
+            AnalyticsEvent::AuthTokenRefresh {
+                user_id: id(),
+                success: true,
+                error: None,
+            },
+            AnalyticsEvent::AuthTokenExpiring {
+                user_id: id(),
+                provider: None,
+                expires_in_seconds: 300,
+            },
+            AnalyticsEvent::AuthSessionValidated {
+                user_id: id(),
+                valid: true,
+            },
+            AnalyticsEvent::SessionStarted {
+                session_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::SessionEnded {
+                session_id: id(),
+                user_id: id(),
+                duration_seconds: 1800,
+                reason: Some("logout".to_string()),
+            },
+            AnalyticsEvent::AuthLoginAttemptsCompacted {
+                user_id: None,
+                email: "user@example.com".to_string(),
+                attempt_count: 2,
+                failure_count: 1,
+                last_error: None,
+                first_attempt_at: now,
+                last_attempt_at: now,
+            },
+            AnalyticsEvent::TaskCreated {
+                task_id: id(),
+                user_id: id(),
+                project_id: None,
+                cocoon_id: None,
+                command: text(),
+            },
+            AnalyticsEvent::TaskStarted {
+                task_id: id(),
+                user_id: id(),
+                cocoon_id: None,
+            },
+            AnalyticsEvent::TaskProgress {
+                task_id: id(),
+                user_id: id(),
+                percent: Some(50),
+                stage: None,
+                message: None,
+            },
+            AnalyticsEvent::TaskCompleted {
+                task_id: id(),
+                user_id: id(),
+                duration_ms: DurationMs(10),
+                exit_code: 0,
+            },
+            AnalyticsEvent::TaskFailed {
+                task_id: id(),
+                user_id: id(),
+                duration_ms: None,
+                exit_code: Some(1),
+                error: text(),
+            },
+            AnalyticsEvent::TaskCancelled {
+                task_id: id(),
+                user_id: id(),
+                duration_ms: None,
+            },
+            AnalyticsEvent::IntegrationConnected {
+                integration_id: id(),
+                user_id: id(),
+                provider: Provider::GitHub,
+                project_id: None,
+            },
+            AnalyticsEvent::IntegrationDisconnected {
+                integration_id: id(),
+                user_id: id(),
+                provider: Provider::GitHub,
+                reason: None,
+            },
+            AnalyticsEvent::IntegrationReauthorized {
+                integration_id: id(),
+                user_id: id(),
+                provider: Provider::GitHub,
+            },
+            AnalyticsEvent::IntegrationUsed {
+                integration_id: id(),
+                user_id: id(),
+                provider: Provider::GitHub,
+                action: text(),
+            },
+            AnalyticsEvent::IntegrationError {
+                integration_id: id(),
+                user_id: id(),
+                provider: Provider::GitHub,
+                error: text(),
+            },
+            AnalyticsEvent::OAuthFlowStarted {
+                user_id: id(),
+                provider: Provider::GitHub,
+                state: text(),
+            },
+            AnalyticsEvent::OAuthFlowCompleted {
+                user_id: id(),
+                provider: Provider::GitHub,
+                success: true,
+                error: None,
+            },
+            AnalyticsEvent::WebhookReceived {
+                integration_id: None,
+                provider: Provider::GitHub,
+                event_type: text(),
+                delivery_id: text(),
+            },
+            AnalyticsEvent::WebhookProcessed {
+                integration_id: None,
+                provider: Provider::GitHub,
+                event_type: text(),
+                delivery_id: text(),
+                success: true,
+                duration_ms: DurationMs(10),
+                error: None,
+                retry_count: 0,
+            },
+            AnalyticsEvent::WebhookRetryScheduled {
+                delivery_id: text(),
+                provider: Provider::GitHub,
+                attempt: 1,
+                next_attempt_at: now,
+            },
+            AnalyticsEvent::CocoonRegistered {
+                cocoon_id: id(),
+                user_id: id(),
+                device_name: None,
+            },
+            AnalyticsEvent::CocoonConnected {
+                cocoon_id: id(),
+                user_id: None,
+            },
+            AnalyticsEvent::CocoonDisconnected {
+                cocoon_id: id(),
+                user_id: None,
+                duration_seconds: 60,
+            },
+            AnalyticsEvent::CocoonHeartbeat {
+                cocoon_id: id(),
+                user_id: None,
+                uptime_seconds: 60,
+                cpu_percent: Some(12.5),
+                mem_bytes: None,
+            },
+            AnalyticsEvent::CocoonClaimed {
+                cocoon_id: id(),
+                user_id: id(),
+                via_setup_token: false,
+            },
+            AnalyticsEvent::CocoonSetupTokenCreated {
+                token_id: id(),
+                user_id: id(),
+                cocoon_name: None,
+            },
+            AnalyticsEvent::CocoonSetupTokenUsed {
+                token_id: id(),
+                cocoon_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::ProjectCreated {
+                project_id: id(),
+                user_id: id(),
+                name: text(),
+            },
+            AnalyticsEvent::ProjectUpdated {
+                project_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::ProjectDeleted {
+                project_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::ProjectArchived {
+                project_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::ProjectRestored {
+                project_id: id(),
+                user_id: id(),
+            },
+            AnalyticsEvent::ApiRequest {
+                service: text(),
+                endpoint: "/sample".to_string(),
+                method: "GET".to_string(),
+                status_code: 200,
+                duration_ms: DurationMs(10),
+                user_id: None,
+            },
+            AnalyticsEvent::ProxyRequest {
+                proxy_token_id: id(),
+                user_id: id(),
+                request_id: text(),
+                upstream_request_id: None,
+                requested_model: None,
+                actual_model: None,
+                provider_type: text(),
+                key_mode: text(),
+                endpoint: text(),
+                input_tokens: None,
+                output_tokens: None,
+                total_tokens: None,
+                reported_cost_usd: None,
+                latency_ms: 10,
+                ttft_ms: None,
+                is_streaming: false,
+                status: text(),
+                status_code: Some(200),
+                error_type: None,
+                error_message: None,
+            },
+            AnalyticsEvent::DatabaseQuery {
+                service: text(),
+                query_type: "select".to_string(),
+                duration_ms: DurationMs(10),
+                rows_affected: None,
+                statement_fingerprint: None,
+                is_slow: None,
+            },
+            AnalyticsEvent::ApplicationError {
+                service: text(),
+                error_type: text(),
+                error_message: text(),
+                user_id: None,
+                context: None,
+                context_ref: None,
+            },
+            AnalyticsEvent::BalanceCreated {
+                user_id: id(),
+                balance_id: id(),
+            },
+            AnalyticsEvent::BalanceDeposit {
+                user_id: id(),
+                transaction_id: id(),
+                amount: 100,
+                reference_type: None,
+            },
+            AnalyticsEvent::BalanceDebit {
+                user_id: id(),
+                transaction_id: id(),
+                amount: 100,
+                reference_type: None,
+            },
+            AnalyticsEvent::BalanceInsufficient {
+                user_id: id(),
+                requested_amount: 100,
+                current_balance: 0,
+                reference_type: None,
+            },
+            AnalyticsEvent::EventSummary {
+                event_type: "api_request".to_string(),
+                key: text(),
+                count: 1,
+                p50_duration_ms: None,
+                p95_duration_ms: None,
+                window_start: now,
+                window_end: now,
+            },
+            AnalyticsEvent::CocoonChurn {
+                window_seconds: 60,
+                connects: 2,
+                disconnects: 1,
+            },
+            AnalyticsEvent::PipelineStats {
+                enqueued: 1,
+                dropped: 0,
+                sent: 1,
+                failed: 0,
+                interval_secs: 60,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webhook_processed_retry_count_defaults_to_zero() {
+        let json = serde_json::json!({
+            "type": "webhook_processed",
+            "integration_id": null,
+            "provider": "github",
+            "event_type": "push",
+            "delivery_id": "abc",
+            "success": true,
+            "duration_ms": 12,
+            "error": null
+        });
+
+        let event: AnalyticsEvent = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            event,
+            AnalyticsEvent::WebhookProcessed { retry_count: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_webhook_retry_scheduled_event_type() {
+        let event = AnalyticsEvent::WebhookRetryScheduled {
+            delivery_id: "abc".to_string(),
+            provider: Provider::GitHub,
+            attempt: 2,
+            next_attempt_at: Utc::now(),
+        };
+        assert_eq!(event.event_type(), "webhook_retry_scheduled");
+        assert_eq!(event.user_id(), None);
+    }
+
+    #[test]
+    fn test_project_lifecycle_events() {
+        let (project_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let archived = AnalyticsEvent::ProjectArchived {
+            project_id,
+            user_id,
+        };
+        let restored = AnalyticsEvent::ProjectRestored {
+            project_id,
+            user_id,
+        };
+
+        assert_eq!(archived.event_type(), "project_archived");
+        assert_eq!(restored.event_type(), "project_restored");
+        assert_eq!(archived.user_id(), Some(user_id));
+
+        // Older clients only ever sent project_updated, which still parses
+        let json = serde_json::json!({
+            "type": "project_updated",
+            "project_id": project_id,
+            "user_id": user_id
+        });
+        let event: AnalyticsEvent = serde_json::from_value(json).unwrap();
+        assert!(matches!(event, AnalyticsEvent::ProjectUpdated { .. }));
+    }
+
+    #[test]
+    fn test_session_lifecycle_events() {
+        let (session_id, user_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let started = AnalyticsEvent::SessionStarted {
+            session_id,
+            user_id,
+        };
+        let ended = AnalyticsEvent::SessionEnded {
+            session_id,
+            user_id,
+            duration_seconds: 1800,
+            reason: Some("timeout".to_string()),
+        };
+
+        assert_eq!(started.event_type(), "session_started");
+        assert_eq!(ended.event_type(), "session_ended");
+        assert_eq!(ended.user_id(), Some(user_id));
+        assert_eq!(started.session_id(), Some(session_id));
+        assert_eq!(ended.session_id(), Some(session_id));
+        assert_eq!(
+            AnalyticsEvent::AuthSessionValidated {
+                user_id,
+                valid: true
+            }
+            .session_id(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_integration_reauthorized_event_type() {
+        let user_id = Uuid::new_v4();
+        let event = AnalyticsEvent::IntegrationReauthorized {
+            integration_id: Uuid::new_v4(),
+            user_id,
+            provider: Provider::GitHub,
+        };
+        assert_eq!(event.event_type(), "integration_reauthorized");
+        assert_eq!(event.user_id(), Some(user_id));
+    }
+
+    #[test]
+    fn test_estimated_size_matches_serialized_len() {
+        let large_context = EnrichedEvent::new(AnalyticsEvent::ApplicationError {
+            service: "api".to_string(),
+            error_type: "timeout".to_string(),
+            error_message: "upstream \"timed\" out\n".repeat(50),
+            user_id: None,
+            context: Some(serde_json::json!({ "items": vec!["é"; 1000] })),
+            context_ref: None,
+        });
+
+        for event in samples::all_variants()
+            .into_iter()
+            .map(EnrichedEvent::new)
+            .chain([large_context])
+        {
+            let actual = serde_json::to_vec(&event).unwrap().len();
+            assert_eq!(
+                event.estimated_size(),
+                actual,
+                "{}",
+                event.event.event_type()
+            );
+        }
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_api_request_from_parts() {
+        let user_id = Uuid::new_v4();
+        let (parts, _body) = http::Request::builder()
+            .method(http::Method::PATCH)
+            .uri("https://api.example.com/projects/42?fields=name")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let event = AnalyticsEvent::api_request_from_parts(
+            "api",
+            &parts,
+            http::StatusCode::NO_CONTENT,
+            std::time::Duration::from_millis(35),
+            Some(user_id),
+        );
+        match event {
+            AnalyticsEvent::ApiRequest {
+                service,
+                endpoint,
+                method,
+                status_code,
+                duration_ms,
+                user_id: event_user_id,
+            } => {
+                assert_eq!(service, "api");
+                assert_eq!(endpoint, "/projects/42");
+                assert_eq!(method, "PATCH");
+                assert_eq!(status_code, 204);
+                assert_eq!(duration_ms, DurationMs(35));
+                assert_eq!(event_user_id, Some(user_id));
+            }
+            other => panic!("unexpected event {:?}", other),
         }
     }
 }
@@ -0,0 +1,300 @@
+use crate::events::AnalyticsEvent;
+use serde::ser::{self, Impossible, Serialize, SerializeStruct, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use uuid::Uuid;
+
+/// Typed value of one field in [`AnalyticsEvent::as_fields`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum FieldValue {
+    /// Text, including timestamps (RFC 3339) and enum names (`github`)
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Uuid(Uuid),
+    Bool(bool),
+    /// Nested data, e.g. `ApplicationError::context`
+    Json(Value),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Str(value) => f.write_str(value),
+            FieldValue::Int(value) => write!(f, "{}", value),
+            FieldValue::Float(value) => write!(f, "{}", value),
+            FieldValue::Uuid(value) => write!(f, "{}", value),
+            FieldValue::Bool(value) => write!(f, "{}", value),
+            FieldValue::Json(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl FieldValue {
+    /// Classify a serialized field, `None` for null (unset optional fields)
+    fn from_json(value: Value) -> Option<Self> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Bool(value) => FieldValue::Bool(value),
+            Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+                (Some(value), _) => FieldValue::Int(value),
+                (None, Some(value)) if !number.is_u64() => FieldValue::Float(value),
+                _ => FieldValue::Json(Value::Number(number)),
+            },
+            Value::String(value) => match Uuid::parse_str(&value) {
+                Ok(uuid) if value.len() == 36 => FieldValue::Uuid(uuid),
+                _ => FieldValue::Str(value),
+            },
+            value => FieldValue::Json(value),
+        })
+    }
+}
+
+impl AnalyticsEvent {
+    /// The variant's fields as a flat map of typed values
+    ///
+    /// For sinks that want flat key-values rather than nested JSON (logfmt,
+    /// statsd tags, structured logging). Keys are the serialized field names;
+    /// the `type` tag is left out (see [`event_type`](Self::event_type)) and
+    /// so are unset optional fields. UUID-shaped strings become
+    /// [`FieldValue::Uuid`].
+    pub fn as_fields(&self) -> BTreeMap<&'static str, FieldValue> {
+        let mut fields = BTreeMap::new();
+        // Every variant is a struct, which the collector accepts
+        let _ = self.serialize(FieldCollector {
+            fields: &mut fields,
+        });
+        fields
+    }
+}
+
+/// Serializer taking the fields of a struct apart
+struct FieldCollector<'a> {
+    fields: &'a mut BTreeMap<&'static str, FieldValue>,
+}
+
+fn unsupported() -> serde_json::Error {
+    ser::Error::custom("only structs can be flattened into fields")
+}
+
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<(), serde_json::Error> {
+                Err(unsupported())
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for FieldCollector<'a> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Impossible<(), serde_json::Error>;
+    type SerializeTuple = Impossible<(), serde_json::Error>;
+    type SerializeTupleStruct = Impossible<(), serde_json::Error>;
+    type SerializeTupleVariant = Impossible<(), serde_json::Error>;
+    type SerializeMap = Impossible<(), serde_json::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), serde_json::Error>;
+
+    reject!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    );
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<(), Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(unsupported())
+    }
+}
+
+impl SerializeStruct for FieldCollector<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        if key == "type" {
+            return Ok(());
+        }
+        if let Some(value) = FieldValue::from_json(serde_json::to_value(value)?) {
+            self.fields.insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::DurationMs;
+    use crate::provider::Provider;
+
+    #[test]
+    fn test_api_request_fields() {
+        let user_id = Uuid::new_v4();
+        let event = AnalyticsEvent::ApiRequest {
+            service: "platform".to_string(),
+            endpoint: "/projects".to_string(),
+            method: "GET".to_string(),
+            status_code: 200,
+            duration_ms: DurationMs(42),
+            user_id: Some(user_id),
+        };
+
+        let fields = event.as_fields();
+        assert_eq!(fields["service"], FieldValue::Str("platform".to_string()));
+        assert_eq!(fields["status_code"], FieldValue::Int(200));
+        assert_eq!(fields["duration_ms"], FieldValue::Int(42));
+        assert_eq!(fields["user_id"], FieldValue::Uuid(user_id));
+        assert!(!fields.contains_key("type"));
+    }
+
+    #[test]
+    fn test_optional_and_nested_fields() {
+        let event = AnalyticsEvent::ApplicationError {
+            service: "gateway".to_string(),
+            error_type: "upstream".to_string(),
+            error_message: "bad gateway".to_string(),
+            user_id: None,
+            context: Some(serde_json::json!({ "attempt": 3 })),
+            context_ref: None,
+        };
+
+        let fields = event.as_fields();
+        assert!(!fields.contains_key("user_id"));
+        assert_eq!(
+            fields["context"],
+            FieldValue::Json(serde_json::json!({ "attempt": 3 }))
+        );
+        assert_eq!(fields["context"].to_string(), r#"{"attempt":3}"#);
+    }
+
+    #[test]
+    fn test_bool_float_and_enum_fields() {
+        let heartbeat = AnalyticsEvent::CocoonHeartbeat {
+            cocoon_id: Uuid::new_v4(),
+            user_id: None,
+            uptime_seconds: 60,
+            cpu_percent: Some(12.5),
+            mem_bytes: Some(1024),
+        };
+        assert_eq!(
+            heartbeat.as_fields()["cpu_percent"],
+            FieldValue::Float(12.5)
+        );
+
+        let validated = AnalyticsEvent::AuthSessionValidated {
+            user_id: Uuid::new_v4(),
+            valid: true,
+        };
+        assert_eq!(validated.as_fields()["valid"], FieldValue::Bool(true));
+
+        let reauthorized = AnalyticsEvent::IntegrationReauthorized {
+            integration_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            provider: Provider::GitHub,
+        };
+        let fields = reauthorized.as_fields();
+        assert_eq!(fields.len(), 3);
+        assert!(matches!(fields["provider"], FieldValue::Str(_)));
+    }
+
+    #[test]
+    fn test_every_variant_flattens() {
+        for event in crate::events::samples::all_variants() {
+            assert!(!event.as_fields().is_empty(), "{}", event.event_type());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::events::AnalyticsEvent;
+use std::fmt;
+use std::sync::Arc;
+
+type PredicateFn = dyn Fn(&AnalyticsEvent) -> bool + Send + Sync;
+
+/// Predicate deciding centrally which events a client tracks
+///
+/// Events for which the predicate returns `false` are dropped as soon as they
+/// are tracked, before validation and enrichment, and counted in
+/// [`MetricsSnapshot::filtered_events`](crate::MetricsSnapshot::filtered_events).
+/// The predicate sees every field, so it can go finer than whole event types:
+///
+/// ```rust
+/// use lib_analytics_core::{AnalyticsEvent, ClientConfig, EventFilter};
+///
+/// let config = ClientConfig {
+///     filter: Some(EventFilter::new(|event| match event {
+///         AnalyticsEvent::DatabaseQuery { .. } => false,
+///         AnalyticsEvent::ApiRequest { status_code, .. } => *status_code >= 300,
+///         _ => true,
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct EventFilter {
+    predicate: Arc<PredicateFn>,
+}
+
+impl EventFilter {
+    /// Keep only the events `predicate` returns `true` for
+    pub fn new(predicate: impl Fn(&AnalyticsEvent) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            predicate: Arc::new(predicate),
+        }
+    }
+
+    /// Whether `event` should be tracked
+    pub(crate) fn allows(&self, event: &AnalyticsEvent) -> bool {
+        (self.predicate)(event)
+    }
+}
+
+impl fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFilter").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,89 @@
+//! Optional process-wide client
+//!
+//! Convenience for code where threading an [`AnalyticsClient`] through
+//! constructors is impractical. Passing the client explicitly remains the
+//! recommended approach: it keeps dependencies visible and lets tests use
+//! their own clients. Install the global client once at startup:
+//!
+//! ```rust,no_run
+//! use lib_analytics_core::global as analytics;
+//! use lib_analytics_core::{AnalyticsClient, AnalyticsEvent};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     analytics::init(AnalyticsClient::new("http://localhost:8094")).unwrap();
+//!
+//!     analytics::track(AnalyticsEvent::AuthSessionValidated {
+//!         user_id: uuid::Uuid::new_v4(),
+//!         valid: true,
+//!     });
+//! }
+//! ```
+//!
+//! Tracking before [`init`] silently drops the event.
+
+use crate::client::AnalyticsClient;
+use crate::error::{AnalyticsError, Result};
+use crate::events::AnalyticsEvent;
+use std::sync::OnceLock;
+
+static CLIENT: OnceLock<AnalyticsClient> = OnceLock::new();
+
+/// Install the global client
+///
+/// Returns `AnalyticsError::AlreadyInitialized` if a client was installed
+/// before; the existing client is kept.
+pub fn init(client: AnalyticsClient) -> Result<()> {
+    CLIENT
+        .set(client)
+        .map_err(|_| AnalyticsError::AlreadyInitialized)
+}
+
+/// The global client, if [`init`] has been called
+pub fn client() -> Option<&'static AnalyticsClient> {
+    CLIENT.get()
+}
+
+/// Track an event with the global client
+///
+/// No-op if no global client has been installed.
+pub fn track(event: impl Into<AnalyticsEvent>) {
+    if let Some(client) = client() {
+        client.track(event);
+    }
+}
+
+/// Track an event with the global client only if a condition is true
+pub fn track_if(condition: bool, event: impl Into<AnalyticsEvent>) {
+    if condition {
+        track(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The global is shared by all tests in the binary, so this is the only
+    // test that initializes it.
+    #[tokio::test]
+    async fn test_init_once() {
+        let event = AnalyticsEvent::AuthSessionValidated {
+            user_id: uuid::Uuid::new_v4(),
+            valid: true,
+        };
+
+        // No-op before init
+        track(event.clone());
+
+        let (global, _stream) = AnalyticsClient::channel();
+        assert!(init(global).is_ok());
+        assert!(client().is_some());
+        assert!(matches!(
+            init(AnalyticsClient::channel().0),
+            Err(AnalyticsError::AlreadyInitialized)
+        ));
+
+        track_if(true, event);
+    }
+}
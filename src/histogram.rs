@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds of the histogram buckets in milliseconds; slower sends land
+/// in a final overflow bucket
+const BUCKET_BOUNDS_MS: [u64; 14] = [
+    1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 30_000,
+];
+
+/// Percentiles of batch send durations since the client was created
+///
+/// Part of [`MetricsSnapshot`](crate::MetricsSnapshot) with the
+/// `latency-histogram` feature. Durations are bucketed, so each percentile is
+/// the upper bound of its bucket (1ms up to 30s); sends slower than 30s are
+/// reported as `Duration::MAX`. Percentiles are `None` before the first send.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SendLatency {
+    pub p50: Option<Duration>,
+    pub p95: Option<Duration>,
+    pub p99: Option<Duration>,
+    /// Timed sends that succeeded
+    pub successful: u64,
+    /// Timed sends that failed
+    pub failed: u64,
+}
+
+/// Lock-free histogram of send durations
+#[derive(Debug, Default)]
+pub(crate) struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKET_BOUNDS_MS.len() + 1],
+    successful: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl LatencyHistogram {
+    /// Record one send from request start to response or error
+    pub(crate) fn record(&self, elapsed: Duration, success: bool) {
+        let millis = elapsed.as_millis();
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| millis <= u128::from(*bound))
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+
+        let outcome = if success {
+            &self.successful
+        } else {
+            &self.failed
+        };
+        outcome.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> SendLatency {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+
+        SendLatency {
+            p50: percentile(&counts, 50),
+            p95: percentile(&counts, 95),
+            p99: percentile(&counts, 99),
+            successful: self.successful.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Upper bound of the bucket holding the nearest-rank percentile
+fn percentile(counts: &[u64], p: u64) -> Option<Duration> {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let rank = (p * total).div_ceil(100).max(1);
+    let mut seen = 0;
+    for (bucket, count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Some(match BUCKET_BOUNDS_MS.get(bucket) {
+                Some(bound) => Duration::from_millis(*bound),
+                None => Duration::MAX,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.snapshot(), SendLatency::default());
+
+        for _ in 0..90 {
+            histogram.record(Duration::from_millis(8), true);
+        }
+        for _ in 0..9 {
+            histogram.record(Duration::from_millis(150), true);
+        }
+        histogram.record(Duration::from_secs(60), false);
+
+        let latency = histogram.snapshot();
+        assert_eq!(latency.p50, Some(Duration::from_millis(10)));
+        assert_eq!(latency.p95, Some(Duration::from_millis(200)));
+        assert_eq!(latency.p99, Some(Duration::from_millis(200)));
+        assert_eq!((latency.successful, latency.failed), (99, 1));
+
+        histogram.record(Duration::from_secs(60), false);
+        assert_eq!(histogram.snapshot().p99, Some(Duration::MAX));
+    }
+}
@@ -0,0 +1,331 @@
+//! `tower` middleware tracking an `ApiRequest` event per request
+
+use crate::client::AnalyticsClient;
+use crate::duration::DurationMs;
+use crate::events::AnalyticsEvent;
+use http::request::Parts;
+use http::{Request, Response};
+use pin_project_lite::pin_project;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+use uuid::Uuid;
+
+/// Request extension naming the authenticated user, picked up by
+/// [`AnalyticsLayer`] as the event's `user_id`
+///
+/// Insert it from the authentication middleware or extractor:
+/// `request.extensions_mut().insert(AnalyticsUser(user.id))`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnalyticsUser(pub Uuid);
+
+/// Reads a value for the event from the request head
+type PartsFn<T> = Arc<dyn Fn(&Parts) -> Option<T> + Send + Sync>;
+
+/// [`Layer`] tracking an [`AnalyticsEvent::ApiRequest`] for every request
+/// of the wrapped service
+///
+/// Each event carries the layer's service name, the request method, the
+/// route, the response status and the time until the response (headers)
+/// was ready; streaming the body afterwards is not included. Requests the
+/// inner service fails with an error instead of a response are not tracked.
+///
+/// The route defaults to the URI path. Prefer the matched route template so
+/// `/projects/123` and `/projects/456` count as one endpoint, e.g. axum's
+/// `MatchedPath`, and take the user from whatever extension the
+/// authentication layer sets (by default [`AnalyticsUser`]):
+///
+/// ```rust,ignore
+/// use axum::extract::MatchedPath;
+/// use lib_analytics_core::{AnalyticsClient, AnalyticsLayer};
+///
+/// let analytics = AnalyticsClient::from_env()?;
+/// let app = Router::new()
+///     .route("/projects/:id", get(get_project))
+///     // Added after `route`, so it sees `MatchedPath`
+///     .layer(
+///         AnalyticsLayer::new(analytics, "platform-api")
+///             .route(|parts| {
+///                 let path = parts.extensions.get::<MatchedPath>()?;
+///                 Some(path.as_str().to_string())
+///             })
+///             .user_id(|parts| parts.extensions.get::<CurrentUser>().map(|user| user.id)),
+///     )
+///     // Outermost, so its extensions are set before the analytics layer runs
+///     .layer(auth_layer);
+/// ```
+///
+/// Requires the `tower` feature.
+#[derive(Clone)]
+pub struct AnalyticsLayer {
+    client: AnalyticsClient,
+    service: Arc<str>,
+    route: PartsFn<String>,
+    user_id: PartsFn<Uuid>,
+}
+
+impl AnalyticsLayer {
+    /// Track requests with `client`, naming `service` in every event
+    pub fn new(client: AnalyticsClient, service: impl Into<String>) -> Self {
+        Self {
+            client,
+            service: service.into().into(),
+            route: Arc::new(|parts| Some(parts.uri.path().to_string())),
+            user_id: Arc::new(|parts| parts.extensions.get::<AnalyticsUser>().map(|user| user.0)),
+        }
+    }
+
+    /// Derive the event's `endpoint` with `route`, falling back to the URI
+    /// path where it returns `None`
+    pub fn route(
+        mut self,
+        route: impl Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.route = Arc::new(route);
+        self
+    }
+
+    /// Derive the event's `user_id` with `user_id` instead of from the
+    /// [`AnalyticsUser`] extension
+    pub fn user_id(
+        mut self,
+        user_id: impl Fn(&Parts) -> Option<Uuid> + Send + Sync + 'static,
+    ) -> Self {
+        self.user_id = Arc::new(user_id);
+        self
+    }
+}
+
+impl fmt::Debug for AnalyticsLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnalyticsLayer")
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for AnalyticsLayer {
+    type Service = AnalyticsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AnalyticsService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service produced by [`AnalyticsLayer`]
+#[derive(Clone, Debug)]
+pub struct AnalyticsService<S> {
+    inner: S,
+    layer: AnalyticsLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for AnalyticsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        let pending = PendingRequest {
+            client: self.layer.client.clone(),
+            service: self.layer.service.clone(),
+            method: parts.method.to_string(),
+            endpoint: (self.layer.route)(&parts).unwrap_or_else(|| parts.uri.path().to_string()),
+            user_id: (self.layer.user_id)(&parts),
+            started: Instant::now(),
+        };
+
+        ResponseFuture {
+            inner: self.inner.call(Request::from_parts(parts, body)),
+            pending: Some(pending),
+        }
+    }
+}
+
+/// Request details captured before calling the inner service
+struct PendingRequest {
+    client: AnalyticsClient,
+    service: Arc<str>,
+    method: String,
+    endpoint: String,
+    user_id: Option<Uuid>,
+    started: Instant,
+}
+
+impl PendingRequest {
+    fn track(self, status_code: u16) {
+        self.client.track(AnalyticsEvent::ApiRequest {
+            service: self.service.to_string(),
+            endpoint: self.endpoint,
+            method: self.method,
+            status_code,
+            duration_ms: DurationMs::from_std(self.started.elapsed()),
+            user_id: self.user_id,
+        });
+    }
+}
+
+pin_project! {
+    /// Response future of [`AnalyticsService`]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        pending: Option<PendingRequest>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = std::task::ready!(this.inner.poll(cx));
+
+        if let (Ok(response), Some(pending)) = (&result, this.pending.take()) {
+            pending.track(response.status().as_u16());
+        }
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::future::{ready, Ready};
+
+    /// Answers every request with the status in its `x-status` header
+    #[derive(Clone)]
+    struct StatusService;
+
+    impl Service<Request<()>> for StatusService {
+        type Response = Response<()>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<()>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            let status: u16 = request.headers()["x-status"]
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            let response = Response::builder().status(status).body(()).unwrap();
+            ready(Ok(response))
+        }
+    }
+
+    fn request(method: &str, uri: &str, status: u16) -> Request<()> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-status", status)
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tracks_api_request() {
+        let (client, handle) = AnalyticsClient::recording();
+        let mut service = AnalyticsLayer::new(client.clone(), "api").layer(StatusService);
+        let user_id = Uuid::new_v4();
+
+        let mut authenticated = request("POST", "/projects?draft=1", 201);
+        authenticated
+            .extensions_mut()
+            .insert(AnalyticsUser(user_id));
+        service.call(authenticated).await.unwrap();
+        service
+            .call(request("GET", "/projects/123", 404))
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+
+        let events = handle.events();
+        assert_eq!(events.len(), 2);
+        match &events[0].event {
+            AnalyticsEvent::ApiRequest {
+                service,
+                endpoint,
+                method,
+                status_code,
+                user_id: tracked_user,
+                ..
+            } => {
+                assert_eq!(service, "api");
+                assert_eq!(endpoint, "/projects");
+                assert_eq!(method, "POST");
+                assert_eq!(*status_code, 201);
+                assert_eq!(*tracked_user, Some(user_id));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(
+            &events[1].event,
+            AnalyticsEvent::ApiRequest {
+                status_code: 404,
+                user_id: None,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_custom_route_and_user() {
+        let (client, handle) = AnalyticsClient::recording();
+        let user_id = Uuid::new_v4();
+        let mut service = AnalyticsLayer::new(client.clone(), "api")
+            .route(|parts| {
+                let path = parts.uri.path();
+                path.starts_with("/projects/")
+                    .then(|| "/projects/:id".to_string())
+            })
+            .user_id(move |_| Some(user_id))
+            .layer(StatusService);
+
+        service
+            .call(request("GET", "/projects/123", 200))
+            .await
+            .unwrap();
+        service.call(request("GET", "/health", 200)).await.unwrap();
+        client.flush().await.unwrap();
+
+        let endpoints: Vec<_> = handle
+            .events()
+            .into_iter()
+            .map(|event| match event.event {
+                AnalyticsEvent::ApiRequest {
+                    endpoint,
+                    user_id: tracked_user,
+                    ..
+                } => {
+                    assert_eq!(tracked_user, Some(user_id));
+                    endpoint
+                }
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(endpoints, ["/projects/:id", "/health"]);
+    }
+}
@@ -13,7 +13,11 @@
 //!     let analytics_url = std::env::var("ANALYTICS_URL")
 //!         .unwrap_or_else(|_| "http://localhost:8094".to_string());
 //!
-//!     let client = AnalyticsClient::new(analytics_url);
+//!     let client = AnalyticsClient::builder()
+//!         .url(analytics_url)
+//!         .batch_size(50)
+//!         .build()
+//!         .expect("valid analytics configuration");
 //!
 //!     // Track events (non-blocking, batched automatically)
 //!     client.track(AnalyticsEvent::AuthLoginAttempt {
@@ -24,11 +28,114 @@
 //!     });
 //! }
 //! ```
+//!
+//! [`AnalyticsClient::builder`] is the preferred way to create a client; see
+//! [`AnalyticsClientBuilder`] for all options. Services can also configure the client from `ANALYTICS_*` environment
+//! variables with [`AnalyticsClient::from_env`].
 
+mod aggregate;
+mod backfill;
+mod batch;
+#[cfg(feature = "blob-upload")]
+mod blob;
+mod builder;
+mod churn;
 mod client;
+mod clock;
+mod compact;
+mod compression;
+mod config;
+mod dry_run;
+mod duration;
+mod environment;
 mod error;
 mod events;
+mod fields;
+mod filter;
+pub mod global;
+#[cfg(feature = "latency-histogram")]
+mod histogram;
+#[cfg(feature = "tower")]
+mod layer;
+mod metrics;
+mod middleware;
+#[cfg(feature = "test-util")]
+mod mock;
+mod normalize;
+mod panic;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+mod provider;
+mod queue;
+mod recording;
+mod retention;
+mod sampling;
+mod sanitize;
+mod schema;
+mod scope;
+mod serializer;
+#[cfg(feature = "signal")]
+mod signal;
+mod sink;
+mod sql;
+mod state;
+mod throttle;
+mod validation;
+mod worker;
 
+pub use aggregate::Aggregation;
+pub use backfill::{
+    BackfillOptions, BackfillProgress, BackfillReport, BulkOptions, ChunkReport,
+    DEFAULT_BACKFILL_CHUNK_BYTES,
+};
+pub use batch::{Adaptive, BatchPolicy, BatchState, CountOrInterval};
+#[cfg(feature = "blob-upload")]
+pub use blob::{BlobUploader, DEFAULT_BLOB_THRESHOLD};
+pub use builder::AnalyticsClientBuilder;
 pub use client::AnalyticsClient;
-pub use error::{AnalyticsError, Result};
+pub use compression::Compression;
+#[cfg(feature = "attachments")]
+pub use config::DEFAULT_MAX_ATTACHMENT_BYTES;
+pub use config::{
+    ClientConfig, DEFAULT_BATCH_PATH, DEFAULT_BATCH_SIZE, DEFAULT_CLOCK_SKEW_THRESHOLD,
+    DEFAULT_COMPRESS_MIN_BYTES, DEFAULT_FLUSH_INTERVAL, DEFAULT_MAX_CONCURRENT_SENDS,
+    DEFAULT_MAX_FIELD_LEN, DEFAULT_POOL_IDLE_TIMEOUT, DEFAULT_REQUEST_TIMEOUT,
+};
+pub use duration::DurationMs;
+pub use environment::Environment;
+pub use error::{AnalyticsError, Result, ValidationError};
 pub use events::{AnalyticsEvent, EnrichedEvent};
+pub use fields::FieldValue;
+pub use filter::EventFilter;
+#[cfg(feature = "latency-histogram")]
+pub use histogram::SendLatency;
+#[cfg(feature = "tower")]
+pub use layer::{AnalyticsLayer, AnalyticsService, AnalyticsUser, ResponseFuture};
+pub use metrics::{BatchOutcome, MetricsSnapshot, ShutdownReport};
+pub use middleware::EnrichMiddleware;
+#[cfg(feature = "test-util")]
+pub use mock::{MockIngestServer, MockRequest};
+pub use normalize::{ActionNormalizer, CanonicalAction};
+pub use panic::install_panic_hook;
+#[cfg(feature = "protobuf")]
+pub use protobuf::ProtobufSerializer;
+pub use provider::Provider;
+pub use recording::{RecordingHandle, RecordingSink};
+pub use retention::RetentionClass;
+pub use sampling::{SampleKey, Sampling};
+pub use sanitize::{ContextFilter, UserIdPolicy, TRUNCATION_MARKER};
+pub use scope::{ScopeContext, ScopedClient};
+pub use serializer::{
+    BatchSerializer, Envelope, EnvelopeSerializer, FieldCase, JsonSerializer, NdjsonSerializer,
+    TimestampFormat,
+};
+#[cfg(feature = "signal")]
+pub use signal::install_shutdown_flush;
+#[cfg(feature = "durable")]
+pub use sink::DurableBuffer;
+pub use sink::{EventSink, TeeSink};
+#[cfg(feature = "kafka")]
+pub use sink::{KafkaKeyFn, KafkaSink};
+#[cfg(feature = "ws")]
+pub use sink::{WebSocketOptions, WebSocketSink};
+pub use sql::{normalize_sql, sql_fingerprint};
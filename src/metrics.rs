@@ -0,0 +1,324 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Point-in-time view of a client's delivery pipeline
+///
+/// Returned by [`AnalyticsClient::metrics`](crate::AnalyticsClient::metrics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MetricsSnapshot {
+    /// Batches currently being handed to the sink
+    pub in_flight_sends: usize,
+    /// Events waiting in the queue for the background task
+    pub queued_events: usize,
+    /// Events in the background task's current batch, not yet sent
+    pub buffered_events: usize,
+    /// Events discarded for being older than `max_event_age`, since the
+    /// client was created
+    pub dropped_stale_events: u64,
+    /// Events rejected by the client's `filter`, since the client was created
+    pub filtered_events: u64,
+    /// Events whose `user_id` was removed by the client's `user_id_policy`,
+    /// since the client was created
+    pub stripped_user_ids: u64,
+    /// Events left out by the client's `sampling`, since the client was
+    /// created
+    pub sampled_out_events: u64,
+    /// Durations of batch sends to the sink. Requires the
+    /// `latency-histogram` feature.
+    #[cfg(feature = "latency-histogram")]
+    pub send_latency: crate::SendLatency,
+}
+
+impl MetricsSnapshot {
+    /// Nothing queued, buffered or being sent (drop counts are ignored)
+    ///
+    /// Events held back by aggregations or login compaction until their
+    /// window closes are not counted.
+    pub fn is_idle(&self) -> bool {
+        self.in_flight_sends == 0 && self.queued_events == 0 && self.buffered_events == 0
+    }
+}
+
+/// Delivery summary over a client's lifetime
+///
+/// Returned by [`AnalyticsClient::shutdown`](crate::AnalyticsClient::shutdown)
+/// once the final batch was sent, whether or not that send succeeded. Counts
+/// cover all clones of the client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ShutdownReport {
+    /// Events the sink accepted
+    pub flushed: usize,
+    /// Events lost: rejected before the queue (invalid, queue full),
+    /// discarded as stale (`max_event_age`) or part of a batch the sink
+    /// failed to deliver
+    pub dropped: usize,
+    /// Batches the sink failed to deliver, including the final one
+    pub failed_batches: usize,
+    /// Time spent flushing and stopping the background task
+    pub duration: Duration,
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} events flushed, {} dropped, {} failed batches in {:?}",
+            self.flushed, self.dropped, self.failed_batches, self.duration
+        )
+    }
+}
+
+/// Result of handing one batch to the sink
+///
+/// Yielded by [`AnalyticsClient::subscribe_outcomes`](crate::AnalyticsClient::subscribe_outcomes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BatchOutcome {
+    /// Events in the batch
+    pub count: usize,
+    /// Whether the sink accepted the batch
+    pub success: bool,
+    /// HTTP status the ingestion service rejected the batch with; `None`
+    /// for successful batches and failures without a response (e.g.
+    /// connection errors or custom sinks)
+    pub status: Option<u16>,
+    /// Time from handing the batch to the sink until it returned
+    pub latency: Duration,
+}
+
+/// Event counts since the last stats report, lifetime totals and live gauges
+#[derive(Debug, Default)]
+pub(crate) struct PipelineCounters {
+    enqueued: AtomicU64,
+    dropped: AtomicU64,
+    sent: AtomicU64,
+    failed: AtomicU64,
+    /// Lifetime totals, never reset
+    dropped_stale: AtomicU64,
+    filtered: AtomicU64,
+    stripped_user_ids: AtomicU64,
+    sampled_out: AtomicU64,
+    total_sent: AtomicUsize,
+    total_dropped: AtomicUsize,
+    failed_batches: AtomicUsize,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    buffered: AtomicUsize,
+    /// Unix millis of the last successful send, 0 if there was none
+    last_success_ms: AtomicI64,
+    #[cfg(feature = "latency-histogram")]
+    send_latency: crate::histogram::LatencyHistogram,
+}
+
+/// Marks a send as in flight until dropped
+pub(crate) struct InFlightGuard<'a> {
+    in_flight: &'a AtomicUsize,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts taken by [`PipelineCounters::take`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CounterSnapshot {
+    pub(crate) enqueued: u64,
+    pub(crate) dropped: u64,
+    pub(crate) sent: u64,
+    pub(crate) failed: u64,
+}
+
+impl CounterSnapshot {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl PipelineCounters {
+    /// Event accepted into the queue
+    pub(crate) fn record_enqueued(&self) {
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Event rejected before reaching the queue (invalid, queue full or closed)
+    pub(crate) fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        self.total_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Events handed to the sink successfully
+    pub(crate) fn record_sent(&self, count: usize) {
+        self.sent.fetch_add(count as u64, Ordering::Relaxed);
+        self.total_sent.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Events in a batch the sink failed to deliver
+    pub(crate) fn record_failed(&self, count: usize) {
+        self.failed.fetch_add(count as u64, Ordering::Relaxed);
+        self.total_dropped.fetch_add(count, Ordering::Relaxed);
+        self.failed_batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Events discarded before sending for exceeding `max_event_age`
+    pub(crate) fn record_stale(&self, count: usize) {
+        self.dropped_stale
+            .fetch_add(count as u64, Ordering::Relaxed);
+        self.total_dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Event rejected by the client's `filter`
+    ///
+    /// Deliberately not counted as dropped: nothing was lost by accident.
+    pub(crate) fn record_filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `user_id` removed from an event by the client's `user_id_policy`
+    pub(crate) fn record_user_id_stripped(&self) {
+        self.stripped_user_ids.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Event left out by the client's `sampling`, not counted as dropped
+    pub(crate) fn record_sampled_out(&self) {
+        self.sampled_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Event put on the queue to the background task
+    pub(crate) fn record_queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Event taken off the queue; `buffered` is the batch size after handling it
+    ///
+    /// The batch size is updated first so the event is never counted as
+    /// neither queued nor buffered.
+    pub(crate) fn record_dequeued(&self, buffered: usize) {
+        self.set_buffered(buffered);
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Current size of the background task's batch
+    pub(crate) fn set_buffered(&self, buffered: usize) {
+        self.buffered.store(buffered, Ordering::Relaxed);
+    }
+
+    /// A batch was delivered at `at`
+    pub(crate) fn record_success(&self, at: DateTime<Utc>) {
+        self.last_success_ms
+            .store(at.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Time of the last delivered batch
+    pub(crate) fn last_success(&self) -> Option<DateTime<Utc>> {
+        match self.last_success_ms.load(Ordering::Relaxed) {
+            0 => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        }
+    }
+
+    /// Duration of one send from request start to response or error
+    #[cfg(feature = "latency-histogram")]
+    pub(crate) fn record_latency(&self, elapsed: std::time::Duration, success: bool) {
+        self.send_latency.record(elapsed, success);
+    }
+
+    /// Count a send as in flight for the lifetime of the returned guard
+    pub(crate) fn start_send(&self) -> InFlightGuard<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard {
+            in_flight: &self.in_flight,
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            in_flight_sends: self.in_flight.load(Ordering::Relaxed),
+            queued_events: self.queued.load(Ordering::Relaxed),
+            buffered_events: self.buffered.load(Ordering::Relaxed),
+            dropped_stale_events: self.dropped_stale.load(Ordering::Relaxed),
+            filtered_events: self.filtered.load(Ordering::Relaxed),
+            stripped_user_ids: self.stripped_user_ids.load(Ordering::Relaxed),
+            sampled_out_events: self.sampled_out.load(Ordering::Relaxed),
+            #[cfg(feature = "latency-histogram")]
+            send_latency: self.send_latency.snapshot(),
+        }
+    }
+
+    /// Lifetime totals, for a shutdown that took `duration`
+    pub(crate) fn shutdown_report(&self, duration: Duration) -> ShutdownReport {
+        ShutdownReport {
+            flushed: self.total_sent.load(Ordering::Relaxed),
+            dropped: self.total_dropped.load(Ordering::Relaxed),
+            failed_batches: self.failed_batches.load(Ordering::Relaxed),
+            duration,
+        }
+    }
+
+    /// Read and reset all counters (gauges and lifetime totals are kept)
+    pub(crate) fn take(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            enqueued: self.enqueued.swap(0, Ordering::Relaxed),
+            dropped: self.dropped.swap(0, Ordering::Relaxed),
+            sent: self.sent.swap(0, Ordering::Relaxed),
+            failed: self.failed.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_resets() {
+        let counters = PipelineCounters::default();
+        counters.record_enqueued();
+        counters.record_enqueued();
+        counters.record_dropped();
+        counters.record_sent(2);
+
+        let snapshot = counters.take();
+        assert_eq!(snapshot.enqueued, 2);
+        assert_eq!(snapshot.dropped, 1);
+        assert_eq!(snapshot.sent, 2);
+        assert_eq!(snapshot.failed, 0);
+        assert!(counters.take().is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_report_survives_take() {
+        let counters = PipelineCounters::default();
+        counters.record_sent(3);
+        counters.record_dropped();
+        counters.take();
+        counters.record_failed(2);
+
+        let report = counters.shutdown_report(Duration::from_millis(12));
+        assert_eq!(report.flushed, 3);
+        assert_eq!(report.dropped, 3);
+        assert_eq!(report.failed_batches, 1);
+        assert_eq!(
+            report.to_string(),
+            "3 events flushed, 3 dropped, 1 failed batches in 12ms"
+        );
+    }
+
+    #[test]
+    fn test_in_flight_guard() {
+        let counters = PipelineCounters::default();
+
+        let first = counters.start_send();
+        let second = counters.start_send();
+        assert_eq!(counters.snapshot().in_flight_sends, 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(counters.snapshot().in_flight_sends, 0);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::config::ClientConfig;
+use crate::events::EnrichedEvent;
+use crate::metrics::PipelineCounters;
+use crate::normalize::ActionNormalizer;
+use crate::sanitize::{self, ContextFilter, UserIdPolicy};
+use crate::sql;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Step of the enrichment chain run on every tracked event
+///
+/// Every client runs one chain of middlewares, one after another in list
+/// order, each seeing the changes of the ones before it, so e.g. adding
+/// fields, then redacting, then routing (setting `tenant_id` or
+/// `retention`) compose as listed. The chain starts with the built-in steps
+/// configured on the client, in this order:
+///
+/// 1. `context_filter` redacts `ApplicationError::context`
+/// 2. `user_id_policy` strips user ids
+/// 3. `slow_query_threshold` flags slow `DatabaseQuery` events
+/// 4. `max_field_len` truncates free-form fields
+/// 5. `action_normalizer` sets `canonical_action`
+///
+/// followed by [`ClientConfig::enrich_middleware`]. Before the chain, the
+/// event is validated (with `reject_invalid`) and wrapped into an
+/// [`EnrichedEvent`] carrying host metadata, its sequence number and the
+/// `track_at`/`track_for_tenant` overrides; those steps are fixed. Custom
+/// middlewares run after truncation, so their changes are not truncated;
+/// add a [`ContextFilter`] after them to redact context they add.
+///
+/// `enrich` runs on the thread calling `track`, for every event, so it must
+/// be cheap and must not block: no I/O, locks held for long or waiting on
+/// async work. Middlewares also run for `track_bulk` and `backfill`, but not
+/// for `track_enriched`, whose events are relayed as-is.
+///
+/// ```rust
+/// use lib_analytics_core::{AnalyticsClient, EnrichMiddleware, EnrichedEvent, RetentionClass};
+///
+/// /// Keep everything from the billing hosts for good
+/// struct BillingRetention;
+///
+/// impl EnrichMiddleware for BillingRetention {
+///     fn enrich(&self, event: &mut EnrichedEvent) {
+///         if event.hostname.as_deref().is_some_and(|host| host.starts_with("billing-")) {
+///             event.retention = Some(RetentionClass::Long);
+///         }
+///     }
+/// }
+///
+/// let builder = AnalyticsClient::builder().enrich_middleware(BillingRetention);
+/// ```
+pub trait EnrichMiddleware: Send + Sync + 'static {
+    /// Modify `event` in place
+    fn enrich(&self, event: &mut EnrichedEvent);
+}
+
+impl fmt::Debug for dyn EnrichMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnrichMiddleware").finish_non_exhaustive()
+    }
+}
+
+/// Sets `canonical_action`
+impl EnrichMiddleware for ActionNormalizer {
+    fn enrich(&self, event: &mut EnrichedEvent) {
+        event.canonical_action = self.normalize(&event.event);
+    }
+}
+
+/// Redacts `ApplicationError::context` like
+/// [`ClientConfig::context_filter`], for context added by earlier
+/// middlewares
+impl EnrichMiddleware for ContextFilter {
+    fn enrich(&self, event: &mut EnrichedEvent) {
+        sanitize::filter_context(&mut event.event, self);
+    }
+}
+
+/// `user_id_policy`, counting stripped ids
+struct StripUserIds {
+    policy: UserIdPolicy,
+    counters: Arc<PipelineCounters>,
+}
+
+impl EnrichMiddleware for StripUserIds {
+    fn enrich(&self, event: &mut EnrichedEvent) {
+        if sanitize::strip_user_id(&mut event.event, &self.policy) {
+            self.counters.record_user_id_stripped();
+        }
+    }
+}
+
+/// `slow_query_threshold`
+struct FlagSlowQueries(Duration);
+
+impl EnrichMiddleware for FlagSlowQueries {
+    fn enrich(&self, event: &mut EnrichedEvent) {
+        sql::flag_slow_query(&mut event.event, self.0);
+    }
+}
+
+/// `max_field_len`, keeping `context` whole when it will be uploaded
+struct Truncate {
+    max_field_len: usize,
+    /// `blob_threshold` when a `blob_uploader` is configured
+    #[cfg(feature = "blob-upload")]
+    offload_above: Option<usize>,
+}
+
+impl EnrichMiddleware for Truncate {
+    fn enrich(&self, event: &mut EnrichedEvent) {
+        #[cfg(feature = "blob-upload")]
+        let offload = self
+            .offload_above
+            .is_some_and(|threshold| crate::blob::exceeds_threshold(&event.event, threshold));
+        #[cfg(not(feature = "blob-upload"))]
+        let offload = false;
+        sanitize::truncate_event(&mut event.event, self.max_field_len, offload);
+    }
+}
+
+/// The built-in middlewares followed by the configured ones
+pub(crate) fn chain(
+    config: &ClientConfig,
+    counters: &Arc<PipelineCounters>,
+) -> Vec<Arc<dyn EnrichMiddleware>> {
+    let mut chain: Vec<Arc<dyn EnrichMiddleware>> = vec![
+        Arc::new(config.context_filter.clone()),
+        Arc::new(StripUserIds {
+            policy: config.user_id_policy.clone(),
+            counters: counters.clone(),
+        }),
+    ];
+    if let Some(threshold) = config.slow_query_threshold {
+        chain.push(Arc::new(FlagSlowQueries(threshold)));
+    }
+    chain.push(Arc::new(Truncate {
+        max_field_len: config.max_field_len,
+        #[cfg(feature = "blob-upload")]
+        offload_above: config
+            .blob_uploader
+            .is_some()
+            .then_some(config.blob_threshold),
+    }));
+    chain.push(Arc::new(config.action_normalizer.clone()));
+    chain.extend(config.enrich_middleware.iter().cloned());
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::AnalyticsClient;
+    use crate::events::AnalyticsEvent;
+    use crate::normalize::CanonicalAction;
+    use crate::provider::Provider;
+    use crate::sink::testing::CollectSink;
+    use uuid::Uuid;
+
+    struct Label(&'static str);
+
+    impl EnrichMiddleware for Label {
+        fn enrich(&self, event: &mut EnrichedEvent) {
+            let context = serde_json::json!({ "label": self.0, "secret": "hunter2" });
+            if let AnalyticsEvent::ApplicationError { context: slot, .. } = &mut event.event {
+                *slot = Some(context);
+            }
+        }
+    }
+
+    /// Routes by a label set by an earlier middleware
+    struct RouteByLabel(Uuid);
+
+    impl EnrichMiddleware for RouteByLabel {
+        fn enrich(&self, event: &mut EnrichedEvent) {
+            if let AnalyticsEvent::ApplicationError {
+                context: Some(context),
+                ..
+            } = &event.event
+            {
+                if context["label"] == "checkout" {
+                    event.tenant_id = Some(self.0);
+                }
+            }
+        }
+    }
+
+    fn error() -> AnalyticsEvent {
+        AnalyticsEvent::ApplicationError {
+            service: "api".to_string(),
+            error_type: "timeout".to_string(),
+            error_message: "upstream timed out".to_string(),
+            user_id: None,
+            context: None,
+            context_ref: None,
+        }
+    }
+
+    #[test]
+    fn test_builtins_in_isolation() {
+        let mut event = EnrichedEvent::new(AnalyticsEvent::IntegrationUsed {
+            integration_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            provider: Provider::GitHub,
+            action: "push".to_string(),
+        });
+        ActionNormalizer::default().enrich(&mut event);
+        assert_eq!(event.canonical_action, Some(CanonicalAction::Push));
+
+        let mut event = EnrichedEvent::new(error());
+        Label("checkout").enrich(&mut event);
+        ContextFilter::deny(["secret"]).enrich(&mut event);
+        match &event.event {
+            AnalyticsEvent::ApplicationError { context, .. } => {
+                assert_eq!(context, &Some(serde_json::json!({ "label": "checkout" })));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middlewares_run_in_order() {
+        let tenant_id = Uuid::new_v4();
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .enrich_middleware(Label("checkout"))
+            .enrich_middleware(RouteByLabel(tenant_id))
+            .enrich_middleware(ContextFilter::deny(["secret"]))
+            .build()
+            .unwrap();
+
+        client.track(error());
+        client.flush().await.unwrap();
+
+        let events = sink.events();
+        assert_eq!(events[0].tenant_id, Some(tenant_id));
+        match &events[0].event {
+            AnalyticsEvent::ApplicationError { context, .. } => {
+                assert_eq!(context, &Some(serde_json::json!({ "label": "checkout" })));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // Reversed, routing runs before the label exists
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .enrich_middleware(RouteByLabel(tenant_id))
+            .enrich_middleware(Label("checkout"))
+            .build()
+            .unwrap();
+        client.track(error());
+        client.flush().await.unwrap();
+        assert_eq!(sink.events()[0].tenant_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_builtin_steps_run_before_custom_ones() {
+        /// Adds a key to the existing context
+        struct AddLabel;
+
+        impl EnrichMiddleware for AddLabel {
+            fn enrich(&self, event: &mut EnrichedEvent) {
+                if let AnalyticsEvent::ApplicationError {
+                    context: Some(serde_json::Value::Object(context)),
+                    ..
+                } = &mut event.event
+                {
+                    context.insert("label".to_string(), "checkout".into());
+                }
+            }
+        }
+
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::builder()
+            .sink(sink.clone())
+            .context_filter(ContextFilter::deny(["secret", "label"]))
+            .enrich_middleware(AddLabel)
+            .build()
+            .unwrap();
+
+        let mut event = error();
+        if let AnalyticsEvent::ApplicationError { context, .. } = &mut event {
+            *context = Some(serde_json::json!({ "secret": "tracked", "kept": 1 }));
+        }
+        client.track(event);
+        client.flush().await.unwrap();
+
+        // Redacted by the client's filter before the custom step added to it
+        match &sink.events()[0].event {
+            AnalyticsEvent::ApplicationError { context, .. } => assert_eq!(
+                context,
+                &Some(serde_json::json!({ "kept": 1, "label": "checkout" }))
+            ),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
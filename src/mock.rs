@@ -0,0 +1,418 @@
+//! In-process ingestion service for end-to-end tests of the HTTP path
+
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Ingestion service stand-in listening on an ephemeral local port
+///
+/// Accepts requests on any path, answers them with the programmed status
+/// codes (200 by default) and records them, so tests can drive the real
+/// HTTP sink, including auth, signing, compression and failure handling,
+/// and assert on what arrived. Bodies are recorded decoded (`zstd` with
+/// the `zstd` feature; chunked transfer encoding is undone). A successful
+/// request to `/events/debug` is answered with its event, as
+/// [`AnalyticsClient::debug_roundtrip`](crate::AnalyticsClient::debug_roundtrip)
+/// expects. Stops when dropped. Requires the `test-util` feature.
+///
+/// ```rust
+/// use lib_analytics_core::{AnalyticsClient, AnalyticsEvent, MockIngestServer};
+/// use uuid::Uuid;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let server = MockIngestServer::start().await.unwrap();
+/// // The first batch fails, later ones go through
+/// server.respond_next([503]);
+/// let client = AnalyticsClient::builder()
+///     .url(server.url())
+///     .auth_token("secret")
+///     .build()
+///     .unwrap();
+///
+/// let event = || AnalyticsEvent::ProjectUpdated {
+///     project_id: Uuid::new_v4(),
+///     user_id: Uuid::new_v4(),
+/// };
+/// client.track(event());
+/// assert!(client.flush().await.is_err());
+/// client.track(event());
+/// client.flush().await.unwrap();
+///
+/// let requests = server.requests();
+/// assert_eq!(requests.len(), 2);
+/// assert_eq!(requests[0].status, 503);
+/// assert_eq!(requests[1].header("authorization"), Some("Bearer secret"));
+/// assert_eq!(server.events().len(), 1);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MockIngestServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    requests: Vec<MockRequest>,
+    next_statuses: VecDeque<u16>,
+    status: u16,
+}
+
+impl MockIngestServer {
+    /// Listen on an ephemeral port of `127.0.0.1`
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            requests: Vec::new(),
+            next_statuses: VecDeque::new(),
+            status: 200,
+        }));
+
+        let accepting = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, accepting.clone()));
+            }
+        });
+
+        Ok(Self { addr, state, task })
+    }
+
+    /// Base URL to pass as the client's ingestion URL
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Answer requests with `status` from now on, once the statuses queued
+    /// with [`respond_next`](Self::respond_next) are used up
+    pub fn respond_with(&self, status: u16) {
+        self.state().status = status;
+    }
+
+    /// Answer the next requests with these statuses, one each, in order
+    pub fn respond_next(&self, statuses: impl IntoIterator<Item = u16>) {
+        self.state().next_statuses.extend(statuses);
+    }
+
+    /// Every request received so far, in arrival order
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Events of all requests answered with a 2xx status, in arrival order
+    ///
+    /// Events of failed requests are left out, so a batch resent after an
+    /// error shows up once.
+    pub fn events(&self) -> Vec<EnrichedEvent> {
+        self.state()
+            .requests
+            .iter()
+            .filter(|request| (200..300).contains(&request.status))
+            .flat_map(MockRequest::events)
+            .collect()
+    }
+
+    /// Forget the requests received so far
+    pub fn clear(&self) {
+        self.state().requests.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // Only held briefly without panicking code in between
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for MockIngestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Request received by a [`MockIngestServer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MockRequest {
+    /// HTTP method, e.g. `POST`
+    pub method: String,
+    /// Request path including the query, e.g. `/events/batch`
+    pub path: String,
+    /// Headers with lowercase names, in the order received
+    pub headers: Vec<(String, String)>,
+    /// Body after undoing chunked transfer and content encoding
+    pub body: Vec<u8>,
+    /// Status code the server answered with
+    pub status: u16,
+}
+
+impl MockRequest {
+    /// Value of the header `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Events in the body, sent as a JSON array or as JSON lines
+    ///
+    /// Empty for bodies that are neither, such as `multipart/form-data`
+    /// batches carrying attachments.
+    pub fn events(&self) -> Vec<EnrichedEvent> {
+        if let Ok(events) = serde_json::from_slice(&self.body) {
+            return events;
+        }
+        self.body
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect::<std::result::Result<_, _>>()
+            .unwrap_or_default()
+    }
+}
+
+/// Answer one request on `stream` and close it
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let Ok(Some(mut request)) = read_request(&mut stream).await else {
+        return;
+    };
+
+    let status = {
+        let mut state = state.lock().unwrap();
+        let status = state.next_statuses.pop_front().unwrap_or(state.status);
+        request.status = status;
+        state.requests.push(request.clone());
+        status
+    };
+
+    let body = match request.events().first() {
+        Some(event) if request.path.ends_with("/events/debug") && status < 300 => {
+            serde_json::to_vec(event).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+    let head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&body).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Read and parse one HTTP/1.1 request, `None` if the connection closed
+/// before a complete request arrived
+async fn read_request(stream: &mut (impl AsyncRead + Unpin)) -> Result<Option<MockRequest>> {
+    let mut buf = Vec::new();
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if !read_more(stream, &mut buf).await? {
+            return Ok(None);
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_len]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut rest = buf.split_off(head_len);
+    let body = if header("transfer-encoding").is_some_and(|v| v.contains("chunked")) {
+        match read_chunked(stream, rest).await? {
+            Some(body) => body,
+            None => return Ok(None),
+        }
+    } else {
+        let len = header("content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        while rest.len() < len {
+            if !read_more(stream, &mut rest).await? {
+                return Ok(None);
+            }
+        }
+        rest.truncate(len);
+        rest
+    };
+    let body = decode(header("content-encoding"), body)?;
+
+    Ok(Some(MockRequest {
+        method,
+        path,
+        headers,
+        body,
+        status: 0,
+    }))
+}
+
+/// Decode a `Transfer-Encoding: chunked` body, `buf` holding what was read
+/// past the head already
+async fn read_chunked(
+    stream: &mut (impl AsyncRead + Unpin),
+    mut buf: Vec<u8>,
+) -> Result<Option<Vec<u8>>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = loop {
+            if let Some(pos) = buf.windows(2).position(|w| w == b"\r\n") {
+                break pos;
+            }
+            if !read_more(stream, &mut buf).await? {
+                return Ok(None);
+            }
+        };
+        let size = String::from_utf8_lossy(&buf[..line_end]);
+        let size =
+            usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16).unwrap_or(0);
+        if size == 0 {
+            return Ok(Some(body));
+        }
+
+        let chunk_end = line_end + 2 + size;
+        while buf.len() < chunk_end + 2 {
+            if !read_more(stream, &mut buf).await? {
+                return Ok(None);
+            }
+        }
+        body.extend_from_slice(&buf[line_end + 2..chunk_end]);
+        buf.drain(..chunk_end + 2);
+    }
+}
+
+/// Append what the stream has to `buf`, `false` at end of stream
+async fn read_more(stream: &mut (impl AsyncRead + Unpin), buf: &mut Vec<u8>) -> Result<bool> {
+    let mut chunk = [0; 8192];
+    let n = stream.read(&mut chunk).await?;
+    buf.extend_from_slice(&chunk[..n]);
+    Ok(n > 0)
+}
+
+/// Undo the body's `Content-Encoding`
+fn decode(encoding: Option<&str>, body: Vec<u8>) -> Result<Vec<u8>> {
+    match encoding {
+        #[cfg(feature = "zstd")]
+        Some("zstd") => Ok(zstd::decode_all(body.as_slice())?),
+        _ => Ok(body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::AnalyticsClient;
+    use crate::events::AnalyticsEvent;
+    use uuid::Uuid;
+
+    fn event() -> AnalyticsEvent {
+        AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_batches() {
+        let server = MockIngestServer::start().await.unwrap();
+        let client = AnalyticsClient::try_new(server.url()).unwrap();
+
+        client.track(event());
+        client.track(event());
+        client.flush().await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/events/batch");
+        assert_eq!(requests[0].status, 200);
+        assert!(requests[0].header("Idempotency-Key").is_some());
+        assert_eq!(server.events().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_programmed_statuses() {
+        let server = MockIngestServer::start().await.unwrap();
+        server.respond_with(500);
+        server.respond_next([200]);
+        let client = AnalyticsClient::try_new(server.url()).unwrap();
+
+        for expected in [200, 500, 500] {
+            client.track(event());
+            let result = client.flush().await;
+            assert_eq!(result.is_ok(), expected == 200);
+        }
+
+        let statuses: Vec<_> = server.requests().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [200, 500, 500]);
+        assert_eq!(server.events().len(), 1);
+
+        server.clear();
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_debug_roundtrip() {
+        let server = MockIngestServer::start().await.unwrap();
+        let client = AnalyticsClient::try_new(server.url()).unwrap();
+
+        let echoed = client.debug_roundtrip(event()).await.unwrap();
+        assert_eq!(echoed.event.event_type(), "project_updated");
+        assert_eq!(server.requests()[0].path, "/events/debug");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client
+            .write_all(
+                b"POST /x HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n[1,2\r\n1\r\n]\r\n0\r\n\r\n",
+            )
+            .await
+            .unwrap();
+
+        let request = read_request(&mut server).await.unwrap().unwrap();
+        assert_eq!(request.body, b"[1,2]");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn test_decodes_zstd() {
+        let server = MockIngestServer::start().await.unwrap();
+        let client = AnalyticsClient::builder()
+            .url(server.url())
+            .compression(crate::compression::Compression::Zstd { level: 3 })
+            .compress_min_bytes(0)
+            .build()
+            .unwrap();
+
+        client.track(event());
+        client.flush().await.unwrap();
+
+        assert_eq!(
+            server.requests()[0].header("content-encoding"),
+            Some("zstd")
+        );
+        assert_eq!(server.events().len(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::events::AnalyticsEvent;
+use crate::provider::Provider;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Provider-independent meaning of an integration action or webhook event
+///
+/// Set on [`EnrichedEvent::canonical_action`](crate::EnrichedEvent::canonical_action)
+/// for `IntegrationUsed` (from `action`) and `WebhookReceived` (from
+/// `event_type`) events; the raw provider string stays in its original field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CanonicalAction {
+    Push,
+    PullRequest,
+    Issue,
+    Comment,
+    Message,
+    Reaction,
+    Release,
+    Install,
+    Uninstall,
+    /// No mapping for the raw action
+    Other,
+}
+
+/// Per-provider table mapping raw actions to [`CanonicalAction`]s
+///
+/// The default table covers common GitHub and Slack webhook events and API
+/// actions. Raw actions are matched case-insensitively; unmapped ones
+/// become [`CanonicalAction::Other`]. Add or override mappings with
+/// [`map`](Self::map):
+///
+/// ```rust
+/// use lib_analytics_core::{ActionNormalizer, CanonicalAction, ClientConfig, Provider};
+///
+/// let config = ClientConfig {
+///     action_normalizer: ActionNormalizer::default()
+///         .map(Provider::Linear, "Issue", CanonicalAction::Issue)
+///         .map(Provider::Linear, "Comment", CanonicalAction::Comment),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionNormalizer {
+    mappings: HashMap<(Provider, String), CanonicalAction>,
+}
+
+impl ActionNormalizer {
+    /// Normalizer without any mappings, every action becomes `Other`
+    pub fn empty() -> Self {
+        Self {
+            mappings: HashMap::new(),
+        }
+    }
+
+    /// Map `raw` actions of `provider` to `canonical`
+    pub fn map(
+        mut self,
+        provider: Provider,
+        raw: impl AsRef<str>,
+        canonical: CanonicalAction,
+    ) -> Self {
+        self.mappings
+            .insert((provider, raw.as_ref().to_ascii_lowercase()), canonical);
+        self
+    }
+
+    /// Canonical action of an event, `None` for events without a raw action
+    pub(crate) fn normalize(&self, event: &AnalyticsEvent) -> Option<CanonicalAction> {
+        let (provider, raw) = match event {
+            AnalyticsEvent::IntegrationUsed {
+                provider, action, ..
+            } => (provider, action),
+            AnalyticsEvent::WebhookReceived {
+                provider,
+                event_type,
+                ..
+            } => (provider, event_type),
+            _ => return None,
+        };
+
+        let key = (provider.clone(), raw.to_ascii_lowercase());
+        Some(
+            self.mappings
+                .get(&key)
+                .copied()
+                .unwrap_or(CanonicalAction::Other),
+        )
+    }
+}
+
+impl Default for ActionNormalizer {
+    fn default() -> Self {
+        use CanonicalAction::*;
+
+        let github = [
+            ("push", Push),
+            ("pull_request", PullRequest),
+            ("create_pull_request", PullRequest),
+            ("merge_pull_request", PullRequest),
+            ("issues", Issue),
+            ("create_issue", Issue),
+            ("issue_comment", Comment),
+            ("pull_request_review_comment", Comment),
+            ("create_comment", Comment),
+            ("release", Release),
+            ("installation", Install),
+        ];
+        let slack = [
+            ("message", Message),
+            ("app_mention", Message),
+            ("chat.postmessage", Message),
+            ("reaction_added", Reaction),
+            ("reaction_removed", Reaction),
+            ("reactions.add", Reaction),
+            ("app_installed", Install),
+            ("app_uninstalled", Uninstall),
+        ];
+
+        let mut normalizer = Self::empty();
+        for (raw, canonical) in github {
+            normalizer = normalizer.map(Provider::GitHub, raw, canonical);
+        }
+        for (raw, canonical) in slack {
+            normalizer = normalizer.map(Provider::Slack, raw, canonical);
+        }
+        normalizer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn used(provider: Provider, action: &str) -> AnalyticsEvent {
+        AnalyticsEvent::IntegrationUsed {
+            integration_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            provider,
+            action: action.to_string(),
+        }
+    }
+
+    fn webhook(provider: Provider, event_type: &str) -> AnalyticsEvent {
+        AnalyticsEvent::WebhookReceived {
+            integration_id: None,
+            provider,
+            event_type: event_type.to_string(),
+            delivery_id: "d-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_github_mappings() {
+        let normalizer = ActionNormalizer::default();
+
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::GitHub, "pull_request")),
+            Some(CanonicalAction::PullRequest)
+        );
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::GitHub, "issue_comment")),
+            Some(CanonicalAction::Comment)
+        );
+        assert_eq!(
+            normalizer.normalize(&used(Provider::GitHub, "Create_Issue")),
+            Some(CanonicalAction::Issue)
+        );
+    }
+
+    #[test]
+    fn test_slack_mappings() {
+        let normalizer = ActionNormalizer::default();
+
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::Slack, "app_mention")),
+            Some(CanonicalAction::Message)
+        );
+        assert_eq!(
+            normalizer.normalize(&used(Provider::Slack, "chat.postMessage")),
+            Some(CanonicalAction::Message)
+        );
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::Slack, "reaction_added")),
+            Some(CanonicalAction::Reaction)
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_unrelated() {
+        let normalizer = ActionNormalizer::default();
+
+        // Mappings are per provider
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::Slack, "push")),
+            Some(CanonicalAction::Other)
+        );
+        assert_eq!(
+            normalizer.normalize(&used(Provider::GitHub, "star_repo")),
+            Some(CanonicalAction::Other)
+        );
+        assert_eq!(
+            normalizer.normalize(&AnalyticsEvent::ProjectDeleted {
+                project_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_custom_mapping_overrides_default() {
+        let normalizer =
+            ActionNormalizer::default().map(Provider::GitHub, "push", CanonicalAction::Release);
+
+        assert_eq!(
+            normalizer.normalize(&webhook(Provider::GitHub, "push")),
+            Some(CanonicalAction::Release)
+        );
+    }
+}
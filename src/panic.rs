@@ -0,0 +1,160 @@
+use crate::client::AnalyticsClient;
+use crate::events::AnalyticsEvent;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::sync::mpsc;
+use std::time::Duration;
+use tokio::runtime::Handle;
+
+/// Longest time a panicking thread waits for its event to be sent
+const PANIC_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+thread_local! {
+    /// Set while this thread reports a panic, so a panic in the report
+    /// itself doesn't recurse
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Track an `ApplicationError` for every panic in the process
+///
+/// Replaces the panic hook with one that tracks an `ApplicationError` with
+/// `error_type` `"panic"`, the panic message and a `context` holding the
+/// location, thread name and a backtrace, then runs the previously
+/// installed hook (which prints the usual message) and finally waits up to
+/// two seconds for the event to be sent. Install it once at startup, inside
+/// the tokio runtime the client runs on.
+///
+/// Reporting from a panicking thread is best effort:
+///
+/// * The event is queued without blocking, so it is lost if the queue is
+///   full or the client was shut down.
+/// * Waiting for the send runs the flush from a helper thread on the
+///   runtime captured at install time. On a current-thread runtime whose
+///   only thread is the one panicking, the background task can't run, so
+///   the wait times out and the event is sent only if the process lives on.
+/// * With `panic = "abort"` the process ends right after the hook, so
+///   the wait is the only chance to deliver the event.
+/// * Panics while reporting a panic are not reported.
+///
+/// Like any panic hook, this affects the whole process: installing it twice
+/// reports each panic twice.
+pub fn install_panic_hook(client: AnalyticsClient, service: impl Into<String>) {
+    let service = service.into();
+    let runtime = Handle::try_current().ok();
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let reporting = REPORTING.with(|reporting| reporting.replace(true));
+        if reporting {
+            previous(info);
+            return;
+        }
+
+        let location = info
+            .location()
+            .map(|location| location.to_string())
+            .unwrap_or_default();
+        let thread = std::thread::current();
+        client.track(AnalyticsEvent::ApplicationError {
+            service: service.clone(),
+            error_type: "panic".to_string(),
+            error_message: panic_message(info.payload()),
+            user_id: None,
+            context: Some(serde_json::json!({
+                "location": location,
+                "thread": thread.name().unwrap_or("<unnamed>"),
+                "backtrace": Backtrace::force_capture().to_string(),
+            })),
+            context_ref: None,
+        });
+
+        previous(info);
+        if let Some(runtime) = &runtime {
+            flush_blocking(&client, runtime);
+        }
+        REPORTING.with(|reporting| reporting.set(false));
+    }));
+}
+
+/// Text of a panic payload, as `panic!` formats it
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Flush `client` on `runtime` from a helper thread, waiting at most
+/// [`PANIC_FLUSH_TIMEOUT`]
+///
+/// The panicking thread may be a runtime worker, where blocking on the
+/// runtime is not allowed; the helper thread is outside of it.
+fn flush_blocking(client: &AnalyticsClient, runtime: &Handle) {
+    let (done, flushed) = mpsc::channel();
+    let client = client.clone();
+    let runtime = runtime.clone();
+    let spawned = std::thread::Builder::new()
+        .name("analytics-panic-flush".to_string())
+        .spawn(move || {
+            REPORTING.with(|reporting| reporting.set(true));
+            // The timer must be created inside the runtime, not on this thread
+            let _ = runtime.block_on(async {
+                tokio::time::timeout(PANIC_FLUSH_TIMEOUT, client.flush()).await
+            });
+            let _ = done.send(());
+        });
+    if spawned.is_ok() {
+        let _ = flushed.recv_timeout(PANIC_FLUSH_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::testing::CollectSink;
+
+    #[test]
+    fn test_panic_message() {
+        assert_eq!(panic_message(&"static"), "static");
+        assert_eq!(panic_message(&"owned".to_string()), "owned");
+        assert_eq!(panic_message(&42), "Box<dyn Any>");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_panic_is_tracked_and_sent() {
+        let sink = CollectSink::default();
+        let client = AnalyticsClient::with_sink(sink.clone(), Default::default());
+        install_panic_hook(client, "worker");
+
+        let result = tokio::task::spawn_blocking(|| panic!("synth panic {}", 390)).await;
+        // Restore the default hook for the other tests
+        let _ = std::panic::take_hook();
+        assert!(result.is_err());
+
+        // Other tests may panic meanwhile and be reported too
+        let events = sink.events();
+        let (error_type, context) = events
+            .iter()
+            .find_map(|event| match &event.event {
+                AnalyticsEvent::ApplicationError {
+                    service,
+                    error_type,
+                    error_message,
+                    context,
+                    ..
+                } if error_message == "synth panic 390" => {
+                    assert_eq!(service, "worker");
+                    Some((error_type.clone(), context.clone().unwrap()))
+                }
+                _ => None,
+            })
+            .expect("panic was not sent");
+        assert_eq!(error_type, "panic");
+        assert!(context["location"].as_str().unwrap().contains("panic.rs"));
+        assert!(!context["backtrace"].as_str().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1124 @@
+mod messages;
+
+use crate::duration::DurationMs;
+use crate::environment::Environment;
+use crate::error::{AnalyticsError, Result};
+use crate::events::{AnalyticsEvent, EnrichedEvent};
+use crate::normalize::CanonicalAction;
+use crate::provider::Provider;
+use crate::retention::RetentionClass;
+use crate::serializer::BatchSerializer;
+use chrono::{DateTime, Utc};
+use messages::event::Payload;
+use prost::Message;
+use uuid::Uuid;
+
+/// Batch encoded as a protobuf `EventBatch` (`application/x-protobuf`)
+///
+/// For consumers that ingest protobuf with a fixed schema instead of JSON.
+/// The schema is `proto/analytics.proto` in this crate (package
+/// `adi.analytics.v1`): an `Event` message mirrors [`EnrichedEvent`], with the
+/// [`AnalyticsEvent`] in a `payload` oneof holding one message per variant.
+/// UUIDs are sent as hyphenated strings, timestamps as
+/// `google.protobuf.Timestamp` and `ApplicationError::context` as JSON text.
+/// Attachments are not included, as with the JSON serializers.
+///
+/// The schema only grows compatibly: field numbers are never changed or
+/// reused, and new variants and fields get new numbers, so consumers
+/// generated from an older `.proto` keep decoding newer batches (skipping
+/// what they don't know). Incompatible changes would get a new package.
+///
+/// JSON stays the default; opt in per client:
+///
+/// ```rust
+/// use lib_analytics_core::{ClientConfig, ProtobufSerializer};
+/// use std::sync::Arc;
+///
+/// let config = ClientConfig {
+///     serializer: Arc::new(ProtobufSerializer),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProtobufSerializer;
+
+impl ProtobufSerializer {
+    /// Decode a body produced by this serializer back into events
+    pub fn deserialize(&self, body: &[u8]) -> Result<Vec<EnrichedEvent>> {
+        let batch = messages::EventBatch::decode(body)
+            .map_err(|e| AnalyticsError::Protobuf(e.to_string()))?;
+        batch.events.into_iter().map(decode_event).collect()
+    }
+}
+
+impl BatchSerializer for ProtobufSerializer {
+    fn content_type(&self) -> &str {
+        "application/x-protobuf"
+    }
+
+    fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>> {
+        let batch = messages::EventBatch {
+            events: batch.iter().map(encode_event).collect(),
+        };
+        Ok(batch.encode_to_vec())
+    }
+}
+
+fn encode_event(event: &EnrichedEvent) -> messages::Event {
+    messages::Event {
+        timestamp: Some(timestamp(&event.timestamp)),
+        hostname: event.hostname.clone(),
+        environment: event.environment.as_ref().map(|e| e.to_string()),
+        retention: match event.retention {
+            None => messages::RetentionClass::Unspecified,
+            Some(RetentionClass::Short) => messages::RetentionClass::Short,
+            Some(RetentionClass::Standard) => messages::RetentionClass::Standard,
+            Some(RetentionClass::Long) => messages::RetentionClass::Long,
+        } as i32,
+        sequence: event.sequence,
+        monotonic_ns: event.monotonic_ns,
+        schema_version: event.schema_version,
+        tenant_id: event.tenant_id.map(|id| id.to_string()),
+        canonical_action: match event.canonical_action {
+            None => messages::CanonicalAction::Unspecified,
+            Some(CanonicalAction::Push) => messages::CanonicalAction::Push,
+            Some(CanonicalAction::PullRequest) => messages::CanonicalAction::PullRequest,
+            Some(CanonicalAction::Issue) => messages::CanonicalAction::Issue,
+            Some(CanonicalAction::Comment) => messages::CanonicalAction::Comment,
+            Some(CanonicalAction::Message) => messages::CanonicalAction::Message,
+            Some(CanonicalAction::Reaction) => messages::CanonicalAction::Reaction,
+            Some(CanonicalAction::Release) => messages::CanonicalAction::Release,
+            Some(CanonicalAction::Install) => messages::CanonicalAction::Install,
+            Some(CanonicalAction::Uninstall) => messages::CanonicalAction::Uninstall,
+            Some(CanonicalAction::Other) => messages::CanonicalAction::Other,
+        } as i32,
+        payload: Some(encode_payload(&event.event)),
+    }
+}
+
+/// Values of enums newer than this schema decode as `None`, like unset ones
+fn decode_event(event: messages::Event) -> Result<EnrichedEvent> {
+    let payload = event.payload.ok_or_else(|| missing("payload"))?;
+    Ok(EnrichedEvent {
+        timestamp: datetime(event.timestamp, "timestamp")?,
+        event: decode_payload(payload)?,
+        hostname: event.hostname,
+        environment: event.environment.map(Environment::from),
+        retention: match messages::RetentionClass::try_from(event.retention) {
+            Ok(messages::RetentionClass::Short) => Some(RetentionClass::Short),
+            Ok(messages::RetentionClass::Standard) => Some(RetentionClass::Standard),
+            Ok(messages::RetentionClass::Long) => Some(RetentionClass::Long),
+            Ok(messages::RetentionClass::Unspecified) | Err(_) => None,
+        },
+        sequence: event.sequence,
+        monotonic_ns: event.monotonic_ns,
+        schema_version: event.schema_version,
+        tenant_id: optional_uuid(event.tenant_id.as_deref(), "tenant_id")?,
+        canonical_action: match messages::CanonicalAction::try_from(event.canonical_action) {
+            Ok(messages::CanonicalAction::Push) => Some(CanonicalAction::Push),
+            Ok(messages::CanonicalAction::PullRequest) => Some(CanonicalAction::PullRequest),
+            Ok(messages::CanonicalAction::Issue) => Some(CanonicalAction::Issue),
+            Ok(messages::CanonicalAction::Comment) => Some(CanonicalAction::Comment),
+            Ok(messages::CanonicalAction::Message) => Some(CanonicalAction::Message),
+            Ok(messages::CanonicalAction::Reaction) => Some(CanonicalAction::Reaction),
+            Ok(messages::CanonicalAction::Release) => Some(CanonicalAction::Release),
+            Ok(messages::CanonicalAction::Install) => Some(CanonicalAction::Install),
+            Ok(messages::CanonicalAction::Uninstall) => Some(CanonicalAction::Uninstall),
+            Ok(messages::CanonicalAction::Other) => Some(CanonicalAction::Other),
+            Ok(messages::CanonicalAction::Unspecified) | Err(_) => None,
+        },
+        #[cfg(feature = "attachments")]
+        attachment: None,
+    })
+}
+
+/// Exhaustive, so a new `AnalyticsEvent` variant can't be left out of the
+/// schema
+fn encode_payload(event: &AnalyticsEvent) -> Payload {
+    match event {
+        AnalyticsEvent::AuthLoginAttempt {
+            user_id,
+            email,
+            success,
+            error,
+        } => Payload::AuthLoginAttempt(messages::AuthLoginAttempt {
+            user_id: user_id.map(|id| id.to_string()),
+            email: email.clone(),
+            success: *success,
+            error: error.clone(),
+        }),
+        AnalyticsEvent::AuthCodeVerified {
+            user_id,
+            success,
+            error,
+        } => Payload::AuthCodeVerified(messages::AuthCodeVerified {
+            user_id: user_id.to_string(),
+            success: *success,
+            error: error.clone(),
+        }),
+        AnalyticsEvent::AuthTokenRefresh {
+            user_id,
+            success,
+            error,
+        } => Payload::AuthTokenRefresh(messages::AuthTokenRefresh {
+            user_id: user_id.to_string(),
+            success: *success,
+            error: error.clone(),
+        }),
+        AnalyticsEvent::AuthTokenExpiring {
+            user_id,
+            provider,
+            expires_in_seconds,
+        } => Payload::AuthTokenExpiring(messages::AuthTokenExpiring {
+            user_id: user_id.to_string(),
+            provider: provider.clone(),
+            expires_in_seconds: *expires_in_seconds,
+        }),
+        AnalyticsEvent::AuthSessionValidated { user_id, valid } => {
+            Payload::AuthSessionValidated(messages::AuthSessionValidated {
+                user_id: user_id.to_string(),
+                valid: *valid,
+            })
+        }
+        AnalyticsEvent::SessionStarted {
+            session_id,
+            user_id,
+        } => Payload::SessionStarted(messages::SessionStarted {
+            session_id: session_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::SessionEnded {
+            session_id,
+            user_id,
+            duration_seconds,
+            reason,
+        } => Payload::SessionEnded(messages::SessionEnded {
+            session_id: session_id.to_string(),
+            user_id: user_id.to_string(),
+            duration_seconds: *duration_seconds,
+            reason: reason.clone(),
+        }),
+        AnalyticsEvent::AuthLoginAttemptsCompacted {
+            user_id,
+            email,
+            attempt_count,
+            failure_count,
+            last_error,
+            first_attempt_at,
+            last_attempt_at,
+        } => Payload::AuthLoginAttemptsCompacted(messages::AuthLoginAttemptsCompacted {
+            user_id: user_id.map(|id| id.to_string()),
+            email: email.clone(),
+            attempt_count: *attempt_count,
+            failure_count: *failure_count,
+            last_error: last_error.clone(),
+            first_attempt_at: Some(timestamp(first_attempt_at)),
+            last_attempt_at: Some(timestamp(last_attempt_at)),
+        }),
+        AnalyticsEvent::TaskCreated {
+            task_id,
+            user_id,
+            project_id,
+            cocoon_id,
+            command,
+        } => Payload::TaskCreated(messages::TaskCreated {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            project_id: project_id.map(|id| id.to_string()),
+            cocoon_id: cocoon_id.map(|id| id.to_string()),
+            command: command.clone(),
+        }),
+        AnalyticsEvent::TaskStarted {
+            task_id,
+            user_id,
+            cocoon_id,
+        } => Payload::TaskStarted(messages::TaskStarted {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            cocoon_id: cocoon_id.map(|id| id.to_string()),
+        }),
+        AnalyticsEvent::TaskProgress {
+            task_id,
+            user_id,
+            percent,
+            stage,
+            message,
+        } => Payload::TaskProgress(messages::TaskProgress {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            percent: percent.map(u32::from),
+            stage: stage.clone(),
+            message: message.clone(),
+        }),
+        AnalyticsEvent::TaskCompleted {
+            task_id,
+            user_id,
+            duration_ms,
+            exit_code,
+        } => Payload::TaskCompleted(messages::TaskCompleted {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            duration_ms: duration_ms.0,
+            exit_code: *exit_code,
+        }),
+        AnalyticsEvent::TaskFailed {
+            task_id,
+            user_id,
+            duration_ms,
+            exit_code,
+            error,
+        } => Payload::TaskFailed(messages::TaskFailed {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            duration_ms: duration_ms.map(|d| d.0),
+            exit_code: *exit_code,
+            error: error.clone(),
+        }),
+        AnalyticsEvent::TaskCancelled {
+            task_id,
+            user_id,
+            duration_ms,
+        } => Payload::TaskCancelled(messages::TaskCancelled {
+            task_id: task_id.to_string(),
+            user_id: user_id.to_string(),
+            duration_ms: duration_ms.map(|d| d.0),
+        }),
+        AnalyticsEvent::IntegrationConnected {
+            integration_id,
+            user_id,
+            provider,
+            project_id,
+        } => Payload::IntegrationConnected(messages::IntegrationConnected {
+            integration_id: integration_id.to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            project_id: project_id.map(|id| id.to_string()),
+        }),
+        AnalyticsEvent::IntegrationDisconnected {
+            integration_id,
+            user_id,
+            provider,
+            reason,
+        } => Payload::IntegrationDisconnected(messages::IntegrationDisconnected {
+            integration_id: integration_id.to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            reason: reason.clone(),
+        }),
+        AnalyticsEvent::IntegrationReauthorized {
+            integration_id,
+            user_id,
+            provider,
+        } => Payload::IntegrationReauthorized(messages::IntegrationReauthorized {
+            integration_id: integration_id.to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+        }),
+        AnalyticsEvent::IntegrationUsed {
+            integration_id,
+            user_id,
+            provider,
+            action,
+        } => Payload::IntegrationUsed(messages::IntegrationUsed {
+            integration_id: integration_id.to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            action: action.clone(),
+        }),
+        AnalyticsEvent::IntegrationError {
+            integration_id,
+            user_id,
+            provider,
+            error,
+        } => Payload::IntegrationError(messages::IntegrationError {
+            integration_id: integration_id.to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            error: error.clone(),
+        }),
+        AnalyticsEvent::OAuthFlowStarted {
+            user_id,
+            provider,
+            state,
+        } => Payload::OAuthFlowStarted(messages::OAuthFlowStarted {
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            state: state.clone(),
+        }),
+        AnalyticsEvent::OAuthFlowCompleted {
+            user_id,
+            provider,
+            success,
+            error,
+        } => Payload::OAuthFlowCompleted(messages::OAuthFlowCompleted {
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            success: *success,
+            error: error.clone(),
+        }),
+        AnalyticsEvent::WebhookReceived {
+            integration_id,
+            provider,
+            event_type,
+            delivery_id,
+        } => Payload::WebhookReceived(messages::WebhookReceived {
+            integration_id: integration_id.map(|id| id.to_string()),
+            provider: provider.to_string(),
+            event_type: event_type.clone(),
+            delivery_id: delivery_id.clone(),
+        }),
+        AnalyticsEvent::WebhookProcessed {
+            integration_id,
+            provider,
+            event_type,
+            delivery_id,
+            success,
+            duration_ms,
+            error,
+            retry_count,
+        } => Payload::WebhookProcessed(messages::WebhookProcessed {
+            integration_id: integration_id.map(|id| id.to_string()),
+            provider: provider.to_string(),
+            event_type: event_type.clone(),
+            delivery_id: delivery_id.clone(),
+            success: *success,
+            duration_ms: duration_ms.0,
+            error: error.clone(),
+            retry_count: *retry_count,
+        }),
+        AnalyticsEvent::WebhookRetryScheduled {
+            delivery_id,
+            provider,
+            attempt,
+            next_attempt_at,
+        } => Payload::WebhookRetryScheduled(messages::WebhookRetryScheduled {
+            delivery_id: delivery_id.clone(),
+            provider: provider.to_string(),
+            attempt: *attempt,
+            next_attempt_at: Some(timestamp(next_attempt_at)),
+        }),
+        AnalyticsEvent::CocoonRegistered {
+            cocoon_id,
+            user_id,
+            device_name,
+        } => Payload::CocoonRegistered(messages::CocoonRegistered {
+            cocoon_id: cocoon_id.to_string(),
+            user_id: user_id.to_string(),
+            device_name: device_name.clone(),
+        }),
+        AnalyticsEvent::CocoonConnected { cocoon_id, user_id } => {
+            Payload::CocoonConnected(messages::CocoonConnected {
+                cocoon_id: cocoon_id.to_string(),
+                user_id: user_id.map(|id| id.to_string()),
+            })
+        }
+        AnalyticsEvent::CocoonDisconnected {
+            cocoon_id,
+            user_id,
+            duration_seconds,
+        } => Payload::CocoonDisconnected(messages::CocoonDisconnected {
+            cocoon_id: cocoon_id.to_string(),
+            user_id: user_id.map(|id| id.to_string()),
+            duration_seconds: *duration_seconds,
+        }),
+        AnalyticsEvent::CocoonHeartbeat {
+            cocoon_id,
+            user_id,
+            uptime_seconds,
+            cpu_percent,
+            mem_bytes,
+        } => Payload::CocoonHeartbeat(messages::CocoonHeartbeat {
+            cocoon_id: cocoon_id.to_string(),
+            user_id: user_id.map(|id| id.to_string()),
+            uptime_seconds: *uptime_seconds,
+            cpu_percent: *cpu_percent,
+            mem_bytes: *mem_bytes,
+        }),
+        AnalyticsEvent::CocoonClaimed {
+            cocoon_id,
+            user_id,
+            via_setup_token,
+        } => Payload::CocoonClaimed(messages::CocoonClaimed {
+            cocoon_id: cocoon_id.to_string(),
+            user_id: user_id.to_string(),
+            via_setup_token: *via_setup_token,
+        }),
+        AnalyticsEvent::CocoonSetupTokenCreated {
+            token_id,
+            user_id,
+            cocoon_name,
+        } => Payload::CocoonSetupTokenCreated(messages::CocoonSetupTokenCreated {
+            token_id: token_id.to_string(),
+            user_id: user_id.to_string(),
+            cocoon_name: cocoon_name.clone(),
+        }),
+        AnalyticsEvent::CocoonSetupTokenUsed {
+            token_id,
+            cocoon_id,
+            user_id,
+        } => Payload::CocoonSetupTokenUsed(messages::CocoonSetupTokenUsed {
+            token_id: token_id.to_string(),
+            cocoon_id: cocoon_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::ProjectCreated {
+            project_id,
+            user_id,
+            name,
+        } => Payload::ProjectCreated(messages::ProjectCreated {
+            project_id: project_id.to_string(),
+            user_id: user_id.to_string(),
+            name: name.clone(),
+        }),
+        AnalyticsEvent::ProjectUpdated {
+            project_id,
+            user_id,
+        } => Payload::ProjectUpdated(messages::ProjectUpdated {
+            project_id: project_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::ProjectDeleted {
+            project_id,
+            user_id,
+        } => Payload::ProjectDeleted(messages::ProjectDeleted {
+            project_id: project_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::ProjectArchived {
+            project_id,
+            user_id,
+        } => Payload::ProjectArchived(messages::ProjectArchived {
+            project_id: project_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::ProjectRestored {
+            project_id,
+            user_id,
+        } => Payload::ProjectRestored(messages::ProjectRestored {
+            project_id: project_id.to_string(),
+            user_id: user_id.to_string(),
+        }),
+        AnalyticsEvent::ApiRequest {
+            service,
+            endpoint,
+            method,
+            status_code,
+            duration_ms,
+            user_id,
+        } => Payload::ApiRequest(messages::ApiRequest {
+            service: service.clone(),
+            endpoint: endpoint.clone(),
+            method: method.clone(),
+            status_code: u32::from(*status_code),
+            duration_ms: duration_ms.0,
+            user_id: user_id.map(|id| id.to_string()),
+        }),
+        AnalyticsEvent::ProxyRequest {
+            proxy_token_id,
+            user_id,
+            request_id,
+            upstream_request_id,
+            requested_model,
+            actual_model,
+            provider_type,
+            key_mode,
+            endpoint,
+            input_tokens,
+            output_tokens,
+            total_tokens,
+            reported_cost_usd,
+            latency_ms,
+            ttft_ms,
+            is_streaming,
+            status,
+            status_code,
+            error_type,
+            error_message,
+        } => Payload::ProxyRequest(messages::ProxyRequest {
+            proxy_token_id: proxy_token_id.to_string(),
+            user_id: user_id.to_string(),
+            request_id: request_id.clone(),
+            upstream_request_id: upstream_request_id.clone(),
+            requested_model: requested_model.clone(),
+            actual_model: actual_model.clone(),
+            provider_type: provider_type.clone(),
+            key_mode: key_mode.clone(),
+            endpoint: endpoint.clone(),
+            input_tokens: *input_tokens,
+            output_tokens: *output_tokens,
+            total_tokens: *total_tokens,
+            reported_cost_usd: *reported_cost_usd,
+            latency_ms: *latency_ms,
+            ttft_ms: *ttft_ms,
+            is_streaming: *is_streaming,
+            status: status.clone(),
+            status_code: *status_code,
+            error_type: error_type.clone(),
+            error_message: error_message.clone(),
+        }),
+        AnalyticsEvent::DatabaseQuery {
+            service,
+            query_type,
+            duration_ms,
+            rows_affected,
+            statement_fingerprint,
+            is_slow,
+        } => Payload::DatabaseQuery(messages::DatabaseQuery {
+            service: service.clone(),
+            query_type: query_type.clone(),
+            duration_ms: duration_ms.0,
+            rows_affected: *rows_affected,
+            statement_fingerprint: statement_fingerprint.clone(),
+            is_slow: *is_slow,
+        }),
+        AnalyticsEvent::ApplicationError {
+            service,
+            error_type,
+            error_message,
+            user_id,
+            context,
+            context_ref,
+        } => Payload::ApplicationError(messages::ApplicationError {
+            service: service.clone(),
+            error_type: error_type.clone(),
+            error_message: error_message.clone(),
+            user_id: user_id.map(|id| id.to_string()),
+            context: context.as_ref().map(|context| context.to_string()),
+            context_ref: context_ref.clone(),
+        }),
+        AnalyticsEvent::BalanceCreated {
+            user_id,
+            balance_id,
+        } => Payload::BalanceCreated(messages::BalanceCreated {
+            user_id: user_id.to_string(),
+            balance_id: balance_id.to_string(),
+        }),
+        AnalyticsEvent::BalanceDeposit {
+            user_id,
+            transaction_id,
+            amount,
+            reference_type,
+        } => Payload::BalanceDeposit(messages::BalanceDeposit {
+            user_id: user_id.to_string(),
+            transaction_id: transaction_id.to_string(),
+            amount: *amount,
+            reference_type: reference_type.clone(),
+        }),
+        AnalyticsEvent::BalanceDebit {
+            user_id,
+            transaction_id,
+            amount,
+            reference_type,
+        } => Payload::BalanceDebit(messages::BalanceDebit {
+            user_id: user_id.to_string(),
+            transaction_id: transaction_id.to_string(),
+            amount: *amount,
+            reference_type: reference_type.clone(),
+        }),
+        AnalyticsEvent::BalanceInsufficient {
+            user_id,
+            requested_amount,
+            current_balance,
+            reference_type,
+        } => Payload::BalanceInsufficient(messages::BalanceInsufficient {
+            user_id: user_id.to_string(),
+            requested_amount: *requested_amount,
+            current_balance: *current_balance,
+            reference_type: reference_type.clone(),
+        }),
+        AnalyticsEvent::EventSummary {
+            event_type,
+            key,
+            count,
+            p50_duration_ms,
+            p95_duration_ms,
+            window_start,
+            window_end,
+        } => Payload::EventSummary(messages::EventSummary {
+            event_type: event_type.clone(),
+            key: key.clone(),
+            count: *count,
+            p50_duration_ms: p50_duration_ms.map(|d| d.0),
+            p95_duration_ms: p95_duration_ms.map(|d| d.0),
+            window_start: Some(timestamp(window_start)),
+            window_end: Some(timestamp(window_end)),
+        }),
+        AnalyticsEvent::CocoonChurn {
+            window_seconds,
+            connects,
+            disconnects,
+        } => Payload::CocoonChurn(messages::CocoonChurn {
+            window_seconds: *window_seconds,
+            connects: *connects,
+            disconnects: *disconnects,
+        }),
+        AnalyticsEvent::PipelineStats {
+            enqueued,
+            dropped,
+            sent,
+            failed,
+            interval_secs,
+        } => Payload::PipelineStats(messages::PipelineStats {
+            enqueued: *enqueued,
+            dropped: *dropped,
+            sent: *sent,
+            failed: *failed,
+            interval_secs: *interval_secs,
+        }),
+    }
+}
+
+fn decode_payload(payload: Payload) -> Result<AnalyticsEvent> {
+    Ok(match payload {
+        Payload::AuthLoginAttempt(message) => AnalyticsEvent::AuthLoginAttempt {
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+            email: message.email,
+            success: message.success,
+            error: message.error,
+        },
+        Payload::AuthCodeVerified(message) => AnalyticsEvent::AuthCodeVerified {
+            user_id: uuid(&message.user_id, "user_id")?,
+            success: message.success,
+            error: message.error,
+        },
+        Payload::AuthTokenRefresh(message) => AnalyticsEvent::AuthTokenRefresh {
+            user_id: uuid(&message.user_id, "user_id")?,
+            success: message.success,
+            error: message.error,
+        },
+        Payload::AuthTokenExpiring(message) => AnalyticsEvent::AuthTokenExpiring {
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: message.provider,
+            expires_in_seconds: message.expires_in_seconds,
+        },
+        Payload::AuthSessionValidated(message) => AnalyticsEvent::AuthSessionValidated {
+            user_id: uuid(&message.user_id, "user_id")?,
+            valid: message.valid,
+        },
+        Payload::SessionStarted(message) => AnalyticsEvent::SessionStarted {
+            session_id: uuid(&message.session_id, "session_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::SessionEnded(message) => AnalyticsEvent::SessionEnded {
+            session_id: uuid(&message.session_id, "session_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            duration_seconds: message.duration_seconds,
+            reason: message.reason,
+        },
+        Payload::AuthLoginAttemptsCompacted(message) => {
+            AnalyticsEvent::AuthLoginAttemptsCompacted {
+                user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+                email: message.email,
+                attempt_count: message.attempt_count,
+                failure_count: message.failure_count,
+                last_error: message.last_error,
+                first_attempt_at: datetime(message.first_attempt_at, "first_attempt_at")?,
+                last_attempt_at: datetime(message.last_attempt_at, "last_attempt_at")?,
+            }
+        }
+        Payload::TaskCreated(message) => AnalyticsEvent::TaskCreated {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            project_id: optional_uuid(message.project_id.as_deref(), "project_id")?,
+            cocoon_id: optional_uuid(message.cocoon_id.as_deref(), "cocoon_id")?,
+            command: message.command,
+        },
+        Payload::TaskStarted(message) => AnalyticsEvent::TaskStarted {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            cocoon_id: optional_uuid(message.cocoon_id.as_deref(), "cocoon_id")?,
+        },
+        Payload::TaskProgress(message) => AnalyticsEvent::TaskProgress {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            percent: message
+                .percent
+                .map(|value| narrow(value, "percent"))
+                .transpose()?,
+            stage: message.stage,
+            message: message.message,
+        },
+        Payload::TaskCompleted(message) => AnalyticsEvent::TaskCompleted {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            duration_ms: DurationMs(message.duration_ms),
+            exit_code: message.exit_code,
+        },
+        Payload::TaskFailed(message) => AnalyticsEvent::TaskFailed {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            duration_ms: message.duration_ms.map(DurationMs),
+            exit_code: message.exit_code,
+            error: message.error,
+        },
+        Payload::TaskCancelled(message) => AnalyticsEvent::TaskCancelled {
+            task_id: uuid(&message.task_id, "task_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            duration_ms: message.duration_ms.map(DurationMs),
+        },
+        Payload::IntegrationConnected(message) => AnalyticsEvent::IntegrationConnected {
+            integration_id: uuid(&message.integration_id, "integration_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            project_id: optional_uuid(message.project_id.as_deref(), "project_id")?,
+        },
+        Payload::IntegrationDisconnected(message) => AnalyticsEvent::IntegrationDisconnected {
+            integration_id: uuid(&message.integration_id, "integration_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            reason: message.reason,
+        },
+        Payload::IntegrationReauthorized(message) => AnalyticsEvent::IntegrationReauthorized {
+            integration_id: uuid(&message.integration_id, "integration_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+        },
+        Payload::IntegrationUsed(message) => AnalyticsEvent::IntegrationUsed {
+            integration_id: uuid(&message.integration_id, "integration_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            action: message.action,
+        },
+        Payload::IntegrationError(message) => AnalyticsEvent::IntegrationError {
+            integration_id: uuid(&message.integration_id, "integration_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            error: message.error,
+        },
+        Payload::OAuthFlowStarted(message) => AnalyticsEvent::OAuthFlowStarted {
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            state: message.state,
+        },
+        Payload::OAuthFlowCompleted(message) => AnalyticsEvent::OAuthFlowCompleted {
+            user_id: uuid(&message.user_id, "user_id")?,
+            provider: Provider::from(message.provider),
+            success: message.success,
+            error: message.error,
+        },
+        Payload::WebhookReceived(message) => AnalyticsEvent::WebhookReceived {
+            integration_id: optional_uuid(message.integration_id.as_deref(), "integration_id")?,
+            provider: Provider::from(message.provider),
+            event_type: message.event_type,
+            delivery_id: message.delivery_id,
+        },
+        Payload::WebhookProcessed(message) => AnalyticsEvent::WebhookProcessed {
+            integration_id: optional_uuid(message.integration_id.as_deref(), "integration_id")?,
+            provider: Provider::from(message.provider),
+            event_type: message.event_type,
+            delivery_id: message.delivery_id,
+            success: message.success,
+            duration_ms: DurationMs(message.duration_ms),
+            error: message.error,
+            retry_count: message.retry_count,
+        },
+        Payload::WebhookRetryScheduled(message) => AnalyticsEvent::WebhookRetryScheduled {
+            delivery_id: message.delivery_id,
+            provider: Provider::from(message.provider),
+            attempt: message.attempt,
+            next_attempt_at: datetime(message.next_attempt_at, "next_attempt_at")?,
+        },
+        Payload::CocoonRegistered(message) => AnalyticsEvent::CocoonRegistered {
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            device_name: message.device_name,
+        },
+        Payload::CocoonConnected(message) => AnalyticsEvent::CocoonConnected {
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+        },
+        Payload::CocoonDisconnected(message) => AnalyticsEvent::CocoonDisconnected {
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+            duration_seconds: message.duration_seconds,
+        },
+        Payload::CocoonHeartbeat(message) => AnalyticsEvent::CocoonHeartbeat {
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+            uptime_seconds: message.uptime_seconds,
+            cpu_percent: message.cpu_percent,
+            mem_bytes: message.mem_bytes,
+        },
+        Payload::CocoonClaimed(message) => AnalyticsEvent::CocoonClaimed {
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            via_setup_token: message.via_setup_token,
+        },
+        Payload::CocoonSetupTokenCreated(message) => AnalyticsEvent::CocoonSetupTokenCreated {
+            token_id: uuid(&message.token_id, "token_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            cocoon_name: message.cocoon_name,
+        },
+        Payload::CocoonSetupTokenUsed(message) => AnalyticsEvent::CocoonSetupTokenUsed {
+            token_id: uuid(&message.token_id, "token_id")?,
+            cocoon_id: uuid(&message.cocoon_id, "cocoon_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::ProjectCreated(message) => AnalyticsEvent::ProjectCreated {
+            project_id: uuid(&message.project_id, "project_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            name: message.name,
+        },
+        Payload::ProjectUpdated(message) => AnalyticsEvent::ProjectUpdated {
+            project_id: uuid(&message.project_id, "project_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::ProjectDeleted(message) => AnalyticsEvent::ProjectDeleted {
+            project_id: uuid(&message.project_id, "project_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::ProjectArchived(message) => AnalyticsEvent::ProjectArchived {
+            project_id: uuid(&message.project_id, "project_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::ProjectRestored(message) => AnalyticsEvent::ProjectRestored {
+            project_id: uuid(&message.project_id, "project_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+        },
+        Payload::ApiRequest(message) => AnalyticsEvent::ApiRequest {
+            service: message.service,
+            endpoint: message.endpoint,
+            method: message.method,
+            status_code: narrow(message.status_code, "status_code")?,
+            duration_ms: DurationMs(message.duration_ms),
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+        },
+        Payload::ProxyRequest(message) => AnalyticsEvent::ProxyRequest {
+            proxy_token_id: uuid(&message.proxy_token_id, "proxy_token_id")?,
+            user_id: uuid(&message.user_id, "user_id")?,
+            request_id: message.request_id,
+            upstream_request_id: message.upstream_request_id,
+            requested_model: message.requested_model,
+            actual_model: message.actual_model,
+            provider_type: message.provider_type,
+            key_mode: message.key_mode,
+            endpoint: message.endpoint,
+            input_tokens: message.input_tokens,
+            output_tokens: message.output_tokens,
+            total_tokens: message.total_tokens,
+            reported_cost_usd: message.reported_cost_usd,
+            latency_ms: message.latency_ms,
+            ttft_ms: message.ttft_ms,
+            is_streaming: message.is_streaming,
+            status: message.status,
+            status_code: message.status_code,
+            error_type: message.error_type,
+            error_message: message.error_message,
+        },
+        Payload::DatabaseQuery(message) => AnalyticsEvent::DatabaseQuery {
+            service: message.service,
+            query_type: message.query_type,
+            duration_ms: DurationMs(message.duration_ms),
+            rows_affected: message.rows_affected,
+            statement_fingerprint: message.statement_fingerprint,
+            is_slow: message.is_slow,
+        },
+        Payload::ApplicationError(message) => AnalyticsEvent::ApplicationError {
+            service: message.service,
+            error_type: message.error_type,
+            error_message: message.error_message,
+            user_id: optional_uuid(message.user_id.as_deref(), "user_id")?,
+            context: message
+                .context
+                .map(|context| serde_json::from_str(&context))
+                .transpose()?,
+            context_ref: message.context_ref,
+        },
+        Payload::BalanceCreated(message) => AnalyticsEvent::BalanceCreated {
+            user_id: uuid(&message.user_id, "user_id")?,
+            balance_id: uuid(&message.balance_id, "balance_id")?,
+        },
+        Payload::BalanceDeposit(message) => AnalyticsEvent::BalanceDeposit {
+            user_id: uuid(&message.user_id, "user_id")?,
+            transaction_id: uuid(&message.transaction_id, "transaction_id")?,
+            amount: message.amount,
+            reference_type: message.reference_type,
+        },
+        Payload::BalanceDebit(message) => AnalyticsEvent::BalanceDebit {
+            user_id: uuid(&message.user_id, "user_id")?,
+            transaction_id: uuid(&message.transaction_id, "transaction_id")?,
+            amount: message.amount,
+            reference_type: message.reference_type,
+        },
+        Payload::BalanceInsufficient(message) => AnalyticsEvent::BalanceInsufficient {
+            user_id: uuid(&message.user_id, "user_id")?,
+            requested_amount: message.requested_amount,
+            current_balance: message.current_balance,
+            reference_type: message.reference_type,
+        },
+        Payload::EventSummary(message) => AnalyticsEvent::EventSummary {
+            event_type: message.event_type,
+            key: message.key,
+            count: message.count,
+            p50_duration_ms: message.p50_duration_ms.map(DurationMs),
+            p95_duration_ms: message.p95_duration_ms.map(DurationMs),
+            window_start: datetime(message.window_start, "window_start")?,
+            window_end: datetime(message.window_end, "window_end")?,
+        },
+        Payload::CocoonChurn(message) => AnalyticsEvent::CocoonChurn {
+            window_seconds: message.window_seconds,
+            connects: message.connects,
+            disconnects: message.disconnects,
+        },
+        Payload::PipelineStats(message) => AnalyticsEvent::PipelineStats {
+            enqueued: message.enqueued,
+            dropped: message.dropped,
+            sent: message.sent,
+            failed: message.failed,
+            interval_secs: message.interval_secs,
+        },
+    })
+}
+
+fn timestamp(time: &DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn datetime(timestamp: Option<prost_types::Timestamp>, field: &str) -> Result<DateTime<Utc>> {
+    let timestamp = timestamp.ok_or_else(|| missing(field))?;
+    u32::try_from(timestamp.nanos)
+        .ok()
+        .and_then(|nanos| DateTime::from_timestamp(timestamp.seconds, nanos))
+        .ok_or_else(|| invalid(field))
+}
+
+fn uuid(value: &str, field: &str) -> Result<Uuid> {
+    Uuid::parse_str(value).map_err(|_| invalid(field))
+}
+
+fn optional_uuid(value: Option<&str>, field: &str) -> Result<Option<Uuid>> {
+    value.map(|value| uuid(value, field)).transpose()
+}
+
+/// Fit a `uint32` into the event field's smaller type
+fn narrow<T: TryFrom<u32>>(value: u32, field: &str) -> Result<T> {
+    T::try_from(value).map_err(|_| invalid(field))
+}
+
+fn missing(field: &str) -> AnalyticsError {
+    AnalyticsError::Protobuf(format!("missing field '{}'", field))
+}
+
+fn invalid(field: &str) -> AnalyticsError {
+    AnalyticsError::Protobuf(format!("invalid value in field '{}'", field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::samples;
+
+    fn enriched(event: AnalyticsEvent) -> EnrichedEvent {
+        let mut event = EnrichedEvent::new(event);
+        event.hostname = Some("worker-1".to_string());
+        event.environment = Some(Environment::Other("qa".to_string()));
+        event.tenant_id = Some(Uuid::new_v4());
+        event.canonical_action = Some(CanonicalAction::PullRequest);
+        event
+    }
+
+    /// Samples with their optional fields filled, complementing the mostly
+    /// empty ones of `all_variants`
+    fn filled_variants() -> Vec<AnalyticsEvent> {
+        vec![
+            AnalyticsEvent::TaskProgress {
+                task_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+                percent: Some(42),
+                stage: Some("build".to_string()),
+                message: Some("compiling".to_string()),
+            },
+            AnalyticsEvent::CocoonHeartbeat {
+                cocoon_id: Uuid::new_v4(),
+                user_id: Some(Uuid::new_v4()),
+                uptime_seconds: 3600,
+                cpu_percent: Some(12.5),
+                mem_bytes: Some(1 << 30),
+            },
+            AnalyticsEvent::ApplicationError {
+                service: "api".to_string(),
+                error_type: "timeout".to_string(),
+                error_message: "upstream timed out".to_string(),
+                user_id: Some(Uuid::new_v4()),
+                context: Some(serde_json::json!({ "attempt": 3, "hosts": ["a", "b"] })),
+                context_ref: None,
+            },
+            AnalyticsEvent::TaskFailed {
+                task_id: Uuid::new_v4(),
+                user_id: Uuid::new_v4(),
+                duration_ms: Some(DurationMs(-1)),
+                exit_code: Some(-9),
+                error: "killed".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_all_variants() {
+        let batch: Vec<_> = samples::all_variants()
+            .into_iter()
+            .chain(filled_variants())
+            .map(enriched)
+            .collect();
+
+        let body = ProtobufSerializer.serialize(&batch).unwrap();
+        let decoded = ProtobufSerializer.deserialize(&body).unwrap();
+
+        assert_eq!(decoded.len(), batch.len());
+        for (original, decoded) in batch.iter().zip(&decoded) {
+            assert_eq!(
+                serde_json::to_value(decoded).unwrap(),
+                serde_json::to_value(original).unwrap(),
+                "{}",
+                original.event.event_type()
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_covers_every_variant() {
+        let schema = include_str!("../proto/analytics.proto");
+        let oneof = schema
+            .split("oneof payload {")
+            .nth(1)
+            .and_then(|rest| rest.split('}').next())
+            .unwrap();
+        let mut cases: Vec<_> = oneof
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .collect();
+        cases.sort_unstable();
+
+        let mut event_types: Vec<_> = samples::all_variants()
+            .iter()
+            .map(|event| event.event_type())
+            .collect();
+        event_types.sort_unstable();
+        assert_eq!(cases, event_types);
+    }
+
+    #[test]
+    fn test_invalid_bodies_are_rejected() {
+        assert!(matches!(
+            ProtobufSerializer.deserialize(b"\xff\xff\xff"),
+            Err(AnalyticsError::Protobuf(_))
+        ));
+
+        let mut event = encode_event(&enriched(samples::all_variants().remove(1)));
+        event.payload = Some(Payload::AuthCodeVerified(messages::AuthCodeVerified {
+            user_id: "not-a-uuid".to_string(),
+            success: true,
+            error: None,
+        }));
+        let body = messages::EventBatch {
+            events: vec![event],
+        }
+        .encode_to_vec();
+        let error = ProtobufSerializer.deserialize(&body).unwrap_err();
+        assert!(error.to_string().contains("user_id"), "{}", error);
+        assert!(!error.is_retryable());
+    }
+}
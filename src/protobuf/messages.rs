@@ -0,0 +1,744 @@
+//! Rust types of `proto/analytics.proto`, in the shape `prost-build` generates
+//!
+//! Written out instead of generated at build time so the crate builds
+//! without `protoc`. Keep in sync with the `.proto` file.
+
+/// Request body: the events of one batch in tracking order
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventBatch {
+    #[prost(message, repeated, tag = "1")]
+    pub events: Vec<Event>,
+}
+
+/// One tracked event with its metadata
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Event {
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<::prost_types::Timestamp>,
+    #[prost(string, optional, tag = "2")]
+    pub hostname: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub environment: Option<String>,
+    #[prost(enumeration = "RetentionClass", tag = "4")]
+    pub retention: i32,
+    #[prost(uint64, tag = "5")]
+    pub sequence: u64,
+    #[prost(uint64, tag = "6")]
+    pub monotonic_ns: u64,
+    #[prost(uint32, tag = "7")]
+    pub schema_version: u32,
+    #[prost(string, optional, tag = "8")]
+    pub tenant_id: Option<String>,
+    #[prost(enumeration = "CanonicalAction", tag = "9")]
+    pub canonical_action: i32,
+    #[prost(
+        oneof = "event::Payload",
+        tags = "100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119, 120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 139, 140, 141, 142, 143, 144, 145, 146"
+    )]
+    pub payload: Option<event::Payload>,
+}
+
+pub mod event {
+    /// The `AnalyticsEvent`
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    #[allow(clippy::large_enum_variant)]
+    pub enum Payload {
+        #[prost(message, tag = "100")]
+        AuthLoginAttempt(super::AuthLoginAttempt),
+        #[prost(message, tag = "101")]
+        AuthCodeVerified(super::AuthCodeVerified),
+        #[prost(message, tag = "102")]
+        AuthTokenRefresh(super::AuthTokenRefresh),
+        #[prost(message, tag = "103")]
+        AuthTokenExpiring(super::AuthTokenExpiring),
+        #[prost(message, tag = "104")]
+        AuthSessionValidated(super::AuthSessionValidated),
+        #[prost(message, tag = "105")]
+        SessionStarted(super::SessionStarted),
+        #[prost(message, tag = "106")]
+        SessionEnded(super::SessionEnded),
+        #[prost(message, tag = "107")]
+        AuthLoginAttemptsCompacted(super::AuthLoginAttemptsCompacted),
+        #[prost(message, tag = "108")]
+        TaskCreated(super::TaskCreated),
+        #[prost(message, tag = "109")]
+        TaskStarted(super::TaskStarted),
+        #[prost(message, tag = "110")]
+        TaskProgress(super::TaskProgress),
+        #[prost(message, tag = "111")]
+        TaskCompleted(super::TaskCompleted),
+        #[prost(message, tag = "112")]
+        TaskFailed(super::TaskFailed),
+        #[prost(message, tag = "113")]
+        TaskCancelled(super::TaskCancelled),
+        #[prost(message, tag = "114")]
+        IntegrationConnected(super::IntegrationConnected),
+        #[prost(message, tag = "115")]
+        IntegrationDisconnected(super::IntegrationDisconnected),
+        #[prost(message, tag = "116")]
+        IntegrationReauthorized(super::IntegrationReauthorized),
+        #[prost(message, tag = "117")]
+        IntegrationUsed(super::IntegrationUsed),
+        #[prost(message, tag = "118")]
+        IntegrationError(super::IntegrationError),
+        #[prost(message, tag = "119")]
+        OAuthFlowStarted(super::OAuthFlowStarted),
+        #[prost(message, tag = "120")]
+        OAuthFlowCompleted(super::OAuthFlowCompleted),
+        #[prost(message, tag = "121")]
+        WebhookReceived(super::WebhookReceived),
+        #[prost(message, tag = "122")]
+        WebhookProcessed(super::WebhookProcessed),
+        #[prost(message, tag = "123")]
+        WebhookRetryScheduled(super::WebhookRetryScheduled),
+        #[prost(message, tag = "124")]
+        CocoonRegistered(super::CocoonRegistered),
+        #[prost(message, tag = "125")]
+        CocoonConnected(super::CocoonConnected),
+        #[prost(message, tag = "126")]
+        CocoonDisconnected(super::CocoonDisconnected),
+        #[prost(message, tag = "127")]
+        CocoonHeartbeat(super::CocoonHeartbeat),
+        #[prost(message, tag = "128")]
+        CocoonClaimed(super::CocoonClaimed),
+        #[prost(message, tag = "129")]
+        CocoonSetupTokenCreated(super::CocoonSetupTokenCreated),
+        #[prost(message, tag = "130")]
+        CocoonSetupTokenUsed(super::CocoonSetupTokenUsed),
+        #[prost(message, tag = "131")]
+        ProjectCreated(super::ProjectCreated),
+        #[prost(message, tag = "132")]
+        ProjectUpdated(super::ProjectUpdated),
+        #[prost(message, tag = "133")]
+        ProjectDeleted(super::ProjectDeleted),
+        #[prost(message, tag = "134")]
+        ProjectArchived(super::ProjectArchived),
+        #[prost(message, tag = "135")]
+        ProjectRestored(super::ProjectRestored),
+        #[prost(message, tag = "136")]
+        ApiRequest(super::ApiRequest),
+        #[prost(message, tag = "137")]
+        ProxyRequest(super::ProxyRequest),
+        #[prost(message, tag = "138")]
+        DatabaseQuery(super::DatabaseQuery),
+        #[prost(message, tag = "139")]
+        ApplicationError(super::ApplicationError),
+        #[prost(message, tag = "140")]
+        BalanceCreated(super::BalanceCreated),
+        #[prost(message, tag = "141")]
+        BalanceDeposit(super::BalanceDeposit),
+        #[prost(message, tag = "142")]
+        BalanceDebit(super::BalanceDebit),
+        #[prost(message, tag = "143")]
+        BalanceInsufficient(super::BalanceInsufficient),
+        #[prost(message, tag = "144")]
+        EventSummary(super::EventSummary),
+        #[prost(message, tag = "145")]
+        CocoonChurn(super::CocoonChurn),
+        #[prost(message, tag = "146")]
+        PipelineStats(super::PipelineStats),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RetentionClass {
+    Unspecified = 0,
+    Short = 1,
+    Standard = 2,
+    Long = 3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum CanonicalAction {
+    Unspecified = 0,
+    Push = 1,
+    PullRequest = 2,
+    Issue = 3,
+    Comment = 4,
+    Message = 5,
+    Reaction = 6,
+    Release = 7,
+    Install = 8,
+    Uninstall = 9,
+    Other = 10,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthLoginAttempt {
+    #[prost(string, optional, tag = "1")]
+    pub user_id: Option<String>,
+    #[prost(string, tag = "2")]
+    pub email: String,
+    #[prost(bool, tag = "3")]
+    pub success: bool,
+    #[prost(string, optional, tag = "4")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthCodeVerified {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(bool, tag = "2")]
+    pub success: bool,
+    #[prost(string, optional, tag = "3")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthTokenRefresh {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(bool, tag = "2")]
+    pub success: bool,
+    #[prost(string, optional, tag = "3")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthTokenExpiring {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub provider: Option<String>,
+    #[prost(int64, tag = "3")]
+    pub expires_in_seconds: i64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthSessionValidated {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(bool, tag = "2")]
+    pub valid: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionStarted {
+    #[prost(string, tag = "1")]
+    pub session_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionEnded {
+    #[prost(string, tag = "1")]
+    pub session_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(int64, tag = "3")]
+    pub duration_seconds: i64,
+    #[prost(string, optional, tag = "4")]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthLoginAttemptsCompacted {
+    #[prost(string, optional, tag = "1")]
+    pub user_id: Option<String>,
+    #[prost(string, tag = "2")]
+    pub email: String,
+    #[prost(uint32, tag = "3")]
+    pub attempt_count: u32,
+    #[prost(uint32, tag = "4")]
+    pub failure_count: u32,
+    #[prost(string, optional, tag = "5")]
+    pub last_error: Option<String>,
+    #[prost(message, optional, tag = "6")]
+    pub first_attempt_at: Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "7")]
+    pub last_attempt_at: Option<::prost_types::Timestamp>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskCreated {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, optional, tag = "3")]
+    pub project_id: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub cocoon_id: Option<String>,
+    #[prost(string, tag = "5")]
+    pub command: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskStarted {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, optional, tag = "3")]
+    pub cocoon_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskProgress {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(uint32, optional, tag = "3")]
+    pub percent: Option<u32>,
+    #[prost(string, optional, tag = "4")]
+    pub stage: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskCompleted {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(int64, tag = "3")]
+    pub duration_ms: i64,
+    #[prost(int32, tag = "4")]
+    pub exit_code: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskFailed {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(int64, optional, tag = "3")]
+    pub duration_ms: Option<i64>,
+    #[prost(int32, optional, tag = "4")]
+    pub exit_code: Option<i32>,
+    #[prost(string, tag = "5")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskCancelled {
+    #[prost(string, tag = "1")]
+    pub task_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(int64, optional, tag = "3")]
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntegrationConnected {
+    #[prost(string, tag = "1")]
+    pub integration_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub provider: String,
+    #[prost(string, optional, tag = "4")]
+    pub project_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntegrationDisconnected {
+    #[prost(string, tag = "1")]
+    pub integration_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub provider: String,
+    #[prost(string, optional, tag = "4")]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntegrationReauthorized {
+    #[prost(string, tag = "1")]
+    pub integration_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub provider: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntegrationUsed {
+    #[prost(string, tag = "1")]
+    pub integration_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub provider: String,
+    #[prost(string, tag = "4")]
+    pub action: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IntegrationError {
+    #[prost(string, tag = "1")]
+    pub integration_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub provider: String,
+    #[prost(string, tag = "4")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OAuthFlowStarted {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(string, tag = "3")]
+    pub state: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OAuthFlowCompleted {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(bool, tag = "3")]
+    pub success: bool,
+    #[prost(string, optional, tag = "4")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WebhookReceived {
+    #[prost(string, optional, tag = "1")]
+    pub integration_id: Option<String>,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(string, tag = "3")]
+    pub event_type: String,
+    #[prost(string, tag = "4")]
+    pub delivery_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WebhookProcessed {
+    #[prost(string, optional, tag = "1")]
+    pub integration_id: Option<String>,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(string, tag = "3")]
+    pub event_type: String,
+    #[prost(string, tag = "4")]
+    pub delivery_id: String,
+    #[prost(bool, tag = "5")]
+    pub success: bool,
+    #[prost(int64, tag = "6")]
+    pub duration_ms: i64,
+    #[prost(string, optional, tag = "7")]
+    pub error: Option<String>,
+    #[prost(uint32, tag = "8")]
+    pub retry_count: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WebhookRetryScheduled {
+    #[prost(string, tag = "1")]
+    pub delivery_id: String,
+    #[prost(string, tag = "2")]
+    pub provider: String,
+    #[prost(uint32, tag = "3")]
+    pub attempt: u32,
+    #[prost(message, optional, tag = "4")]
+    pub next_attempt_at: Option<::prost_types::Timestamp>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonRegistered {
+    #[prost(string, tag = "1")]
+    pub cocoon_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, optional, tag = "3")]
+    pub device_name: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonConnected {
+    #[prost(string, tag = "1")]
+    pub cocoon_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub user_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonDisconnected {
+    #[prost(string, tag = "1")]
+    pub cocoon_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub user_id: Option<String>,
+    #[prost(int64, tag = "3")]
+    pub duration_seconds: i64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonHeartbeat {
+    #[prost(string, tag = "1")]
+    pub cocoon_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub user_id: Option<String>,
+    #[prost(int64, tag = "3")]
+    pub uptime_seconds: i64,
+    #[prost(float, optional, tag = "4")]
+    pub cpu_percent: Option<f32>,
+    #[prost(uint64, optional, tag = "5")]
+    pub mem_bytes: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonClaimed {
+    #[prost(string, tag = "1")]
+    pub cocoon_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(bool, tag = "3")]
+    pub via_setup_token: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonSetupTokenCreated {
+    #[prost(string, tag = "1")]
+    pub token_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, optional, tag = "3")]
+    pub cocoon_name: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonSetupTokenUsed {
+    #[prost(string, tag = "1")]
+    pub token_id: String,
+    #[prost(string, tag = "2")]
+    pub cocoon_id: String,
+    #[prost(string, tag = "3")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectCreated {
+    #[prost(string, tag = "1")]
+    pub project_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub name: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectUpdated {
+    #[prost(string, tag = "1")]
+    pub project_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectDeleted {
+    #[prost(string, tag = "1")]
+    pub project_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectArchived {
+    #[prost(string, tag = "1")]
+    pub project_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProjectRestored {
+    #[prost(string, tag = "1")]
+    pub project_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApiRequest {
+    #[prost(string, tag = "1")]
+    pub service: String,
+    #[prost(string, tag = "2")]
+    pub endpoint: String,
+    #[prost(string, tag = "3")]
+    pub method: String,
+    #[prost(uint32, tag = "4")]
+    pub status_code: u32,
+    #[prost(int64, tag = "5")]
+    pub duration_ms: i64,
+    #[prost(string, optional, tag = "6")]
+    pub user_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ProxyRequest {
+    #[prost(string, tag = "1")]
+    pub proxy_token_id: String,
+    #[prost(string, tag = "2")]
+    pub user_id: String,
+    #[prost(string, tag = "3")]
+    pub request_id: String,
+    #[prost(string, optional, tag = "4")]
+    pub upstream_request_id: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub requested_model: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub actual_model: Option<String>,
+    #[prost(string, tag = "7")]
+    pub provider_type: String,
+    #[prost(string, tag = "8")]
+    pub key_mode: String,
+    #[prost(string, tag = "9")]
+    pub endpoint: String,
+    #[prost(int32, optional, tag = "10")]
+    pub input_tokens: Option<i32>,
+    #[prost(int32, optional, tag = "11")]
+    pub output_tokens: Option<i32>,
+    #[prost(int32, optional, tag = "12")]
+    pub total_tokens: Option<i32>,
+    #[prost(double, optional, tag = "13")]
+    pub reported_cost_usd: Option<f64>,
+    #[prost(int32, tag = "14")]
+    pub latency_ms: i32,
+    #[prost(int32, optional, tag = "15")]
+    pub ttft_ms: Option<i32>,
+    #[prost(bool, tag = "16")]
+    pub is_streaming: bool,
+    #[prost(string, tag = "17")]
+    pub status: String,
+    #[prost(int32, optional, tag = "18")]
+    pub status_code: Option<i32>,
+    #[prost(string, optional, tag = "19")]
+    pub error_type: Option<String>,
+    #[prost(string, optional, tag = "20")]
+    pub error_message: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DatabaseQuery {
+    #[prost(string, tag = "1")]
+    pub service: String,
+    #[prost(string, tag = "2")]
+    pub query_type: String,
+    #[prost(int64, tag = "3")]
+    pub duration_ms: i64,
+    #[prost(int64, optional, tag = "4")]
+    pub rows_affected: Option<i64>,
+    #[prost(string, optional, tag = "5")]
+    pub statement_fingerprint: Option<String>,
+    #[prost(bool, optional, tag = "6")]
+    pub is_slow: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApplicationError {
+    #[prost(string, tag = "1")]
+    pub service: String,
+    #[prost(string, tag = "2")]
+    pub error_type: String,
+    #[prost(string, tag = "3")]
+    pub error_message: String,
+    #[prost(string, optional, tag = "4")]
+    pub user_id: Option<String>,
+    #[prost(string, optional, tag = "5")]
+    pub context: Option<String>,
+    #[prost(string, optional, tag = "6")]
+    pub context_ref: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BalanceCreated {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub balance_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BalanceDeposit {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub transaction_id: String,
+    #[prost(int64, tag = "3")]
+    pub amount: i64,
+    #[prost(string, optional, tag = "4")]
+    pub reference_type: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BalanceDebit {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub transaction_id: String,
+    #[prost(int64, tag = "3")]
+    pub amount: i64,
+    #[prost(string, optional, tag = "4")]
+    pub reference_type: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BalanceInsufficient {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(int64, tag = "2")]
+    pub requested_amount: i64,
+    #[prost(int64, tag = "3")]
+    pub current_balance: i64,
+    #[prost(string, optional, tag = "4")]
+    pub reference_type: Option<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EventSummary {
+    #[prost(string, tag = "1")]
+    pub event_type: String,
+    #[prost(string, tag = "2")]
+    pub key: String,
+    #[prost(uint64, tag = "3")]
+    pub count: u64,
+    #[prost(int64, optional, tag = "4")]
+    pub p50_duration_ms: Option<i64>,
+    #[prost(int64, optional, tag = "5")]
+    pub p95_duration_ms: Option<i64>,
+    #[prost(message, optional, tag = "6")]
+    pub window_start: Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "7")]
+    pub window_end: Option<::prost_types::Timestamp>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CocoonChurn {
+    #[prost(uint64, tag = "1")]
+    pub window_seconds: u64,
+    #[prost(uint64, tag = "2")]
+    pub connects: u64,
+    #[prost(uint64, tag = "3")]
+    pub disconnects: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PipelineStats {
+    #[prost(uint64, tag = "1")]
+    pub enqueued: u64,
+    #[prost(uint64, tag = "2")]
+    pub dropped: u64,
+    #[prost(uint64, tag = "3")]
+    pub sent: u64,
+    #[prost(uint64, tag = "4")]
+    pub failed: u64,
+    #[prost(uint64, tag = "5")]
+    pub interval_secs: u64,
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// External service an integration, OAuth flow or webhook belongs to
+///
+/// Serialized as a snake_case string (`"github"`, `"gitlab"`, ...). Parsing is
+/// case-insensitive and ignores `-`, `_` and spaces, so `"GitHub"` and `"github"`
+/// end up as the same variant. Unknown providers are kept verbatim in `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum Provider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Slack,
+    Linear,
+    Jira,
+    Notion,
+    Discord,
+    Google,
+    Other(String),
+}
+
+impl Provider {
+    /// Canonical wire name of the provider
+    pub fn as_str(&self) -> &str {
+        match self {
+            Provider::GitHub => "github",
+            Provider::GitLab => "gitlab",
+            Provider::Bitbucket => "bitbucket",
+            Provider::Slack => "slack",
+            Provider::Linear => "linear",
+            Provider::Jira => "jira",
+            Provider::Notion => "notion",
+            Provider::Discord => "discord",
+            Provider::Google => "google",
+            Provider::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Provider {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        Ok(match normalized.as_str() {
+            "github" => Provider::GitHub,
+            "gitlab" => Provider::GitLab,
+            "bitbucket" => Provider::Bitbucket,
+            "slack" => Provider::Slack,
+            "linear" => Provider::Linear,
+            "jira" => Provider::Jira,
+            "notion" => Provider::Notion,
+            "discord" => Provider::Discord,
+            "google" => Provider::Google,
+            _ => Provider::Other(s.to_string()),
+        })
+    }
+}
+
+impl From<&str> for Provider {
+    fn from(s: &str) -> Self {
+        let Ok(provider) = s.parse();
+        provider
+    }
+}
+
+impl From<String> for Provider {
+    fn from(s: String) -> Self {
+        Provider::from(s.as_str())
+    }
+}
+
+impl From<Provider> for String {
+    fn from(provider: Provider) -> Self {
+        match provider {
+            Provider::Other(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_is_case_insensitive() {
+        assert_eq!("GitHub".parse::<Provider>().unwrap(), Provider::GitHub);
+        assert_eq!("github".parse::<Provider>().unwrap(), Provider::GitHub);
+        assert_eq!("Git-Lab".parse::<Provider>().unwrap(), Provider::GitLab);
+    }
+
+    #[test]
+    fn test_unknown_provider_kept_verbatim() {
+        let provider = Provider::from("Asana");
+        assert_eq!(provider, Provider::Other("Asana".to_string()));
+        assert_eq!(provider.to_string(), "Asana");
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        assert_eq!(
+            serde_json::to_string(&Provider::GitHub).unwrap(),
+            "\"github\""
+        );
+        let parsed: Provider = serde_json::from_str("\"Slack\"").unwrap();
+        assert_eq!(parsed, Provider::Slack);
+        let parsed: Provider = serde_json::from_str("\"asana\"").unwrap();
+        assert_eq!(parsed, Provider::Other("asana".to_string()));
+    }
+}
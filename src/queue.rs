@@ -0,0 +1,148 @@
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use crate::recording::RecordingHandle;
+use tokio::sync::{mpsc, oneshot};
+
+/// Item travelling from clients to the background task
+///
+/// Control messages share the queue with events so that a flush covers
+/// every event tracked before it.
+// Events make up nearly all traffic, so boxing them would only add an allocation
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Message {
+    /// Event to batch and send
+    Event(EnrichedEvent),
+
+    /// Send the current batch now and report the result
+    Flush(oneshot::Sender<Result<()>>),
+
+    /// Send the current batch and stop the background task
+    Shutdown(oneshot::Sender<()>),
+}
+
+impl Message {
+    /// Answer a control message right away, as if its work was done
+    fn acknowledge(self) {
+        match self {
+            Message::Event(_) => {}
+            Message::Flush(reply) => {
+                let _ = reply.send(Ok(()));
+            }
+            Message::Shutdown(reply) => {
+                let _ = reply.send(());
+            }
+        }
+    }
+}
+
+/// Create the event queue between clients and the background task
+///
+/// `None` creates an unbounded queue, `Some(n)` a queue holding at most `n` events.
+pub(crate) fn channel(capacity: Option<usize>) -> (EventSender, EventReceiver) {
+    match capacity {
+        Some(capacity) => {
+            let (sender, receiver) = mpsc::channel(capacity.max(1));
+            (
+                EventSender::Bounded(sender),
+                EventReceiver::Bounded(receiver),
+            )
+        }
+        None => {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            (
+                EventSender::Unbounded(sender),
+                EventReceiver::Unbounded(receiver),
+            )
+        }
+    }
+}
+
+/// Sending half of the event queue
+#[derive(Clone)]
+pub(crate) enum EventSender {
+    Unbounded(mpsc::UnboundedSender<Message>),
+    Bounded(mpsc::Sender<Message>),
+    /// No queue: events are stored in memory as they are tracked
+    Recording(RecordingHandle),
+    /// No queue: events are dropped as they are tracked
+    Discard,
+}
+
+impl EventSender {
+    /// Enqueue an event without waiting
+    pub(crate) fn send(&self, event: EnrichedEvent) -> Result<()> {
+        match self {
+            EventSender::Unbounded(sender) => sender
+                .send(Message::Event(event))
+                .map_err(|_| AnalyticsError::ChannelClosed),
+            EventSender::Bounded(sender) => {
+                sender.try_send(Message::Event(event)).map_err(|e| match e {
+                    mpsc::error::TrySendError::Full(_) => AnalyticsError::QueueFull,
+                    mpsc::error::TrySendError::Closed(_) => AnalyticsError::ChannelClosed,
+                })
+            }
+            EventSender::Recording(recording) => {
+                recording.push(event);
+                Ok(())
+            }
+            EventSender::Discard => Ok(()),
+        }
+    }
+
+    /// Enqueue an event, waiting for room in a bounded queue
+    pub(crate) async fn send_wait(&self, event: EnrichedEvent) -> Result<()> {
+        match self {
+            EventSender::Bounded(sender) => sender
+                .send(Message::Event(event))
+                .await
+                .map_err(|_| AnalyticsError::ChannelClosed),
+            _ => self.send(event),
+        }
+    }
+
+    /// Enqueue a control message, waiting for room in a bounded queue
+    pub(crate) async fn send_control(&self, message: Message) -> Result<()> {
+        match self {
+            EventSender::Unbounded(sender) => sender
+                .send(message)
+                .map_err(|_| AnalyticsError::ChannelClosed),
+            EventSender::Bounded(sender) => sender
+                .send(message)
+                .await
+                .map_err(|_| AnalyticsError::ChannelClosed),
+            EventSender::Recording(_) | EventSender::Discard => {
+                // Nothing is buffered, so there is nothing to wait for
+                message.acknowledge();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Receiving half of the event queue
+pub(crate) enum EventReceiver {
+    Unbounded(mpsc::UnboundedReceiver<Message>),
+    Bounded(mpsc::Receiver<Message>),
+}
+
+impl EventReceiver {
+    pub(crate) async fn recv(&mut self) -> Option<Message> {
+        match self {
+            EventReceiver::Unbounded(receiver) => receiver.recv().await,
+            EventReceiver::Bounded(receiver) => receiver.recv().await,
+        }
+    }
+
+    /// Next event, acknowledging control messages immediately
+    ///
+    /// Used when the caller consumes events directly and there is no batch
+    /// to flush.
+    pub(crate) async fn recv_event(&mut self) -> Option<EnrichedEvent> {
+        loop {
+            match self.recv().await? {
+                Message::Event(event) => return Some(event),
+                control => control.acknowledge(),
+            }
+        }
+    }
+}
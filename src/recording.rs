@@ -0,0 +1,127 @@
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use crate::sink::EventSink;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// In-memory store of events tracked through a recording client
+///
+/// Returned by [`AnalyticsClient::recording`](crate::AnalyticsClient::recording).
+/// Cheap to clone; all clones see the same events.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingHandle {
+    events: Arc<Mutex<Vec<EnrichedEvent>>>,
+}
+
+impl RecordingHandle {
+    pub(crate) fn push(&self, event: EnrichedEvent) {
+        self.lock().push(event);
+    }
+
+    /// Copy of all recorded events, in tracking order
+    pub fn events(&self) -> Vec<EnrichedEvent> {
+        self.lock().clone()
+    }
+
+    /// Take all recorded events, leaving the store empty
+    pub fn drain(&self) -> Vec<EnrichedEvent> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Number of recorded events
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no events are recorded
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Number of recorded events per [`event_type`](crate::AnalyticsEvent::event_type)
+    ///
+    /// Compares a whole emission profile in one assertion:
+    ///
+    /// ```rust
+    /// use lib_analytics_core::{AnalyticsClient, AnalyticsEvent};
+    /// use std::collections::BTreeMap;
+    /// use uuid::Uuid;
+    ///
+    /// let (client, recording) = AnalyticsClient::recording();
+    /// let user_id = Uuid::new_v4();
+    /// for _ in 0..2 {
+    ///     client.track(AnalyticsEvent::ProjectUpdated { project_id: Uuid::new_v4(), user_id });
+    /// }
+    /// client.track(AnalyticsEvent::ProjectDeleted { project_id: Uuid::new_v4(), user_id });
+    ///
+    /// assert_eq!(
+    ///     recording.summary(),
+    ///     BTreeMap::from([("project_deleted", 1), ("project_updated", 2)])
+    /// );
+    /// ```
+    pub fn summary(&self) -> BTreeMap<&'static str, usize> {
+        let mut summary = BTreeMap::new();
+        for event in self.lock().iter() {
+            *summary.entry(event.event.event_type()).or_insert(0) += 1;
+        }
+        summary
+    }
+
+    /// Number of recorded events of `event_type`, e.g. `"task_failed"`
+    pub fn count_of(&self, event_type: &str) -> usize {
+        self.lock()
+            .iter()
+            .filter(|event| event.event.event_type() == event_type)
+            .count()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<EnrichedEvent>> {
+        // A panic while holding the lock can't leave the Vec inconsistent
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Sink storing every delivered event in a [`RecordingHandle`]
+///
+/// Unlike [`AnalyticsClient::recording`](crate::AnalyticsClient::recording),
+/// which stores events as they are tracked, events reach this sink through
+/// the full pipeline (queue, batching, aggregation, compaction), so tests can
+/// observe exactly what would be delivered.
+///
+/// ```rust
+/// use lib_analytics_core::{AnalyticsClient, ClientConfig, RecordingSink};
+///
+/// # async fn example() {
+/// let sink = RecordingSink::new();
+/// let client = AnalyticsClient::with_sink(sink.clone(), ClientConfig::default());
+/// // ... track events, then `client.flush().await`
+/// let delivered = sink.handle().events();
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSink {
+    handle: RecordingHandle,
+}
+
+impl RecordingSink {
+    /// Sink with an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store holding the delivered events; shared by all clones of the sink
+    pub fn handle(&self) -> RecordingHandle {
+        self.handle.clone()
+    }
+}
+
+#[async_trait]
+impl EventSink for RecordingSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        self.handle.lock().extend_from_slice(batch);
+        Ok(())
+    }
+}
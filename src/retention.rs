@@ -0,0 +1,85 @@
+use crate::events::AnalyticsEvent;
+use serde::{Deserialize, Serialize};
+
+/// Storage retention hint for the ingestion service
+///
+/// Only a hint: the ingestion service maps each class to a storage tier and
+/// decides the actual TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionClass {
+    /// High-volume operational data, only useful short-term
+    Short,
+    /// Product analytics kept for the usual reporting window
+    Standard,
+    /// Business records kept indefinitely
+    Long,
+}
+
+impl AnalyticsEvent {
+    /// Retention class used when none is given explicitly
+    ///
+    /// By category:
+    /// * API request, API proxy and database query events - `Short`
+    /// * Project and balance events - `Long`
+    /// * Everything else (auth, tasks, integrations, webhooks, cocoons,
+    ///   errors) - `Standard`
+    pub fn default_retention(&self) -> RetentionClass {
+        match self {
+            AnalyticsEvent::ApiRequest { .. }
+            | AnalyticsEvent::ProxyRequest { .. }
+            | AnalyticsEvent::DatabaseQuery { .. } => RetentionClass::Short,
+            AnalyticsEvent::ProjectCreated { .. }
+            | AnalyticsEvent::ProjectUpdated { .. }
+            | AnalyticsEvent::ProjectDeleted { .. }
+            | AnalyticsEvent::ProjectArchived { .. }
+            | AnalyticsEvent::ProjectRestored { .. }
+            | AnalyticsEvent::BalanceCreated { .. }
+            | AnalyticsEvent::BalanceDeposit { .. }
+            | AnalyticsEvent::BalanceDebit { .. }
+            | AnalyticsEvent::BalanceInsufficient { .. } => RetentionClass::Long,
+            _ => RetentionClass::Standard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::DurationMs;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_default_retention_by_category() {
+        let query = AnalyticsEvent::DatabaseQuery {
+            service: "platform".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs(3),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow: None,
+        };
+        let project = AnalyticsEvent::ProjectCreated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "demo".to_string(),
+        };
+        let task = AnalyticsEvent::TaskStarted {
+            task_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            cocoon_id: None,
+        };
+
+        assert_eq!(query.default_retention(), RetentionClass::Short);
+        assert_eq!(project.default_retention(), RetentionClass::Long);
+        assert_eq!(task.default_retention(), RetentionClass::Standard);
+    }
+
+    #[test]
+    fn test_retention_serializes_snake_case() {
+        assert_eq!(
+            serde_json::to_value(RetentionClass::Long).unwrap(),
+            serde_json::json!("long")
+        );
+    }
+}
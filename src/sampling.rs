@@ -0,0 +1,209 @@
+use crate::events::AnalyticsEvent;
+use sha2::{Digest, Sha256};
+
+/// Event field whose value decides whether an event is sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SampleKey {
+    /// [`AnalyticsEvent::user_id`], keeps or drops all of a user's events
+    UserId,
+    /// [`AnalyticsEvent::session_id`], keeps or drops whole sessions
+    SessionId,
+    /// [`AnalyticsEvent::cocoon_id`], keeps or drops all events of a cocoon
+    CocoonId,
+    /// `request_id` of `ProxyRequest` events, keeps or drops whole traces
+    /// of requests retried or fanned out under one id
+    RequestId,
+}
+
+impl SampleKey {
+    /// Bytes of the key's value in `event`, `None` if the event has none
+    fn value(&self, event: &AnalyticsEvent) -> Option<Vec<u8>> {
+        match self {
+            SampleKey::UserId => event.user_id().map(|id| id.as_bytes().to_vec()),
+            SampleKey::SessionId => event.session_id().map(|id| id.as_bytes().to_vec()),
+            SampleKey::CocoonId => event.cocoon_id().map(|id| id.as_bytes().to_vec()),
+            SampleKey::RequestId => match event {
+                AnalyticsEvent::ProxyRequest { request_id, .. } => {
+                    Some(request_id.as_bytes().to_vec())
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Consistent sampling: keep a share of events, decided per key value
+///
+/// Every event carrying the [`SampleKey`] is kept or dropped based on a hash
+/// of the key's value alone, so all events of one user, session, cocoon or
+/// request share the same fate and sampled traces stay complete. The first
+/// 8 bytes of the SHA-256 of the value, read as a big-endian integer and
+/// divided by 2^64, give a fraction in `[0, 1)`; the event is kept if that
+/// is below `rate`. The hash is the same in every process and version, so
+/// services sampling on the same key at the same rate keep the same
+/// users, and raising the rate only adds keys to the sample. A
+/// [`seed`](Self::seed) picks a different, equally reproducible sample.
+///
+/// Events without a value for the key (e.g. `DatabaseQuery` for
+/// [`SampleKey::UserId`]) are always kept. Restrict sampling further with
+/// [`event_types`](Self::event_types). Dropped events are counted in
+/// [`MetricsSnapshot::sampled_out_events`](crate::MetricsSnapshot::sampled_out_events).
+///
+/// ```rust
+/// use lib_analytics_core::{ClientConfig, SampleKey, Sampling};
+///
+/// // A tenth of users' API requests, with all requests of each sampled user
+/// let config = ClientConfig {
+///     sampling: Some(Sampling::new(SampleKey::UserId, 0.1).event_types(["api_request"])),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sampling {
+    key: SampleKey,
+    rate: f64,
+    event_types: Option<Vec<String>>,
+    seed: Option<u64>,
+}
+
+impl Sampling {
+    /// Keep the share `rate` (clamped to `0.0..=1.0`) of `key` values
+    pub fn new(key: SampleKey, rate: f64) -> Self {
+        Self {
+            key,
+            rate: if rate.is_nan() {
+                1.0
+            } else {
+                rate.clamp(0.0, 1.0)
+            },
+            event_types: None,
+            seed: None,
+        }
+    }
+
+    /// Only sample events of these types (as named by
+    /// [`AnalyticsEvent::event_type`]), keeping all others
+    pub fn event_types<I, S>(mut self, event_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.event_types = Some(event_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Mix `seed` into the hash, selecting another sample of key values
+    ///
+    /// Clients sampling with the same seed, key and rate make the same
+    /// decisions for the same events, so a load experiment or test can be
+    /// replayed exactly, while a different seed draws an independent sample,
+    /// e.g. a fresh cohort for the next experiment. Services that should
+    /// keep the same users must share the seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Whether `event` is in the sample
+    pub(crate) fn keeps(&self, event: &AnalyticsEvent) -> bool {
+        let sampled = match &self.event_types {
+            Some(types) => types.iter().any(|t| t == event.event_type()),
+            None => true,
+        };
+        if !sampled {
+            return true;
+        }
+
+        match self.key.value(event) {
+            Some(value) => fraction(self.seed, &value) < self.rate,
+            None => true,
+        }
+    }
+}
+
+/// Position of `value` in `[0, 1)`, uniformly spread by hashing
+///
+/// A seed is hashed as 8 big-endian bytes before the value; without one the
+/// value is hashed alone, keeping unseeded samples as they were.
+fn fraction(seed: Option<u64>, value: &[u8]) -> f64 {
+    let mut hasher = Sha256::new();
+    if let Some(seed) = seed {
+        hasher.update(seed.to_be_bytes());
+    }
+    hasher.update(value);
+    let digest = hasher.finalize();
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix) as f64 / 2f64.powi(64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn api_request(user_id: Uuid) -> AnalyticsEvent {
+        AnalyticsEvent::ApiRequest {
+            service: "api".to_string(),
+            endpoint: "/projects".to_string(),
+            method: "GET".to_string(),
+            status_code: 200,
+            duration_ms: Default::default(),
+            user_id: Some(user_id),
+        }
+    }
+
+    #[test]
+    fn test_same_key_same_decision() {
+        let sampling = Sampling::new(SampleKey::UserId, 0.5);
+
+        for _ in 0..50 {
+            let user_id = Uuid::new_v4();
+            let kept = sampling.keeps(&api_request(user_id));
+            let project = AnalyticsEvent::ProjectUpdated {
+                project_id: Uuid::new_v4(),
+                user_id,
+            };
+            assert_eq!(sampling.keeps(&project), kept);
+            assert_eq!(sampling.keeps(&api_request(user_id)), kept);
+        }
+    }
+
+    #[test]
+    fn test_rate_is_approximated() {
+        let sampling = Sampling::new(SampleKey::UserId, 0.2);
+        let kept = (0..10_000)
+            .filter(|_| sampling.keeps(&api_request(Uuid::new_v4())))
+            .count();
+        assert!((1_700..2_300).contains(&kept), "{}", kept);
+
+        let none = Sampling::new(SampleKey::UserId, 0.0);
+        let all = Sampling::new(SampleKey::UserId, 1.0);
+        let event = api_request(Uuid::new_v4());
+        assert!(!none.keeps(&event));
+        assert!(all.keeps(&event));
+    }
+
+    #[test]
+    fn test_events_without_key_or_other_types_are_kept() {
+        let sampling = Sampling::new(SampleKey::SessionId, 0.0);
+        assert!(sampling.keeps(&api_request(Uuid::new_v4())));
+
+        let sampling = Sampling::new(SampleKey::UserId, 0.0).event_types(["api_request"]);
+        assert!(!sampling.keeps(&api_request(Uuid::new_v4())));
+        assert!(sampling.keeps(&AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        }));
+    }
+
+    #[test]
+    fn test_fraction_is_stable() {
+        // Pinned so a hash change, which would reshuffle every sample, is noticed
+        assert_eq!(
+            fraction(None, b""),
+            0xe3b0c44298fc1c14_u64 as f64 / 2f64.powi(64)
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use crate::events::AnalyticsEvent;
+use serde_json::Value;
+
+/// Marker appended to values that were cut short
+pub const TRUNCATION_MARKER: &str = "...(truncated)";
+
+/// Maximum nesting depth kept in `context` values
+const MAX_CONTEXT_DEPTH: usize = 8;
+
+/// Cap free-form string fields and `context` values of an event
+///
+/// Only fields that may carry arbitrary caller-provided text (error messages,
+/// context blobs) are touched; identifiers and enum-like strings are left alone.
+/// `context` is kept whole if `keep_context` is set (it is uploaded instead).
+pub(crate) fn truncate_event(event: &mut AnalyticsEvent, max_len: usize, keep_context: bool) {
+    match event {
+        AnalyticsEvent::AuthLoginAttempt { error, .. }
+        | AnalyticsEvent::AuthCodeVerified { error, .. }
+        | AnalyticsEvent::AuthTokenRefresh { error, .. }
+        | AnalyticsEvent::OAuthFlowCompleted { error, .. }
+        | AnalyticsEvent::WebhookProcessed { error, .. } => {
+            truncate_opt_string(error, max_len);
+        }
+        AnalyticsEvent::TaskFailed { error, .. }
+        | AnalyticsEvent::IntegrationError { error, .. } => {
+            truncate_string(error, max_len);
+        }
+        AnalyticsEvent::TaskProgress { message, .. } => {
+            truncate_opt_string(message, max_len);
+        }
+        AnalyticsEvent::IntegrationDisconnected { reason, .. } => {
+            truncate_opt_string(reason, max_len);
+        }
+        AnalyticsEvent::ProxyRequest { error_message, .. } => {
+            truncate_opt_string(error_message, max_len);
+        }
+        AnalyticsEvent::ApplicationError {
+            error_message,
+            context,
+            ..
+        } => {
+            truncate_string(error_message, max_len);
+            if let Some(context) = context.as_mut().filter(|_| !keep_context) {
+                truncate_value(context, max_len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Which top-level keys of `context` values are sent
+///
+/// Matching is case-insensitive, so a denylist entry `authorization` also
+/// removes `Authorization`. Nested objects are not inspected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ContextFilter {
+    /// Send context values unchanged (the default)
+    #[default]
+    KeepAll,
+    /// Keep only the listed keys
+    Allow(Vec<String>),
+    /// Remove the listed keys
+    Deny(Vec<String>),
+}
+
+impl ContextFilter {
+    /// Keep only the given top-level keys
+    pub fn allow<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ContextFilter::Allow(keys.into_iter().map(Into::into).collect())
+    }
+
+    /// Remove the given top-level keys
+    pub fn deny<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ContextFilter::Deny(keys.into_iter().map(Into::into).collect())
+    }
+
+    fn keeps(&self, key: &str) -> bool {
+        let listed = |keys: &[String]| keys.iter().any(|k| k.eq_ignore_ascii_case(key));
+        match self {
+            ContextFilter::KeepAll => true,
+            ContextFilter::Allow(keys) => listed(keys),
+            ContextFilter::Deny(keys) => !listed(keys),
+        }
+    }
+}
+
+/// Drop `context` keys rejected by `filter`
+///
+/// Non-object context values are left as they are.
+pub(crate) fn filter_context(event: &mut AnalyticsEvent, filter: &ContextFilter) {
+    if *filter == ContextFilter::KeepAll {
+        return;
+    }
+
+    if let AnalyticsEvent::ApplicationError {
+        context: Some(Value::Object(map)),
+        ..
+    } = event
+    {
+        map.retain(|key, _| filter.keeps(key));
+    }
+}
+
+/// Which event types may carry a `user_id`
+///
+/// Event types are named as by [`AnalyticsEvent::event_type`], e.g.
+/// `api_request`. Events of other types have their `user_id` removed before
+/// they are queued. Only optional `user_id` fields can be removed
+/// (`AuthLoginAttempt`, `AuthLoginAttemptsCompacted`, `CocoonConnected`,
+/// `CocoonDisconnected`, `CocoonHeartbeat`, `ApiRequest` and
+/// `ApplicationError`); events that belong to a user by definition, like
+/// `TaskCreated`, always keep it, and events like `DatabaseQuery` never
+/// carry one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UserIdPolicy {
+    /// Every event type may carry a `user_id` (the default)
+    #[default]
+    AllowAll,
+    /// Only the listed event types may carry a `user_id`
+    Allow(Vec<String>),
+}
+
+impl UserIdPolicy {
+    /// Let only the given event types carry a `user_id`
+    pub fn allow<I, S>(event_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        UserIdPolicy::Allow(event_types.into_iter().map(Into::into).collect())
+    }
+
+    fn permits(&self, event_type: &str) -> bool {
+        match self {
+            UserIdPolicy::AllowAll => true,
+            UserIdPolicy::Allow(event_types) => event_types.iter().any(|t| t == event_type),
+        }
+    }
+}
+
+/// Remove `user_id` from an event whose type `policy` doesn't permit it on
+///
+/// Returns whether a user id was removed.
+pub(crate) fn strip_user_id(event: &mut AnalyticsEvent, policy: &UserIdPolicy) -> bool {
+    if policy.permits(event.event_type()) {
+        return false;
+    }
+
+    match event {
+        AnalyticsEvent::AuthLoginAttempt { user_id, .. }
+        | AnalyticsEvent::AuthLoginAttemptsCompacted { user_id, .. }
+        | AnalyticsEvent::CocoonConnected { user_id, .. }
+        | AnalyticsEvent::CocoonDisconnected { user_id, .. }
+        | AnalyticsEvent::CocoonHeartbeat { user_id, .. }
+        | AnalyticsEvent::ApiRequest { user_id, .. }
+        | AnalyticsEvent::ApplicationError { user_id, .. } => user_id.take().is_some(),
+        _ => false,
+    }
+}
+
+/// Truncate a string to at most `max_len` bytes (on a char boundary) plus the marker
+pub(crate) fn truncate_string(value: &mut String, max_len: usize) {
+    if value.len() <= max_len {
+        return;
+    }
+
+    let mut end = max_len;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    value.truncate(end);
+    value.push_str(TRUNCATION_MARKER);
+}
+
+fn truncate_opt_string(value: &mut Option<String>, max_len: usize) {
+    if let Some(value) = value {
+        truncate_string(value, max_len);
+    }
+}
+
+/// Trim a JSON value so that its serialized form stays around `max_len` bytes
+///
+/// Strings are capped individually and overly deep nesting is cut off first.
+/// If the value is still too large afterwards, it is collapsed into a single
+/// truncated string holding the beginning of its JSON representation.
+pub(crate) fn truncate_value(value: &mut Value, max_len: usize) {
+    trim_value(value, max_len, 0);
+
+    let serialized = value.to_string();
+    if serialized.len() > max_len {
+        let mut collapsed = serialized;
+        truncate_string(&mut collapsed, max_len);
+        *value = Value::String(collapsed);
+    }
+}
+
+fn trim_value(value: &mut Value, max_len: usize, depth: usize) {
+    match value {
+        Value::String(s) => truncate_string(s, max_len),
+        Value::Array(_) | Value::Object(_) if depth >= MAX_CONTEXT_DEPTH => {
+            *value = Value::String(TRUNCATION_MARKER.to_string());
+        }
+        Value::Array(items) => {
+            for item in items {
+                trim_value(item, max_len, depth + 1);
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                trim_value(item, max_len, depth + 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_truncates_large_error_message() {
+        let mut event = AnalyticsEvent::ApplicationError {
+            service: "test".to_string(),
+            error_type: "panic".to_string(),
+            error_message: "x".repeat(1024 * 1024),
+            user_id: None,
+            context: Some(json!({ "dump": "y".repeat(1024 * 1024) })),
+            context_ref: None,
+        };
+
+        truncate_event(&mut event, 8 * 1024, false);
+
+        let AnalyticsEvent::ApplicationError {
+            error_message,
+            context,
+            ..
+        } = event
+        else {
+            unreachable!();
+        };
+        assert_eq!(error_message.len(), 8 * 1024 + TRUNCATION_MARKER.len());
+        assert!(error_message.ends_with(TRUNCATION_MARKER));
+        let context = context.unwrap();
+        let collapsed = context.as_str().unwrap();
+        assert!(collapsed.ends_with(TRUNCATION_MARKER));
+        assert!(collapsed.len() <= 8 * 1024 + TRUNCATION_MARKER.len());
+    }
+
+    fn error_with_context(context: Value) -> AnalyticsEvent {
+        AnalyticsEvent::ApplicationError {
+            service: "billing".to_string(),
+            error_type: "payment".to_string(),
+            error_message: "declined".to_string(),
+            user_id: None,
+            context: Some(context),
+            context_ref: None,
+        }
+    }
+
+    fn context_of(event: AnalyticsEvent) -> Value {
+        match event {
+            AnalyticsEvent::ApplicationError { context, .. } => context.unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_denylist_strips_password() {
+        let mut event = error_with_context(json!({
+            "password": "hunter2",
+            "Authorization": "Bearer abc",
+            "order_id": 42,
+        }));
+
+        filter_context(
+            &mut event,
+            &ContextFilter::deny(["password", "token", "authorization"]),
+        );
+
+        assert_eq!(context_of(event), json!({ "order_id": 42 }));
+    }
+
+    #[test]
+    fn test_allowlist_keeps_listed_keys() {
+        let mut event = error_with_context(json!({
+            "password": "hunter2",
+            "order_id": 42,
+        }));
+
+        filter_context(&mut event, &ContextFilter::allow(["order_id"]));
+
+        assert_eq!(context_of(event), json!({ "order_id": 42 }));
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        let mut value = "ééé".to_string();
+        truncate_string(&mut value, 3);
+        assert_eq!(value, format!("é{}", TRUNCATION_MARKER));
+    }
+
+    #[test]
+    fn test_small_values_untouched() {
+        let mut value = json!({ "order_id": 42, "tags": ["a", "b"] });
+        let original = value.clone();
+        truncate_value(&mut value, 1024);
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn test_deep_nesting_trimmed() {
+        let mut value = json!("leaf");
+        for _ in 0..20 {
+            value = json!({ "nested": value });
+        }
+        truncate_value(&mut value, 8 * 1024);
+        assert!(value.to_string().contains(TRUNCATION_MARKER));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::events::AnalyticsEvent;
+
+// Schema versions per event variant.
+//
+// Bump a variant's version (or give it its own constant) whenever its
+// serialized fields change: added, removed, renamed or retyped. Note the
+// change next to the constant so the ingestion service knows what to expect.
+
+/// Fields unchanged since the variant was introduced
+const V1: u32 = 1;
+
+/// `WebhookProcessed`: v2 added `retry_count`
+const WEBHOOK_PROCESSED: u32 = 2;
+
+/// `ApplicationError`: v2 added `context_ref`
+const APPLICATION_ERROR: u32 = 2;
+
+/// `DatabaseQuery`: v2 added `statement_fingerprint` and `is_slow`
+const DATABASE_QUERY: u32 = 2;
+
+impl AnalyticsEvent {
+    /// Version of this variant's field layout
+    ///
+    /// Sent with every event (`EnrichedEvent::schema_version`) so the
+    /// ingestion service can parse old and new shapes of the same event type
+    /// deterministically.
+    pub fn schema_version(&self) -> u32 {
+        match self {
+            AnalyticsEvent::WebhookProcessed { .. } => WEBHOOK_PROCESSED,
+            AnalyticsEvent::ApplicationError { .. } => APPLICATION_ERROR,
+            AnalyticsEvent::DatabaseQuery { .. } => DATABASE_QUERY,
+
+            AnalyticsEvent::AuthLoginAttempt { .. }
+            | AnalyticsEvent::AuthCodeVerified { .. }
+            | AnalyticsEvent::AuthTokenRefresh { .. }
+            | AnalyticsEvent::AuthTokenExpiring { .. }
+            | AnalyticsEvent::AuthSessionValidated { .. }
+            | AnalyticsEvent::SessionStarted { .. }
+            | AnalyticsEvent::SessionEnded { .. }
+            | AnalyticsEvent::AuthLoginAttemptsCompacted { .. }
+            | AnalyticsEvent::TaskCreated { .. }
+            | AnalyticsEvent::TaskStarted { .. }
+            | AnalyticsEvent::TaskProgress { .. }
+            | AnalyticsEvent::TaskCompleted { .. }
+            | AnalyticsEvent::TaskFailed { .. }
+            | AnalyticsEvent::TaskCancelled { .. }
+            | AnalyticsEvent::IntegrationConnected { .. }
+            | AnalyticsEvent::IntegrationDisconnected { .. }
+            | AnalyticsEvent::IntegrationReauthorized { .. }
+            | AnalyticsEvent::IntegrationUsed { .. }
+            | AnalyticsEvent::IntegrationError { .. }
+            | AnalyticsEvent::OAuthFlowStarted { .. }
+            | AnalyticsEvent::OAuthFlowCompleted { .. }
+            | AnalyticsEvent::WebhookReceived { .. }
+            | AnalyticsEvent::WebhookRetryScheduled { .. }
+            | AnalyticsEvent::CocoonRegistered { .. }
+            | AnalyticsEvent::CocoonConnected { .. }
+            | AnalyticsEvent::CocoonDisconnected { .. }
+            | AnalyticsEvent::CocoonHeartbeat { .. }
+            | AnalyticsEvent::CocoonClaimed { .. }
+            | AnalyticsEvent::CocoonSetupTokenCreated { .. }
+            | AnalyticsEvent::CocoonSetupTokenUsed { .. }
+            | AnalyticsEvent::ProjectCreated { .. }
+            | AnalyticsEvent::ProjectUpdated { .. }
+            | AnalyticsEvent::ProjectDeleted { .. }
+            | AnalyticsEvent::ProjectArchived { .. }
+            | AnalyticsEvent::ProjectRestored { .. }
+            | AnalyticsEvent::ApiRequest { .. }
+            | AnalyticsEvent::ProxyRequest { .. }
+            | AnalyticsEvent::BalanceCreated { .. }
+            | AnalyticsEvent::BalanceDeposit { .. }
+            | AnalyticsEvent::BalanceDebit { .. }
+            | AnalyticsEvent::BalanceInsufficient { .. }
+            | AnalyticsEvent::EventSummary { .. }
+            | AnalyticsEvent::CocoonChurn { .. }
+            | AnalyticsEvent::PipelineStats { .. } => V1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::samples::all_variants;
+    use crate::events::EnrichedEvent;
+
+    #[test]
+    fn test_every_variant_has_a_schema_version() {
+        for event in all_variants() {
+            assert!(
+                event.schema_version() > 0,
+                "{} has no schema version",
+                event.event_type()
+            );
+        }
+    }
+
+    #[test]
+    fn test_schema_version_is_serialized() {
+        let event = EnrichedEvent::new(all_variants().remove(0));
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["schema_version"], event.event.schema_version());
+    }
+}
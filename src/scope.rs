@@ -0,0 +1,156 @@
+use crate::client::AnalyticsClient;
+use crate::error::Result;
+use crate::events::AnalyticsEvent;
+use uuid::Uuid;
+
+/// Ids shared by all events tracked within a scope (e.g. one request)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScopeContext {
+    pub user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub cocoon_id: Option<Uuid>,
+}
+
+impl ScopeContext {
+    /// Fill optional ids the event left as `None`
+    ///
+    /// Ids already set on the event are never overridden, and required
+    /// (non-optional) id fields are left untouched.
+    pub(crate) fn apply(&self, event: &mut AnalyticsEvent) {
+        match event {
+            AnalyticsEvent::AuthLoginAttempt { user_id, .. }
+            | AnalyticsEvent::CocoonConnected { user_id, .. }
+            | AnalyticsEvent::CocoonDisconnected { user_id, .. }
+            | AnalyticsEvent::CocoonHeartbeat { user_id, .. }
+            | AnalyticsEvent::ApiRequest { user_id, .. }
+            | AnalyticsEvent::ApplicationError { user_id, .. } => {
+                fill(user_id, self.user_id);
+            }
+            AnalyticsEvent::TaskCreated {
+                project_id,
+                cocoon_id,
+                ..
+            } => {
+                fill(project_id, self.project_id);
+                fill(cocoon_id, self.cocoon_id);
+            }
+            AnalyticsEvent::TaskStarted { cocoon_id, .. } => {
+                fill(cocoon_id, self.cocoon_id);
+            }
+            AnalyticsEvent::IntegrationConnected { project_id, .. } => {
+                fill(project_id, self.project_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn fill(slot: &mut Option<Uuid>, value: Option<Uuid>) {
+    if slot.is_none() {
+        *slot = value;
+    }
+}
+
+/// Client wrapper that injects fixed ids into every tracked event
+///
+/// Created with [`AnalyticsClient::scoped`]. Shares the underlying client's
+/// queue, so it is cheap to create per request.
+#[derive(Clone)]
+pub struct ScopedClient {
+    client: AnalyticsClient,
+    scope: ScopeContext,
+}
+
+impl ScopedClient {
+    pub(crate) fn new(client: AnalyticsClient, scope: ScopeContext) -> Self {
+        Self { client, scope }
+    }
+
+    /// Ids injected by this client
+    pub fn scope(&self) -> &ScopeContext {
+        &self.scope
+    }
+
+    /// Track an event, filling missing ids from the scope
+    pub fn track(&self, event: impl Into<AnalyticsEvent>) {
+        let _ = self.try_track(event);
+    }
+
+    /// Track an event, filling missing ids from the scope, reporting whether it was accepted
+    pub fn try_track(&self, event: impl Into<AnalyticsEvent>) -> Result<()> {
+        let mut event = event.into();
+        self.scope.apply(&mut event);
+        self.client.try_track(event)
+    }
+
+    /// Track an event only if a condition is true
+    pub fn track_if(&self, condition: bool, event: impl Into<AnalyticsEvent>) {
+        if condition {
+            self.track(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::DurationMs;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_scoped_fills_missing_ids() {
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+
+        let scope = ScopeContext {
+            user_id: Some(Uuid::new_v4()),
+            project_id: Some(Uuid::new_v4()),
+            cocoon_id: Some(Uuid::new_v4()),
+        };
+        let scoped = client.scoped(scope);
+
+        scoped.track(AnalyticsEvent::TaskCreated {
+            task_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            project_id: None,
+            cocoon_id: None,
+            command: "ls".to_string(),
+        });
+
+        let event = stream.next().await.unwrap().event;
+        let AnalyticsEvent::TaskCreated {
+            project_id,
+            cocoon_id,
+            ..
+        } = event
+        else {
+            panic!("unexpected event: {:?}", event);
+        };
+        assert_eq!(project_id, scope.project_id);
+        assert_eq!(cocoon_id, scope.cocoon_id);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_keeps_explicit_ids() {
+        let (client, stream) = AnalyticsClient::channel();
+        let mut stream = std::pin::pin!(stream);
+
+        let explicit_user = Uuid::new_v4();
+        let scoped = client.scoped(ScopeContext {
+            user_id: Some(Uuid::new_v4()),
+            ..Default::default()
+        });
+
+        scoped.track(AnalyticsEvent::ApiRequest {
+            service: "platform".to_string(),
+            endpoint: "/tasks".to_string(),
+            method: "GET".to_string(),
+            status_code: 200,
+            duration_ms: DurationMs(3),
+            user_id: Some(explicit_user),
+        });
+
+        let event = stream.next().await.unwrap().event;
+        assert_eq!(event.user_id(), Some(explicit_user));
+    }
+}
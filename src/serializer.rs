@@ -0,0 +1,546 @@
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Wire format for batches sent by the HTTP sink
+///
+/// The built-in [`JsonSerializer`] (default) and [`NdjsonSerializer`] cover
+/// the formats the ingestion service understands. Custom implementations can
+/// produce anything, e.g. a columnar format a warehouse ingests directly, but
+/// the server behind `analytics_url` must accept whatever is configured: the
+/// client only sets the `Content-Type` header to [`content_type`](Self::content_type).
+pub trait BatchSerializer: Send + Sync + 'static {
+    /// Value of the `Content-Type` header sent with each batch
+    fn content_type(&self) -> &str;
+
+    /// Encode a non-empty batch into a request body
+    fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>>;
+}
+
+impl fmt::Debug for dyn BatchSerializer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchSerializer")
+            .field("content_type", &self.content_type())
+            .finish()
+    }
+}
+
+/// Batch encoded as a JSON array of events (`application/json`)
+///
+/// Events are encoded one at a time; an event that fails to serialize is
+/// logged and left out so it doesn't take the rest of the batch down with it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonSerializer;
+
+impl BatchSerializer for JsonSerializer {
+    fn content_type(&self) -> &str {
+        "application/json"
+    }
+
+    fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>> {
+        Ok(json_array(encode_events(batch)?))
+    }
+}
+
+/// Batch encoded as newline-delimited JSON, one event per line
+/// (`application/x-ndjson`)
+///
+/// Unserializable events are skipped like with [`JsonSerializer`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NdjsonSerializer;
+
+impl BatchSerializer for NdjsonSerializer {
+    fn content_type(&self) -> &str {
+        "application/x-ndjson"
+    }
+
+    fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>> {
+        Ok(ndjson_lines(encode_events(batch)?))
+    }
+}
+
+/// Layout of each event object in the request body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Envelope {
+    /// The [`EnrichedEvent`] as-is: metadata at the top level next to the
+    /// tagged `event` object
+    ///
+    /// `{"timestamp": .., "event": {"type": .., ..}, "hostname": .., ..}`
+    #[default]
+    Flat,
+    /// The tagged event and its metadata in separate objects
+    ///
+    /// `{"event": {"type": .., ..}, "meta": {"timestamp": .., "hostname": .., ..}}`
+    EventMeta,
+}
+
+/// Casing of object keys in the request body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FieldCase {
+    /// `schema_version`, `duration_ms` (the crate's own field names)
+    #[default]
+    Snake,
+    /// `schemaVersion`, `durationMs`
+    Camel,
+}
+
+/// Encoding of the event `timestamp` in the request body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 string, e.g. `"2025-01-31T12:00:00.123456Z"` (chrono's default)
+    #[default]
+    Rfc3339,
+    /// Integer milliseconds since the Unix epoch, e.g. `1738324800123`
+    ///
+    /// Cheaper to store and parse for backends keeping epoch millis.
+    /// Sub-millisecond precision is dropped.
+    UnixMillis,
+}
+
+/// Serializer reshaping events for consumers that expect another layout
+///
+/// Wraps the JSON array or NDJSON encoding of [`JsonSerializer`] /
+/// [`NdjsonSerializer`] and transforms each event into the configured
+/// [`Envelope`] and [`FieldCase`] on the way out, so services feeding e.g.
+/// a data warehouse don't need a server-side rename pipeline.
+///
+/// Casing applies to the keys of the envelope, the metadata and the event's
+/// own fields. Values are left alone: the event `type` tag stays snake_case,
+/// and keys inside free-form JSON (`ApplicationError::context`) are not
+/// renamed.
+///
+/// The [`TimestampFormat`] applies to the event's `timestamp`; other date
+/// fields inside events stay RFC 3339.
+///
+/// ```rust
+/// use lib_analytics_core::{ClientConfig, EnvelopeSerializer};
+/// use std::sync::Arc;
+///
+/// let config = ClientConfig {
+///     serializer: Arc::new(EnvelopeSerializer::warehouse()),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvelopeSerializer {
+    envelope: Envelope,
+    case: FieldCase,
+    timestamps: TimestampFormat,
+    ndjson: bool,
+}
+
+impl EnvelopeSerializer {
+    /// Events in a JSON array (`application/json`)
+    pub fn json(envelope: Envelope, case: FieldCase) -> Self {
+        Self {
+            envelope,
+            case,
+            timestamps: TimestampFormat::Rfc3339,
+            ndjson: false,
+        }
+    }
+
+    /// One event per line (`application/x-ndjson`)
+    pub fn ndjson(envelope: Envelope, case: FieldCase) -> Self {
+        Self {
+            envelope,
+            case,
+            timestamps: TimestampFormat::Rfc3339,
+            ndjson: true,
+        }
+    }
+
+    /// JSON array of camelCase `{"event": {..}, "meta": {..}}` objects, the
+    /// layout warehouse ingestion expects
+    pub fn warehouse() -> Self {
+        Self::json(Envelope::EventMeta, FieldCase::Camel)
+    }
+
+    /// Encode `timestamp` as configured instead of RFC 3339
+    pub fn with_timestamps(mut self, timestamps: TimestampFormat) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Decode a body produced by this serializer back into events
+    pub fn deserialize(&self, body: &[u8]) -> Result<Vec<EnrichedEvent>> {
+        let values: Vec<Value> = if self.ndjson {
+            body.split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(serde_json::from_slice)
+                .collect::<std::result::Result<_, _>>()?
+        } else {
+            serde_json::from_slice(body)?
+        };
+
+        values
+            .into_iter()
+            .map(|value| Ok(serde_json::from_value(self.restore(value))?))
+            .collect()
+    }
+
+    /// Turn the serialized `EnrichedEvent` into the configured shape
+    fn reshape(&self, mut value: Value) -> Value {
+        if self.timestamps == TimestampFormat::UnixMillis {
+            if let Some(timestamp) = value.get_mut("timestamp") {
+                to_unix_millis(timestamp);
+            }
+        }
+        if self.case == FieldCase::Camel {
+            rename_keys(&mut value, to_camel_case);
+        }
+
+        match (self.envelope, value) {
+            (Envelope::EventMeta, Value::Object(mut meta)) => {
+                let event = meta.remove("event").unwrap_or(Value::Null);
+                serde_json::json!({ "event": event, "meta": meta })
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// Inverse of [`reshape`](Self::reshape)
+    fn restore(&self, value: Value) -> Value {
+        let mut value = match (self.envelope, value) {
+            (Envelope::EventMeta, Value::Object(mut envelope)) => {
+                let event = envelope.remove("event").unwrap_or(Value::Null);
+                let mut meta = match envelope.remove("meta") {
+                    Some(Value::Object(meta)) => meta,
+                    _ => Map::new(),
+                };
+                meta.insert("event".to_string(), event);
+                Value::Object(meta)
+            }
+            (_, value) => value,
+        };
+
+        if self.case == FieldCase::Camel {
+            rename_keys(&mut value, to_snake_case);
+        }
+        if self.timestamps == TimestampFormat::UnixMillis {
+            if let Some(timestamp) = value.get_mut("timestamp") {
+                from_unix_millis(timestamp);
+            }
+        }
+        value
+    }
+}
+
+impl BatchSerializer for EnvelopeSerializer {
+    fn content_type(&self) -> &str {
+        if self.ndjson {
+            NdjsonSerializer.content_type()
+        } else {
+            JsonSerializer.content_type()
+        }
+    }
+
+    fn serialize(&self, batch: &[EnrichedEvent]) -> Result<Vec<u8>> {
+        // Nothing to reshape; also keeps the original field order
+        if self.envelope == Envelope::Flat
+            && self.case == FieldCase::Snake
+            && self.timestamps == TimestampFormat::Rfc3339
+        {
+            let encoded = encode_events(batch)?;
+            return Ok(if self.ndjson {
+                ndjson_lines(encoded)
+            } else {
+                json_array(encoded)
+            });
+        }
+
+        let mut last_error = None;
+        let mut reshaped = Vec::with_capacity(batch.len());
+        for event in batch {
+            match serde_json::to_value(event) {
+                Ok(value) => reshaped.push(self.reshape(value)),
+                Err(e) => {
+                    tracing::warn!(
+                        "Dropping analytics event {}: {}",
+                        event.event.event_type(),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = last_error.filter(|_| reshaped.is_empty()) {
+            return Err(AnalyticsError::Serialization(e));
+        }
+
+        // Values always serialize
+        let encoded = encode_each(&reshaped, |_, _| {});
+        Ok(if self.ndjson {
+            ndjson_lines(encoded)
+        } else {
+            json_array(encoded)
+        })
+    }
+}
+
+/// Rename the keys of an enriched event object and of its `event` object
+fn rename_keys(value: &mut Value, rename: fn(&str) -> String) {
+    let Value::Object(object) = value else {
+        return;
+    };
+
+    *object = std::mem::take(object)
+        .into_iter()
+        .map(|(key, mut field)| {
+            if key == "event" {
+                if let Value::Object(event) = &mut field {
+                    *event = std::mem::take(event)
+                        .into_iter()
+                        .map(|(key, field)| (rename(&key), field))
+                        .collect();
+                }
+            }
+            (rename(&key), field)
+        })
+        .collect();
+}
+
+/// Replace an RFC 3339 string with epoch milliseconds
+fn to_unix_millis(value: &mut Value) {
+    if let Some(timestamp) = value.as_str().and_then(|s| s.parse::<DateTime<Utc>>().ok()) {
+        *value = Value::from(timestamp.timestamp_millis());
+    }
+}
+
+/// Inverse of [`to_unix_millis`]
+fn from_unix_millis(value: &mut Value) {
+    if let Some(timestamp) = value.as_i64().and_then(DateTime::from_timestamp_millis) {
+        *value = Value::String(timestamp.to_rfc3339());
+    }
+}
+
+fn to_camel_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn to_snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Join encoded items into a JSON array
+fn json_array(encoded: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(encoded.iter().map(|e| e.len() + 1).sum::<usize>() + 2);
+    body.push(b'[');
+    for (i, item) in encoded.iter().enumerate() {
+        if i > 0 {
+            body.push(b',');
+        }
+        body.extend_from_slice(item);
+    }
+    body.push(b']');
+    body
+}
+
+/// Join encoded items into newline-terminated lines
+fn ndjson_lines(encoded: Vec<Vec<u8>>) -> Vec<u8> {
+    let mut body = Vec::with_capacity(encoded.iter().map(|e| e.len() + 1).sum());
+    for item in encoded {
+        body.extend_from_slice(&item);
+        body.push(b'\n');
+    }
+    body
+}
+
+/// Serialize each event of a batch to JSON, logging and skipping failures
+///
+/// Fails only if no event at all could be serialized.
+fn encode_events(batch: &[EnrichedEvent]) -> Result<Vec<Vec<u8>>> {
+    let mut last_error = None;
+    let encoded = encode_each(batch, |event, e| {
+        tracing::warn!(
+            "Dropping analytics event {}: {}",
+            event.event.event_type(),
+            e
+        );
+        last_error = Some(e);
+    });
+
+    match last_error {
+        Some(e) if encoded.is_empty() => Err(AnalyticsError::Serialization(e)),
+        _ => Ok(encoded),
+    }
+}
+
+fn encode_each<T: Serialize>(
+    items: &[T],
+    mut on_error: impl FnMut(&T, serde_json::Error),
+) -> Vec<Vec<u8>> {
+    items
+        .iter()
+        .filter_map(|item| match serde_json::to_vec(item) {
+            Ok(encoded) => Some(encoded),
+            Err(e) => {
+                on_error(item, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+    use uuid::Uuid;
+
+    /// Stand-in for an event whose payload can't be serialized
+    enum Item {
+        Good(u32),
+        Bad,
+    }
+
+    impl Serialize for Item {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            match self {
+                Item::Good(value) => value.serialize(serializer),
+                Item::Bad => Err(serde::ser::Error::custom("unserializable context")),
+            }
+        }
+    }
+
+    fn batch() -> Vec<EnrichedEvent> {
+        (0..2)
+            .map(|_| {
+                EnrichedEvent::new(AnalyticsEvent::ApplicationError {
+                    service: "platform".to_string(),
+                    error_type: "panic".to_string(),
+                    error_message: "boom".to_string(),
+                    user_id: None,
+                    context: Some(serde_json::json!({ "request": Uuid::new_v4() })),
+                    context_ref: None,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_encode_each_skips_unserializable() {
+        let items = [Item::Good(1), Item::Bad, Item::Good(2)];
+        let mut errors = Vec::new();
+
+        let encoded = encode_each(&items, |_, e| errors.push(e.to_string()));
+
+        assert_eq!(encoded, [b"1".to_vec(), b"2".to_vec()]);
+        assert_eq!(errors, ["unserializable context"]);
+    }
+
+    #[test]
+    fn test_json_round_trips() {
+        let body = JsonSerializer.serialize(&batch()).unwrap();
+        let decoded: Vec<EnrichedEvent> = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].event.event_type(), "application_error");
+    }
+
+    #[test]
+    fn test_warehouse_envelope_round_trips() {
+        let mut batch = batch();
+        batch[0].tenant_id = Some(Uuid::new_v4());
+        let serializer = EnvelopeSerializer::warehouse();
+
+        let body = serializer.serialize(&batch).unwrap();
+        let json: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["event"]["type"], "application_error");
+        assert_eq!(json[0]["event"]["errorMessage"], "boom");
+        assert!(json[0]["event"]["context"]["request"].is_string());
+        assert_eq!(json[0]["meta"]["schemaVersion"], batch[0].schema_version);
+        assert_eq!(
+            json[0]["meta"]["tenantId"],
+            batch[0].tenant_id.unwrap().to_string()
+        );
+        assert!(json[0].get("timestamp").is_none());
+
+        let decoded = serializer.deserialize(&body).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&batch).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_default_envelope_matches_json_serializer() {
+        let batch = batch();
+        let flat = EnvelopeSerializer::default().serialize(&batch).unwrap();
+
+        assert_eq!(flat, JsonSerializer.serialize(&batch).unwrap());
+        let ndjson = EnvelopeSerializer::ndjson(Envelope::Flat, FieldCase::Snake);
+        assert_eq!(
+            ndjson.serialize(&batch).unwrap(),
+            NdjsonSerializer.serialize(&batch).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ndjson_one_event_per_line() {
+        let body = NdjsonSerializer.serialize(&batch()).unwrap();
+        let body = String::from_utf8(body).unwrap();
+
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for line in lines {
+            let event: EnrichedEvent = serde_json::from_str(line).unwrap();
+            assert_eq!(event.event.event_type(), "application_error");
+        }
+    }
+
+    #[test]
+    fn test_unix_millis_timestamps_round_trip() {
+        let mut batch = batch();
+        // Millisecond precision survives the integer encoding
+        let timestamp = DateTime::from_timestamp_millis(1_738_324_800_123).unwrap();
+        batch[0].timestamp = timestamp;
+
+        for serializer in [
+            EnvelopeSerializer::default(),
+            EnvelopeSerializer::warehouse(),
+            EnvelopeSerializer::ndjson(Envelope::Flat, FieldCase::Snake),
+        ] {
+            let serializer = serializer.with_timestamps(TimestampFormat::UnixMillis);
+            let body = serializer.serialize(&batch).unwrap();
+
+            let decoded = serializer.deserialize(&body).unwrap();
+            assert_eq!(decoded[0].timestamp, timestamp);
+            assert_eq!(decoded.len(), 2);
+        }
+
+        let body = EnvelopeSerializer::default()
+            .with_timestamps(TimestampFormat::UnixMillis)
+            .serialize(&batch)
+            .unwrap();
+        let json: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json[0]["timestamp"], 1_738_324_800_123_i64);
+    }
+}
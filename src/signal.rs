@@ -0,0 +1,52 @@
+use crate::client::AnalyticsClient;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Flush and shut down `client` when the process receives SIGTERM or Ctrl-C
+///
+/// Spawns a task that waits for the signal and then calls
+/// [`AnalyticsClient::shutdown`] with `timeout`, so the final batch is not
+/// lost when Kubernetes terminates the pod during a rollout. The returned
+/// handle completes once the flush is done; await it before exiting.
+///
+/// Listening for a signal replaces its default action, so the process no
+/// longer terminates on SIGTERM by itself: the application must exit (or run
+/// its own graceful shutdown) after the handle resolves.
+///
+/// Clones of a client share one background task, so installing the hook for
+/// one clone flushes and stops all of them. Independently created clients
+/// each need their own hook.
+pub fn install_shutdown_flush(client: AnalyticsClient, timeout: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        wait_for_signal().await;
+
+        tracing::info!("Shutdown signal received, flushing analytics events");
+        match client.shutdown(timeout).await {
+            Ok(report) => tracing::info!("Analytics shut down: {}", report),
+            Err(e) => tracing::warn!("Failed to flush analytics events on shutdown: {}", e),
+        }
+    })
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
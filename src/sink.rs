@@ -0,0 +1,133 @@
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+#[cfg(feature = "durable")]
+mod durable;
+mod fallback;
+mod http;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "attachments")]
+mod multipart;
+mod tee;
+#[cfg(feature = "ws")]
+mod websocket;
+
+#[cfg(feature = "durable")]
+pub use durable::DurableBuffer;
+#[cfg(feature = "durable")]
+pub(crate) use durable::DurableSink;
+pub(crate) use fallback::FallbackSink;
+pub(crate) use http::HttpSink;
+#[cfg(feature = "kafka")]
+pub use kafka::{KafkaKeyFn, KafkaSink};
+pub use tee::TeeSink;
+#[cfg(feature = "ws")]
+pub use websocket::{WebSocketOptions, WebSocketSink};
+
+/// Destination for batches of enriched events
+///
+/// The background worker batches tracked events and hands each batch to the
+/// client's sink. The default sink POSTs to the ingestion service; custom
+/// sinks can be installed with [`AnalyticsClient::with_sink`](crate::AnalyticsClient::with_sink).
+///
+/// Errors are logged by the worker; the batch is not retried.
+#[async_trait]
+pub trait EventSink: Send + Sync + 'static {
+    /// Deliver a batch of events
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()>;
+
+    /// Check that the destination is reachable
+    ///
+    /// Called by [`AnalyticsClient::health_check`](crate::AnalyticsClient::health_check).
+    /// The default implementation always succeeds.
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Deliver a single event and return the destination's reading of it
+    ///
+    /// Called by [`AnalyticsClient::debug_roundtrip`](crate::AnalyticsClient::debug_roundtrip).
+    /// The default implementation fails with `AnalyticsError::Config`, as
+    /// most destinations can't echo events back.
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        let _ = event;
+        Err(AnalyticsError::Config(
+            "sink does not support debug round trips".to_string(),
+        ))
+    }
+}
+
+#[async_trait]
+impl<S: EventSink + ?Sized> EventSink for Arc<S> {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        (**self).send(batch).await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        (**self).health_check().await
+    }
+
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        (**self).debug_roundtrip(event).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::error::AnalyticsError;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Sink collecting every batch it receives
+    #[derive(Clone, Default)]
+    pub(crate) struct CollectSink {
+        batches: Arc<Mutex<Vec<Vec<EnrichedEvent>>>>,
+    }
+
+    impl CollectSink {
+        pub(crate) fn batches(&self) -> Vec<Vec<EnrichedEvent>> {
+            self.batches.lock().unwrap().clone()
+        }
+
+        pub(crate) fn events(&self) -> Vec<EnrichedEvent> {
+            self.batches().into_iter().flatten().collect()
+        }
+    }
+
+    #[async_trait]
+    impl EventSink for CollectSink {
+        async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+            self.batches.lock().unwrap().push(batch.to_vec());
+            Ok(())
+        }
+    }
+
+    /// Sink that fails with a retryable HTTP 503 and reports unhealthy while
+    /// `down` is set, and forwards to a `CollectSink` otherwise
+    #[derive(Default)]
+    pub(crate) struct FlakySink {
+        pub(crate) down: AtomicBool,
+        pub(crate) delivered: CollectSink,
+    }
+
+    #[async_trait]
+    impl EventSink for FlakySink {
+        async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(AnalyticsError::Http { status: 503 });
+            }
+            self.delivered.send(batch).await
+        }
+
+        async fn health_check(&self) -> Result<()> {
+            match self.down.load(Ordering::SeqCst) {
+                true => Err(AnalyticsError::Http { status: 503 }),
+                false => Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use super::EventSink;
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use crate::state::SharedState;
+use crate::throttle::LogKind;
+use crate::worker::is_counted;
+use async_trait::async_trait;
+use memmap2::MmapMut;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Identifies a ring file and its layout version
+const MAGIC: [u8; 8] = *b"ADIRING1";
+
+/// Magic, read cursor and write cursor
+const HEADER_LEN: usize = 24;
+
+/// Every record is prefixed with its length as a little-endian u32
+const LEN_PREFIX: usize = 4;
+
+/// On-disk ring buffer that persists every event until it is delivered
+///
+/// Set as [`ClientConfig::durable_buffer`](crate::ClientConfig::durable_buffer).
+/// Each batch is appended to a memory-mapped file at `path` and synced to
+/// disk before it is handed to the sink; the read cursor only advances once
+/// the sink accepted the events. Events the sink failed to deliver with a
+/// retryable error stay in the ring and are retried with the next batch, and
+/// a restarted client resumes from the persisted cursor, so nothing is lost
+/// across crashes or outages as long as the ring doesn't fill up.
+///
+/// Events the sink rejects for good (a non-retryable error such as a 400 or
+/// 413 response) are logged, counted as dropped and skipped, so one bad
+/// record can't block delivery of everything behind it. Records whose
+/// length on disk is corrupt (e.g. a torn write) are logged and discarded
+/// with everything after them.
+///
+/// # Tradeoffs
+///
+/// * Throughput: every batch costs two `msync` calls (records, then cursor),
+///   so sending is bounded by the disk's sync latency rather than the network.
+///   Larger batches amortize this.
+/// * Capacity: `size_bytes` (including a 24 byte header) bounds how much can
+///   pile up during an outage. When a batch doesn't fit even after a
+///   delivery attempt, the events already persisted are kept and counted as
+///   sent, the rest are counted as dropped, and the batch fails with
+///   `AnalyticsError::DurableBufferFull`.
+/// * Duplicates: delivery is at-least-once. A crash between the sink
+///   accepting a batch and the cursor being persisted resends that batch
+///   (the HTTP sink's `Idempotency-Key` lets the service skip it).
+///
+/// The file is created on first use and must not be shared between
+/// processes. Reopening an existing ring with a different `size_bytes` is an
+/// error.
+#[derive(Debug, Clone)]
+pub struct DurableBuffer {
+    path: PathBuf,
+    size_bytes: u64,
+}
+
+impl DurableBuffer {
+    /// Ring stored at `path`, `size_bytes` large in total
+    pub fn new(path: impl Into<PathBuf>, size_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            size_bytes,
+        }
+    }
+
+    /// Path of the ring file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Total size of the ring file in bytes
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+}
+
+/// Memory-mapped ring of length-prefixed records
+///
+/// Cursors are logical byte offsets that only grow; the physical position is
+/// the offset modulo the data capacity.
+struct Ring {
+    map: MmapMut,
+    capacity: u64,
+    read: u64,
+    write: u64,
+}
+
+impl Ring {
+    fn open(path: &Path, size_bytes: u64) -> io::Result<Self> {
+        if size_bytes <= (HEADER_LEN + LEN_PREFIX) as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "durable buffer too small",
+            ));
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let existing = file.metadata()?.len();
+        if existing != 0 && existing != size_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "durable buffer is {} bytes, expected {}",
+                    existing, size_bytes
+                ),
+            ));
+        }
+        file.set_len(size_bytes)?;
+
+        // SAFETY: the file is owned by this ring; nothing else may modify or
+        // truncate it while mapped (documented on `DurableBuffer`).
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        let mut ring = Self {
+            map,
+            capacity: size_bytes - HEADER_LEN as u64,
+            read: 0,
+            write: 0,
+        };
+
+        if ring.map[..8] == MAGIC {
+            ring.read = ring.header_u64(8);
+            ring.write = ring.header_u64(16);
+            if ring.write < ring.read || ring.write - ring.read > ring.capacity {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "durable buffer cursors are corrupt",
+                ));
+            }
+        } else {
+            ring.map[..8].copy_from_slice(&MAGIC);
+            ring.persist_cursors()?;
+        }
+
+        Ok(ring)
+    }
+
+    fn header_u64(&self, at: usize) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.map[at..at + 8]);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.read == self.write
+    }
+
+    /// Append a record, `false` if there is no room for it
+    ///
+    /// Not durable until [`sync`](Self::sync).
+    fn push(&mut self, record: &[u8]) -> bool {
+        let Ok(len) = u32::try_from(record.len()) else {
+            return false;
+        };
+        let needed = (LEN_PREFIX + record.len()) as u64;
+        if needed > self.capacity - (self.write - self.read) {
+            return false;
+        }
+
+        self.copy_in(self.write, &len.to_le_bytes());
+        self.copy_in(self.write + LEN_PREFIX as u64, record);
+        self.write += needed;
+        true
+    }
+
+    /// Records between the read and write cursor, with the offset after each
+    ///
+    /// Stops at a record whose length prefix points past the written data,
+    /// which can only come from corruption; the rest of the ring is
+    /// unreadable then. Also returns the offset up to which the ring was
+    /// consumed: `write`, including any corrupt tail.
+    fn records(&self) -> (Vec<(Vec<u8>, u64)>, u64) {
+        let mut records = Vec::new();
+        let mut at = self.read;
+        while at < self.write {
+            let remaining = self.write - at;
+            let len = if remaining >= LEN_PREFIX as u64 {
+                let mut len = [0; LEN_PREFIX];
+                self.copy_out(at, &mut len);
+                Some(u64::from(u32::from_le_bytes(len)))
+            } else {
+                None
+            };
+            let Some(len) = len.filter(|len| LEN_PREFIX as u64 + len <= remaining) else {
+                tracing::error!(
+                    "Discarding {} bytes of corrupt durable analytics buffer at offset {}",
+                    remaining,
+                    at
+                );
+                break;
+            };
+
+            let mut record = vec![0; len as usize];
+            self.copy_out(at + LEN_PREFIX as u64, &mut record);
+            at += LEN_PREFIX as u64 + len;
+            records.push((record, at));
+        }
+        (records, self.write)
+    }
+
+    /// Persist appended records, then the cursor that makes them visible
+    fn sync(&mut self) -> io::Result<()> {
+        self.map.flush()?;
+        self.persist_cursors()
+    }
+
+    /// Mark everything before `offset` as delivered
+    fn advance(&mut self, offset: u64) -> io::Result<()> {
+        self.read = offset;
+        self.persist_cursors()
+    }
+
+    fn persist_cursors(&mut self) -> io::Result<()> {
+        self.map[8..16].copy_from_slice(&self.read.to_le_bytes());
+        self.map[16..24].copy_from_slice(&self.write.to_le_bytes());
+        self.map.flush_range(0, HEADER_LEN)
+    }
+
+    fn copy_in(&mut self, offset: u64, bytes: &[u8]) {
+        let start = (offset % self.capacity) as usize;
+        let first = bytes.len().min(self.capacity as usize - start);
+        self.map[HEADER_LEN + start..HEADER_LEN + start + first].copy_from_slice(&bytes[..first]);
+        self.map[HEADER_LEN..HEADER_LEN + bytes.len() - first].copy_from_slice(&bytes[first..]);
+    }
+
+    fn copy_out(&self, offset: u64, bytes: &mut [u8]) {
+        let start = (offset % self.capacity) as usize;
+        let first = bytes.len().min(self.capacity as usize - start);
+        let rest = bytes.len() - first;
+        bytes[..first].copy_from_slice(&self.map[HEADER_LEN + start..HEADER_LEN + start + first]);
+        bytes[first..].copy_from_slice(&self.map[HEADER_LEN..HEADER_LEN + rest]);
+    }
+}
+
+/// Sink persisting every batch to a [`DurableBuffer`] before delivering it
+/// through the wrapped sink
+pub(crate) struct DurableSink {
+    inner: Arc<dyn EventSink>,
+    ring: Mutex<Ring>,
+    batch_size: usize,
+    shared: Arc<SharedState>,
+}
+
+impl DurableSink {
+    /// Open (or create) the ring; buffered events are delivered by the next `send`
+    pub(crate) fn open(
+        buffer: &DurableBuffer,
+        inner: Arc<dyn EventSink>,
+        batch_size: usize,
+        shared: Arc<SharedState>,
+    ) -> Result<Self> {
+        let ring = Ring::open(&buffer.path, buffer.size_bytes)?;
+        if !ring.is_empty() {
+            tracing::info!(
+                "Resuming {} bytes of buffered analytics events from {}",
+                ring.write - ring.read,
+                buffer.path.display()
+            );
+        }
+
+        Ok(Self {
+            inner,
+            ring: Mutex::new(ring),
+            batch_size: batch_size.max(1),
+            shared,
+        })
+    }
+
+    /// Hand buffered events to the inner sink, advancing after each chunk
+    ///
+    /// Stops at the first retryable failure, leaving the rest for the next
+    /// attempt; chunks rejected for good are dropped.
+    async fn deliver(&self, ring: &mut Ring) -> Result<()> {
+        let (records, end) = ring.records();
+
+        for chunk in records.chunks(self.batch_size) {
+            let events: Vec<EnrichedEvent> = chunk
+                .iter()
+                .filter_map(|(record, _)| match serde_json::from_slice(record) {
+                    Ok(event) => Some(event),
+                    Err(e) => {
+                        tracing::warn!("Skipping unreadable buffered analytics event: {}", e);
+                        None
+                    }
+                })
+                .collect();
+
+            if !events.is_empty() {
+                if let Err(e) = self.inner.send(&events).await {
+                    if e.is_retryable() {
+                        return Err(e);
+                    }
+                    self.shared.log.log(
+                        LogKind::Drop,
+                        format!(
+                            "Dropping {} buffered analytics events rejected by the sink: {}",
+                            events.len(),
+                            e
+                        ),
+                    );
+                    for _ in &events {
+                        self.shared.counters.record_dropped();
+                    }
+                }
+            }
+            if let Some((_, end)) = chunk.last() {
+                ring.advance(*end)?;
+            }
+        }
+
+        // Skip a corrupt tail
+        if ring.read < end {
+            ring.advance(end)?;
+        }
+        Ok(())
+    }
+
+    /// Count a batch that only partly fit into the ring: `persisted` as
+    /// sent, as it will be delivered later, and `rest` as dropped
+    fn overflow(
+        &self,
+        persisted: &[EnrichedEvent],
+        rest: &[EnrichedEvent],
+        cause: Option<AnalyticsError>,
+    ) {
+        let counters = &self.shared.counters;
+        counters.record_sent(persisted.iter().filter(|e| is_counted(e)).count());
+        for _ in rest.iter().filter(|e| is_counted(e)) {
+            counters.record_dropped();
+        }
+        let cause = match cause {
+            Some(e) => format!(" (delivery failed: {})", e),
+            None => String::new(),
+        };
+        self.shared.log.log(
+            LogKind::Drop,
+            format!(
+                "Durable analytics buffer full, dropping {} events{}",
+                rest.len(),
+                cause
+            ),
+        );
+    }
+}
+
+#[async_trait]
+impl EventSink for DurableSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        let mut ring = self.ring.lock().await;
+
+        for (persisted, event) in batch.iter().enumerate() {
+            let record = match serde_json::to_vec(event) {
+                Ok(record) => record,
+                Err(e) => {
+                    tracing::warn!(
+                        "Dropping analytics event {}: {}",
+                        event.event.event_type(),
+                        AnalyticsError::Serialization(e)
+                    );
+                    continue;
+                }
+            };
+
+            if !ring.push(&record) {
+                // Make room by delivering what is already buffered
+                ring.sync()?;
+                let delivered = self.deliver(&mut ring).await;
+                if delivered.is_err() || !ring.push(&record) {
+                    ring.sync()?;
+                    self.overflow(&batch[..persisted], &batch[persisted..], delivered.err());
+                    return Err(AnalyticsError::DurableBufferFull);
+                }
+            }
+        }
+
+        ring.sync()?;
+        self.deliver(&mut ring).await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        self.inner.debug_roundtrip(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClientConfig;
+    use crate::events::AnalyticsEvent;
+    use crate::sink::testing::FlakySink;
+    use std::sync::atomic::Ordering;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("analytics-ring-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn shared() -> Arc<SharedState> {
+        Arc::new(SharedState::new(&ClientConfig::default()))
+    }
+
+    fn event() -> EnrichedEvent {
+        EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: uuid::Uuid::new_v4(),
+            user_id: uuid::Uuid::new_v4(),
+        })
+    }
+
+    #[test]
+    fn test_ring_wraps_and_resumes_after_reopen() {
+        let path = temp_path();
+        let size = (HEADER_LEN + 40) as u64;
+
+        {
+            let mut ring = Ring::open(&path, size).unwrap();
+            assert!(ring.push(b"0123456789abcdef"));
+            ring.sync().unwrap();
+            let (_, end) = ring.records().0[0].clone();
+            ring.advance(end).unwrap();
+
+            // Crosses the end of the data region
+            assert!(ring.push(b"wrapped-record-01"));
+            assert!(!ring.push(b"does not fit anymore"));
+            ring.sync().unwrap();
+        }
+
+        let ring = Ring::open(&path, size).unwrap();
+        let records: Vec<_> = ring.records().0.into_iter().map(|(r, _)| r).collect();
+        assert_eq!(records, vec![b"wrapped-record-01".to_vec()]);
+
+        assert!(Ring::open(&path, size + 1).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batches_are_retried() {
+        let path = temp_path();
+        let inner = Arc::new(FlakySink::default());
+        let sink = DurableSink::open(
+            &DurableBuffer::new(&path, 64 * 1024),
+            inner.clone(),
+            100,
+            shared(),
+        )
+        .unwrap();
+
+        inner.down.store(true, Ordering::SeqCst);
+        assert!(sink.send(&[event(), event()]).await.is_err());
+        assert!(inner.delivered.events().is_empty());
+
+        inner.down.store(false, Ordering::SeqCst);
+        sink.send(&[event()]).await.unwrap();
+        assert_eq!(inner.delivered.events().len(), 3);
+
+        // Nothing left to resend after a restart
+        drop(sink);
+        let sink = DurableSink::open(
+            &DurableBuffer::new(&path, 64 * 1024),
+            inner.clone(),
+            100,
+            shared(),
+        )
+        .unwrap();
+        sink.send(&[]).await.unwrap();
+        assert_eq!(inner.delivered.events().len(), 3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_events_survive_restart() {
+        let path = temp_path();
+        let buffer = DurableBuffer::new(&path, 64 * 1024);
+        let inner = Arc::new(FlakySink::default());
+        inner.down.store(true, Ordering::SeqCst);
+
+        let sink = DurableSink::open(&buffer, inner.clone(), 100, shared()).unwrap();
+        let tracked = event();
+        assert!(sink.send(std::slice::from_ref(&tracked)).await.is_err());
+        drop(sink);
+
+        inner.down.store(false, Ordering::SeqCst);
+        let sink = DurableSink::open(&buffer, inner.clone(), 100, shared()).unwrap();
+        sink.send(&[]).await.unwrap();
+
+        let delivered = inner.delivered.events();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].sequence, tracked.sequence);
+        assert_eq!(delivered[0].timestamp, tracked.timestamp);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_corrupt_length_is_discarded() {
+        let path = temp_path();
+        let mut ring = Ring::open(&path, (HEADER_LEN + 64) as u64).unwrap();
+        assert!(ring.push(b"good"));
+        assert!(ring.push(b"torn"));
+        ring.sync().unwrap();
+
+        // Length prefix of the second record, as after a torn write
+        let second = HEADER_LEN + LEN_PREFIX + 4;
+        ring.map[second..second + LEN_PREFIX].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let (records, end) = ring.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, b"good");
+        assert_eq!(end, ring.write);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_overflow_while_down_counts_dropped_events() {
+        let path = temp_path();
+        let shared = shared();
+        let inner = Arc::new(FlakySink::default());
+        inner.down.store(true, Ordering::SeqCst);
+        // Room for a few events only
+        let sink = DurableSink::open(
+            &DurableBuffer::new(&path, 1024),
+            inner.clone(),
+            100,
+            shared.clone(),
+        )
+        .unwrap();
+
+        let batch: Vec<_> = (0..20).map(|_| event()).collect();
+        assert!(matches!(
+            sink.send(&batch).await,
+            Err(AnalyticsError::DurableBufferFull)
+        ));
+        let persisted = sink.ring.lock().await.records().0.len();
+        assert!(persisted > 0 && persisted < 20, "{} persisted", persisted);
+        let counts = shared.counters.take();
+        assert_eq!(counts.sent, persisted as u64);
+        assert_eq!(counts.dropped, (20 - persisted) as u64);
+
+        // The persisted events are delivered once the sink is back
+        inner.down.store(false, Ordering::SeqCst);
+        sink.send(&[]).await.unwrap();
+        let sequences =
+            |events: &[EnrichedEvent]| events.iter().map(|e| e.sequence).collect::<Vec<_>>();
+        assert_eq!(
+            sequences(&inner.delivered.events()),
+            sequences(&batch[..persisted])
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Sink rejecting every batch like a server answering 400
+    struct RejectingSink;
+
+    #[async_trait]
+    impl EventSink for RejectingSink {
+        async fn send(&self, _batch: &[EnrichedEvent]) -> Result<()> {
+            Err(AnalyticsError::Http { status: 400 })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_events_are_skipped() {
+        let path = temp_path();
+        let shared = shared();
+        // Room for a few events only, so a stuck record would fill the ring
+        let sink = DurableSink::open(
+            &DurableBuffer::new(&path, 2048),
+            Arc::new(RejectingSink),
+            1,
+            shared.clone(),
+        )
+        .unwrap();
+
+        for _ in 0..50 {
+            sink.send(&[event()]).await.unwrap();
+        }
+        assert!(sink.ring.lock().await.is_empty());
+        assert_eq!(shared.counters.take().dropped, 50);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,164 @@
+use super::EventSink;
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Time between reachability probes while writing to stdout
+pub(crate) const FALLBACK_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sink writing batches to stdout once the inner sink keeps failing
+///
+/// After `threshold` consecutive failed batches, batches are printed as one
+/// JSON line per event instead of being sent, and a one-time notice is
+/// logged. While in that mode the inner sink's `health_check` is probed at
+/// most every [`FALLBACK_PROBE_INTERVAL`], on the next batch; once it
+/// succeeds, sending resumes.
+///
+/// There is no separate circuit breaker in the client: the count of
+/// consecutive failed batches is the breaker. Any failed send counts,
+/// retryable or not, and any successful send resets the count to zero.
+/// Once tripped, only a successful probe resets it. The count is updated
+/// after the inner sink returned, so the batch whose failure reaches the
+/// threshold is still returned as an error (and counted as failed); only
+/// the batches after it are printed.
+pub(crate) struct FallbackSink {
+    inner: Arc<dyn EventSink>,
+    threshold: u32,
+    state: Mutex<FallbackState>,
+}
+
+struct FallbackState {
+    consecutive_failures: u32,
+    /// Next time to probe the inner sink, `None` while sending normally
+    next_probe: Option<Instant>,
+}
+
+impl FallbackSink {
+    pub(crate) fn new(inner: Arc<dyn EventSink>, threshold: u32) -> Self {
+        Self {
+            inner,
+            threshold: threshold.max(1),
+            state: Mutex::new(FallbackState {
+                consecutive_failures: 0,
+                next_probe: None,
+            }),
+        }
+    }
+
+    /// Whether batches currently go to stdout, probing the inner sink if due
+    async fn in_fallback(&self) -> bool {
+        let probe_due = match self.state.lock().unwrap().next_probe {
+            None => return false,
+            Some(next_probe) => Instant::now() >= next_probe,
+        };
+        if !probe_due {
+            return true;
+        }
+
+        let reachable = self.inner.health_check().await.is_ok();
+        let mut state = self.state.lock().unwrap();
+        if reachable {
+            tracing::info!("Analytics endpoint reachable again, resuming delivery");
+            state.consecutive_failures = 0;
+            state.next_probe = None;
+        } else {
+            state.next_probe = Some(Instant::now() + FALLBACK_PROBE_INTERVAL);
+        }
+        !reachable
+    }
+
+    fn record(&self, result: &Result<()>) {
+        let mut state = self.state.lock().unwrap();
+        if result.is_ok() {
+            state.consecutive_failures = 0;
+            return;
+        }
+
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold {
+            tracing::warn!(
+                "Analytics endpoint failed {} batches in a row, writing events to stdout until it is reachable again",
+                state.consecutive_failures
+            );
+            state.next_probe = Some(Instant::now() + FALLBACK_PROBE_INTERVAL);
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for FallbackSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        if self.in_fallback().await {
+            for event in batch {
+                println!("{}", serde_json::to_string(event)?);
+            }
+            return Ok(());
+        }
+
+        let result = self.inner.send(batch).await;
+        self.record(&result);
+        result
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        self.inner.debug_roundtrip(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+    use crate::sink::testing::FlakySink;
+    use std::sync::atomic::Ordering;
+
+    fn batch() -> Vec<EnrichedEvent> {
+        vec![EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: uuid::Uuid::new_v4(),
+            user_id: uuid::Uuid::new_v4(),
+        })]
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_falls_back_and_recovers() {
+        let inner = Arc::new(FlakySink::default());
+        let sink = FallbackSink::new(inner.clone(), 2);
+        inner.down.store(true, Ordering::SeqCst);
+
+        assert!(sink.send(&batch()).await.is_err());
+        assert!(sink.send(&batch()).await.is_err());
+        // Threshold reached: written to stdout instead
+        sink.send(&batch()).await.unwrap();
+
+        // Reachable again, but not probed before the interval has passed
+        inner.down.store(false, Ordering::SeqCst);
+        sink.send(&batch()).await.unwrap();
+        assert!(inner.delivered.events().is_empty());
+
+        tokio::time::advance(FALLBACK_PROBE_INTERVAL).await;
+        sink.send(&batch()).await.unwrap();
+        assert_eq!(inner.delivered.events().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failure_count() {
+        let inner = Arc::new(FlakySink::default());
+        let sink = FallbackSink::new(inner.clone(), 2);
+
+        inner.down.store(true, Ordering::SeqCst);
+        assert!(sink.send(&batch()).await.is_err());
+        inner.down.store(false, Ordering::SeqCst);
+        sink.send(&batch()).await.unwrap();
+        inner.down.store(true, Ordering::SeqCst);
+
+        // Only one failure in a row, so this is still sent and fails
+        assert!(sink.send(&batch()).await.is_err());
+    }
+}
@@ -0,0 +1,719 @@
+use super::EventSink;
+use crate::clock;
+use crate::config::ClientConfig;
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use crate::state::SharedState;
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Upper bound for a pause requested with `Retry-After`
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Sink that POSTs batches to the analytics ingestion service
+///
+/// A `429` or `503` response with a `Retry-After` header pauses all sends
+/// for the requested time (at most [`MAX_RETRY_AFTER`]). The worker waits
+/// for the pause to end before sending the next batch; events tracked
+/// meanwhile keep queueing, subject to `queue_capacity`.
+///
+/// The base URL lives in the shared state, so `AnalyticsClient::set_url`
+/// takes effect with the next request.
+///
+/// Every batch request carries its number in the [`BATCH_SEQ_HEADER`]
+/// header; see [`EnrichedEvent::sequence`] for how the ingestion service
+/// can use it to detect loss.
+pub(crate) struct HttpSink {
+    client: reqwest::Client,
+    config: Arc<ClientConfig>,
+    shared: Arc<SharedState>,
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl HttpSink {
+    pub(crate) fn new(
+        analytics_url: String,
+        config: Arc<ClientConfig>,
+        shared: Arc<SharedState>,
+    ) -> Self {
+        let builder = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host);
+
+        let builder = match &config.proxy {
+            Some(proxy) => builder.proxy(proxy.clone()),
+            None => builder,
+        };
+
+        #[cfg(all(unix, feature = "uds"))]
+        let builder = match &config.uds_path {
+            Some(path) => builder.unix_socket(path.clone()),
+            None => builder,
+        };
+
+        #[cfg(feature = "http2")]
+        let builder = if config.http2_prior_knowledge {
+            builder.http2_prior_knowledge()
+        } else {
+            builder
+        };
+
+        // Only fails if the TLS backend can't be initialized, same as reqwest::Client::new
+        let client = builder
+            .build()
+            .expect("Failed to build analytics HTTP client");
+
+        shared.analytics_url.store(Some(Arc::new(analytics_url)));
+
+        Self {
+            client,
+            config,
+            shared,
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// POST one batch, naming `tenant_id` in the tenant header if given
+    async fn post(&self, batch: &[EnrichedEvent], tenant_id: Option<Uuid>) -> Result<()> {
+        let serializer = &self.config.serializer;
+        let body = serializer.serialize(batch)?;
+        let url = self.url(&self.config.batch_path);
+
+        let request = self
+            .client
+            .post(&url)
+            .header(IDEMPOTENCY_KEY, idempotency_key(&body));
+
+        #[cfg(feature = "attachments")]
+        let request = if super::multipart::has_attachments(batch) {
+            let request = self.sign(request, &body);
+            request.multipart(super::multipart::form(
+                batch,
+                body,
+                serializer.content_type(),
+            )?)
+        } else {
+            self.body(request, body)?
+        };
+        #[cfg(not(feature = "attachments"))]
+        let request = self.body(request, body)?;
+
+        let mut request = request;
+        if let Some(token) = &self.config.auth_token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(tenant_id) = tenant_id {
+            request = request.header(TENANT_HEADER, tenant_id.to_string());
+        }
+        // Numbered last so only requests that actually go out use up a number
+        let request = request.header(BATCH_SEQ_HEADER, self.shared.next_batch_seq());
+
+        let response = request.send().await?;
+
+        let server_time = response
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(clock::parse_http_date);
+        if let Some(server_time) = server_time {
+            self.shared.clock_skew.record(
+                server_time,
+                Utc::now(),
+                self.config.clock_skew_threshold,
+                &self.shared.log,
+            );
+        }
+
+        let status = response.status();
+        if matches!(status.as_u16(), 429 | 503) {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, Utc::now()));
+            if let Some(retry_after) = retry_after {
+                self.pause(retry_after);
+            }
+        }
+
+        check_status(status)
+    }
+
+    /// `path` on the current base URL
+    fn url(&self, path: &str) -> String {
+        let base = self.shared.analytics_url.load();
+        join_url(base.as_deref().map_or("", String::as_str), path)
+    }
+
+    /// Hold back further sends for `duration`, as asked by the server
+    fn pause(&self, duration: Duration) {
+        let duration = duration.min(MAX_RETRY_AFTER);
+        tracing::warn!(
+            "Analytics ingestion service asked to retry after {:?}, pausing sends",
+            duration
+        );
+        *self.paused_until.lock().unwrap() = Some(Instant::now() + duration);
+    }
+
+    /// Wait until a pause requested with `Retry-After` is over
+    async fn wait_if_paused(&self) {
+        let paused_until = *self.paused_until.lock().unwrap();
+        if let Some(deadline) = paused_until {
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// Set the serialized batch as the body, compressed as configured
+    /// unless it is below `compress_min_bytes`
+    fn body(
+        &self,
+        request: reqwest::RequestBuilder,
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder> {
+        let compression = self.config.compression_for(body.len());
+        let request = request.header(
+            reqwest::header::CONTENT_TYPE,
+            self.config.serializer.content_type(),
+        );
+        let request = match compression.content_encoding() {
+            Some(encoding) => request.header(reqwest::header::CONTENT_ENCODING, encoding),
+            None => request,
+        };
+        let body = compression.compress(body)?;
+        Ok(self.sign(request, &body).body(body))
+    }
+
+    /// Add the `X-Signature` header for `body` if a signing secret is set
+    fn sign(&self, request: reqwest::RequestBuilder, body: &[u8]) -> reqwest::RequestBuilder {
+        match &self.config.signing_secret {
+            Some(secret) => request.header(SIGNATURE_HEADER, signature(secret, body)),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for HttpSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        if !self.config.tenant_header {
+            self.wait_if_paused().await;
+            return self.post(batch, None).await;
+        }
+        // A pause requested by a concurrent send holds back the next tenants too
+        for (tenant_id, events) in split_by_tenant(batch) {
+            self.wait_if_paused().await;
+            self.post(&events, tenant_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        let url = self.url("/health");
+
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.config.auth_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        check_status(response.status())
+    }
+
+    /// POST the event as a batch of one to `/events/debug` and parse the
+    /// echoed event from the response
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        let url = self.url(DEBUG_PATH);
+        let body = self
+            .config
+            .serializer
+            .serialize(std::slice::from_ref(event))?;
+
+        let request = self.client.post(&url).header(
+            reqwest::header::CONTENT_TYPE,
+            self.config.serializer.content_type(),
+        );
+        let mut request = self.sign(request, &body).body(body);
+        if let Some(token) = &self.config.auth_token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        check_status(response.status())?;
+        Ok(response.json().await?)
+    }
+}
+
+/// Path of the echo endpoint used by `debug_roundtrip`
+const DEBUG_PATH: &str = "/events/debug";
+
+/// `AnalyticsError::Http` for anything but a 2xx response
+fn check_status(status: reqwest::StatusCode) -> Result<()> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(AnalyticsError::Http {
+            status: status.as_u16(),
+        })
+    }
+}
+
+/// Parse a `Retry-After` header value, either delay seconds or an HTTP date
+///
+/// Dates in the past mean no pause.
+fn parse_retry_after(value: &str, now: chrono::DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = clock::parse_http_date(value)?;
+    (date - now).to_std().ok()
+}
+
+/// Header naming the tenant of every event in a request (`tenant_header`)
+const TENANT_HEADER: &str = "X-Tenant-Id";
+
+/// Header numbering batch requests per client, starting at 0
+const BATCH_SEQ_HEADER: &str = "X-Batch-Seq";
+
+/// Group events by tenant, in order of each tenant's first event
+fn split_by_tenant(batch: &[EnrichedEvent]) -> Vec<(Option<Uuid>, Vec<EnrichedEvent>)> {
+    let mut groups: Vec<(Option<Uuid>, Vec<EnrichedEvent>)> = Vec::new();
+    for event in batch {
+        match groups
+            .iter_mut()
+            .find(|(tenant, _)| *tenant == event.tenant_id)
+        {
+            Some((_, events)) => events.push(event.clone()),
+            None => groups.push((event.tenant_id, vec![event.clone()])),
+        }
+    }
+    groups
+}
+
+/// Header carrying the batch content hash
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Hex SHA-256 of the serialized batch, before any compression
+///
+/// Resending the same body yields the same key, so the ingestion service can
+/// recognize and skip a batch it already stored.
+fn idempotency_key(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Header carrying the HMAC of the request body (`signing_secret`)
+const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// `sha256=<hex>` HMAC-SHA256 of `body` keyed with `secret`
+fn signature(secret: &[u8], body: &[u8]) -> String {
+    // HMAC takes keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Append `path` to `base`, keeping any path prefix on `base`
+///
+/// Exactly one slash ends up between the two, whether or not `base` ends
+/// with one or `path` starts with one.
+fn join_url(base: &str, path: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let path = path.trim_start_matches('/');
+
+    if path.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_key_is_stable() {
+        use crate::events::AnalyticsEvent;
+        use crate::serializer::{BatchSerializer, JsonSerializer};
+
+        let batch = vec![EnrichedEvent::new(AnalyticsEvent::AuthSessionValidated {
+            user_id: uuid::Uuid::new_v4(),
+            valid: true,
+        })];
+        let body = JsonSerializer.serialize(&batch).unwrap();
+
+        let key = idempotency_key(&body);
+        assert_eq!(
+            key,
+            idempotency_key(&JsonSerializer.serialize(&batch).unwrap())
+        );
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, idempotency_key(b"[]"));
+    }
+
+    #[test]
+    fn test_signature_known_vector() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_signature_covers_sent_body() {
+        let config = Arc::new(ClientConfig {
+            signing_secret: Some(b"secret".to_vec()),
+            ..Default::default()
+        });
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = HttpSink::new("http://localhost:8094".into(), config, shared);
+
+        let request = sink
+            .body(sink.client.post("http://localhost:8094"), b"[]".to_vec())
+            .unwrap()
+            .build()
+            .unwrap();
+        let sent = request.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(
+            request.headers()[SIGNATURE_HEADER],
+            signature(b"secret", sent).as_str()
+        );
+
+        // Unsigned unless configured
+        let sink = HttpSink::new(
+            "http://localhost:8094".into(),
+            Arc::new(ClientConfig::default()),
+            Arc::new(SharedState::new(&ClientConfig::default())),
+        );
+        let request = sink
+            .body(sink.client.post("http://localhost:8094"), b"[]".to_vec())
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(request.headers().get(SIGNATURE_HEADER).is_none());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_signature_covers_compressed_body() {
+        let config = Arc::new(ClientConfig {
+            signing_secret: Some(b"secret".to_vec()),
+            compression: crate::compression::Compression::Zstd { level: 1 },
+            compress_min_bytes: 0,
+            ..Default::default()
+        });
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = HttpSink::new("http://localhost:8094".into(), config, shared);
+
+        let body = b"[]".repeat(64);
+        let request = sink
+            .body(sink.client.post("http://localhost:8094"), body.clone())
+            .unwrap()
+            .build()
+            .unwrap();
+        let sent = request.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_ne!(sent, body.as_slice());
+        assert_eq!(
+            request.headers()[SIGNATURE_HEADER],
+            signature(b"secret", sent).as_str()
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compress_min_bytes() {
+        let config = Arc::new(ClientConfig {
+            compression: crate::compression::Compression::Zstd { level: 1 },
+            compress_min_bytes: 1024,
+            ..Default::default()
+        });
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = HttpSink::new("http://localhost:8094".into(), config, shared);
+        let build = |body: Vec<u8>| {
+            sink.body(sink.client.post("http://localhost:8094"), body)
+                .unwrap()
+                .build()
+                .unwrap()
+        };
+
+        let small = b"[]".to_vec();
+        let request = build(small.clone());
+        assert!(request
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .is_none());
+        assert_eq!(
+            request.body().and_then(|body| body.as_bytes()),
+            Some(&small[..])
+        );
+
+        let large = b"[]".repeat(512);
+        let request = build(large.clone());
+        assert_eq!(request.headers()[reqwest::header::CONTENT_ENCODING], "zstd");
+        let sent = request.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(zstd::decode_all(sent).unwrap(), large);
+    }
+
+    #[test]
+    fn test_split_by_tenant() {
+        use crate::events::AnalyticsEvent;
+
+        let event = |tenant_id| {
+            let mut event = EnrichedEvent::new(AnalyticsEvent::BalanceCreated {
+                user_id: Uuid::new_v4(),
+                balance_id: Uuid::new_v4(),
+            });
+            event.tenant_id = tenant_id;
+            event
+        };
+        let (a, b) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+
+        let groups = split_by_tenant(&[event(a), event(None), event(b), event(a)]);
+        let shape: Vec<_> = groups.iter().map(|(t, e)| (*t, e.len())).collect();
+        assert_eq!(shape, vec![(a, 2), (None, 1), (b, 1)]);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        use chrono::TimeZone;
+
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            None
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_delays_next_send() {
+        let config = Arc::new(ClientConfig::default());
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = HttpSink::new("http://localhost:8094".into(), config, shared);
+
+        sink.pause(Duration::from_secs(30));
+        let started = Instant::now();
+        sink.wait_if_paused().await;
+        assert_eq!(started.elapsed(), Duration::from_secs(30));
+
+        // Over-long requests are capped
+        sink.pause(Duration::from_secs(24 * 60 * 60));
+        let started = Instant::now();
+        sink.wait_if_paused().await;
+        assert_eq!(started.elapsed(), MAX_RETRY_AFTER);
+    }
+
+    #[tokio::test]
+    async fn test_pause_delays_next_tenant() {
+        use crate::events::AnalyticsEvent;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::sync::oneshot;
+
+        const PAUSE: Duration = Duration::from_millis(200);
+
+        // Hold the first answer until told to, report when the second request arrived
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (received, on_received) = oneshot::channel();
+        let (answer, on_answer) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let mut signals = Some((received, on_answer));
+            let mut arrived = Instant::now();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !request.ends_with(b"]") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                arrived = Instant::now();
+                if let Some((received, on_answer)) = signals.take() {
+                    received.send(()).unwrap();
+                    on_answer.await.unwrap();
+                }
+                let response = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            arrived
+        });
+
+        let config = Arc::new(ClientConfig {
+            tenant_header: true,
+            ..ClientConfig::default()
+        });
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = Arc::new(HttpSink::new(format!("http://{}", addr), config, shared));
+        let batch: Vec<_> = (0..2)
+            .map(|_| {
+                let mut event = EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+                    project_id: Uuid::new_v4(),
+                    user_id: Uuid::new_v4(),
+                });
+                event.tenant_id = Some(Uuid::new_v4());
+                event
+            })
+            .collect();
+
+        let sending = tokio::spawn({
+            let sink = sink.clone();
+            async move { sink.send(&batch).await }
+        });
+        // Another send is asked to back off while the first tenant's POST is in flight
+        on_received.await.unwrap();
+        sink.pause(PAUSE);
+        let paused_at = Instant::now();
+        answer.send(()).unwrap();
+
+        sending.await.unwrap().unwrap();
+        assert!(server.await.unwrap() >= paused_at + PAUSE);
+    }
+
+    #[cfg(all(unix, feature = "uds"))]
+    #[tokio::test]
+    async fn test_uds_survives_collector_restart() {
+        use crate::events::AnalyticsEvent;
+        use crate::AnalyticsClient;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        /// Answer one request on the socket with `204 No Content`
+        async fn answer_one(listener: &UnixListener) {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !request.ends_with(b"]") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            assert!(request.starts_with(b"POST /events/batch HTTP/1.1"));
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        }
+
+        let path = std::env::temp_dir().join(format!("analytics-{}.sock", Uuid::new_v4()));
+        let client = AnalyticsClient::builder()
+            .url("http://localhost")
+            .uds_path(&path)
+            .build()
+            .unwrap();
+        let event = || AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        };
+
+        for _ in 0..2 {
+            let listener = UnixListener::bind(&path).unwrap();
+            client.track(event());
+            let (sent, ()) = tokio::join!(client.flush(), answer_one(&listener));
+            sent.unwrap();
+
+            // The collector goes away and comes back on the same path
+            drop(listener);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_seq_counts_sent_requests() {
+        use crate::events::AnalyticsEvent;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answer requests with 200, 500, 200 and report their batch numbers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut seqs = Vec::new();
+            for status in ["200 OK", "500 Internal Server Error", "200 OK"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                while !request.ends_with(b"]") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8(request).unwrap().to_ascii_lowercase();
+                let seq = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("x-batch-seq: "))
+                    .unwrap()
+                    .trim()
+                    .to_string();
+                seqs.push(seq);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            seqs
+        });
+
+        let config = Arc::new(ClientConfig::default());
+        let shared = Arc::new(SharedState::new(&config));
+        let sink = HttpSink::new(format!("http://{}", addr), config, shared);
+        let batch = [EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        })];
+
+        // Empty batches are not sent and take no number
+        sink.send(&[]).await.unwrap();
+        sink.send(&batch).await.unwrap();
+        assert!(sink.send(&batch).await.is_err());
+        sink.send(&batch).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), ["0", "1", "2"]);
+    }
+
+    #[test]
+    fn test_join_url() {
+        assert_eq!(
+            join_url("http://localhost:8094", "/events/batch"),
+            "http://localhost:8094/events/batch"
+        );
+        assert_eq!(
+            join_url("http://localhost:8094/", "/events/batch"),
+            "http://localhost:8094/events/batch"
+        );
+        assert_eq!(
+            join_url("http://localhost:8094", "events/batch"),
+            "http://localhost:8094/events/batch"
+        );
+    }
+
+    #[test]
+    fn test_join_url_with_prefix() {
+        assert_eq!(
+            join_url("https://gateway/api/v2/analytics", "/events/batch"),
+            "https://gateway/api/v2/analytics/events/batch"
+        );
+        assert_eq!(
+            join_url("https://gateway/api/v2/analytics/", "/ingest"),
+            "https://gateway/api/v2/analytics/ingest"
+        );
+        assert_eq!(join_url("https://gateway/api/", ""), "https://gateway/api");
+    }
+}
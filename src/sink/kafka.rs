@@ -0,0 +1,160 @@
+use super::EventSink;
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::ClientConfig;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long `health_check` waits for the topic's metadata
+const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Message key derived from an event, `None` to leave it unkeyed
+pub type KafkaKeyFn = Arc<dyn Fn(&EnrichedEvent) -> Option<String> + Send + Sync>;
+
+/// Sink producing events straight to a Kafka topic
+///
+/// Every event becomes one record whose value is the JSON-serialized
+/// [`EnrichedEvent`] (the same object that appears in the HTTP batch array).
+/// Records are keyed by the event's `user_id` by default, so one user's
+/// events land on the same partition in order; unkeyed records are spread
+/// by the producer's partitioner. Use [`key_by`](Self::key_by) to change it.
+///
+/// Delivery is at-least-once: `send` waits until every record of the batch
+/// is acknowledged and fails with the first producer error otherwise. The
+/// batch is then retried as a whole where the client retries (e.g. from the
+/// durable buffer), so records that did make it may be delivered twice.
+/// Consumers should deduplicate on the event's `hostname` and `sequence`.
+///
+/// [`KafkaSink::new`] only needs the brokers and enables `acks=all` with
+/// idempotent production. The topic must exist unless the cluster allows
+/// auto-creation. For TLS, SASL or tuning, pass a prepared producer to
+/// [`KafkaSink::with_producer`].
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    key: KafkaKeyFn,
+}
+
+impl KafkaSink {
+    /// Producer for `brokers` (comma-separated `host:port` list) and `topic`
+    pub fn new(brokers: &str, topic: impl Into<String>) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("acks", "all")
+            .set("enable.idempotence", "true")
+            .create()?;
+        Ok(Self::with_producer(producer, topic))
+    }
+
+    /// Produce to `topic` through an already configured producer
+    pub fn with_producer(producer: FutureProducer, topic: impl Into<String>) -> Self {
+        Self {
+            producer,
+            topic: topic.into(),
+            key: Arc::new(user_key),
+        }
+    }
+
+    /// Derive record keys with `key` instead of from `user_id`
+    pub fn key_by(
+        mut self,
+        key: impl Fn(&EnrichedEvent) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.key = Arc::new(key);
+        self
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        // An unserializable event is dropped alone, like with the other sinks
+        let records: Vec<_> = batch
+            .iter()
+            .filter_map(|event| match serde_json::to_vec(event) {
+                Ok(payload) => Some(((self.key)(event), payload)),
+                Err(e) => {
+                    tracing::warn!(
+                        "Dropping analytics event {}: {}",
+                        event.event.event_type(),
+                        AnalyticsError::Serialization(e)
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        // Queue the whole batch at once, librdkafka batches per partition
+        let deliveries = records.iter().map(|(key, payload)| {
+            let mut record = FutureRecord::to(&self.topic).payload(payload);
+            if let Some(key) = key {
+                record = record.key(key);
+            }
+            self.producer.send(record, Duration::ZERO)
+        });
+
+        for delivery in join_all(deliveries).await {
+            delivery.map_err(|(e, _)| e)?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        let producer = self.producer.clone();
+        let topic = self.topic.clone();
+
+        // Fetching metadata blocks on the network
+        tokio::task::spawn_blocking(move || {
+            producer
+                .client()
+                .fetch_metadata(Some(&topic), METADATA_TIMEOUT)
+        })
+        .await
+        .map_err(|e| AnalyticsError::Io(std::io::Error::other(e)))??;
+        Ok(())
+    }
+}
+
+/// Default key: the event's user, if it has one
+fn user_key(event: &EnrichedEvent) -> Option<String> {
+    event.event.user_id().map(|id| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_user_key() {
+        let user_id = Uuid::new_v4();
+
+        let event = EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id,
+        });
+        assert_eq!(user_key(&event), Some(user_id.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_without_broker() {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", "127.0.0.1:1")
+            .set("message.timeout.ms", "100")
+            .create()
+            .unwrap();
+        let sink = KafkaSink::with_producer(producer, "analytics");
+        let event = EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+
+        let err = sink.send(&[event]).await.unwrap_err();
+        assert!(matches!(err, AnalyticsError::Kafka(_)));
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,89 @@
+//! `multipart/form-data` layout for batches carrying attachments
+//!
+//! The ingestion service must parse these parts:
+//!
+//! * `events` - the serialized batch, exactly the body of a request without
+//!   attachments, with the serializer's content type
+//! * `attachment.<index>` - one part per attachment, `<index>` being the
+//!   position of its event in `events` (starting at 0). The part's file name
+//!   is the attachment name, its content type `application/octet-stream`.
+//!
+//! The `Idempotency-Key` header still hashes the `events` part only, and
+//! `X-Signature` (with a `signing_secret`) signs it.
+
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use reqwest::multipart::{Form, Part};
+
+/// Whether any event in the batch carries an attachment
+pub(super) fn has_attachments(batch: &[EnrichedEvent]) -> bool {
+    batch.iter().any(|event| event.attachment.is_some())
+}
+
+/// Form with the serialized batch `body` followed by the attachments
+pub(super) fn form(batch: &[EnrichedEvent], body: Vec<u8>, content_type: &str) -> Result<Form> {
+    let mut form = Form::new().part("events", Part::bytes(body).mime_str(content_type)?);
+
+    for (index, name, data) in attachments(batch) {
+        let part = Part::bytes(data.to_vec())
+            .file_name(name.to_string())
+            .mime_str("application/octet-stream")?;
+        form = form.part(format!("attachment.{}", index), part);
+    }
+    Ok(form)
+}
+
+/// Position in the batch, name and content of every attachment
+fn attachments(batch: &[EnrichedEvent]) -> impl Iterator<Item = (usize, &str, &[u8])> {
+    batch.iter().enumerate().filter_map(|(index, event)| {
+        event
+            .attachment
+            .as_ref()
+            .map(|(name, data)| (index, name.as_str(), data.as_slice()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::AnalyticsEvent;
+    use uuid::Uuid;
+
+    fn event(attachment: Option<(&str, &[u8])>) -> EnrichedEvent {
+        let mut event = EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        });
+        event.attachment = attachment.map(|(name, data)| (name.to_string(), data.to_vec()));
+        event
+    }
+
+    #[test]
+    fn test_attachments_keep_batch_index() {
+        let batch = [
+            event(None),
+            event(Some(("minidump.dmp", b"MDMP"))),
+            event(None),
+            event(Some(("screen.png", b"PNG"))),
+        ];
+
+        assert!(has_attachments(&batch));
+        assert!(!has_attachments(&batch[..1]));
+        let found: Vec<_> = attachments(&batch).collect();
+        assert_eq!(
+            found,
+            [
+                (1, "minidump.dmp", &b"MDMP"[..]),
+                (3, "screen.png", &b"PNG"[..])
+            ]
+        );
+        assert!(form(&batch, b"[]".to_vec(), "application/json").is_ok());
+    }
+
+    #[test]
+    fn test_attachment_not_serialized() {
+        let event = event(Some(("minidump.dmp", b"MDMP")));
+        let json = serde_json::to_value(&event).unwrap();
+        assert!(json.get("attachment").is_none());
+    }
+}
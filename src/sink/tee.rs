@@ -0,0 +1,111 @@
+use super::EventSink;
+use crate::error::Result;
+use crate::events::EnrichedEvent;
+use async_trait::async_trait;
+use futures_util::future::join;
+use std::sync::Arc;
+
+/// Sink writing every batch to a primary and a mirror sink
+///
+/// Meant for dual-writing while migrating to a new pipeline. Both sinks get
+/// each batch concurrently, and only the primary's result counts: mirror
+/// failures are logged and otherwise ignored, so the mirror can't cause
+/// retries or drops. Health checks and debug round trips go to the
+/// primary only.
+///
+/// Every batch is serialized and sent twice and a send lasts until the
+/// slower sink is done, so drop the mirror once the cutover is validated.
+/// [`AnalyticsClientBuilder::mirror_to`](crate::AnalyticsClientBuilder::mirror_to)
+/// mirrors the built-in HTTP sink.
+pub struct TeeSink {
+    primary: Arc<dyn EventSink>,
+    mirror: Arc<dyn EventSink>,
+}
+
+impl TeeSink {
+    /// Deliver to `primary`, copying every batch to `mirror`
+    pub fn new(primary: impl EventSink, mirror: impl EventSink) -> Self {
+        Self {
+            primary: Arc::new(primary),
+            mirror: Arc::new(mirror),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for TeeSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        let (result, mirrored) = join(self.primary.send(batch), self.mirror.send(batch)).await;
+        if let Err(e) = mirrored {
+            tracing::warn!(
+                "Failed to mirror analytics batch of {} events: {}",
+                batch.len(),
+                e
+            );
+        }
+        result
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.primary.health_check().await
+    }
+
+    async fn debug_roundtrip(&self, event: &EnrichedEvent) -> Result<EnrichedEvent> {
+        self.primary.debug_roundtrip(event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AnalyticsError;
+    use crate::events::AnalyticsEvent;
+    use crate::sink::testing::CollectSink;
+    use uuid::Uuid;
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl EventSink for FailingSink {
+        async fn send(&self, _batch: &[EnrichedEvent]) -> Result<()> {
+            Err(AnalyticsError::Http { status: 503 })
+        }
+    }
+
+    fn batch() -> Vec<EnrichedEvent> {
+        vec![EnrichedEvent::new(AnalyticsEvent::ProjectUpdated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+        })]
+    }
+
+    #[tokio::test]
+    async fn test_both_sinks_receive_batch() {
+        let primary = CollectSink::default();
+        let mirror = CollectSink::default();
+        let sink = TeeSink::new(primary.clone(), mirror.clone());
+
+        let batch = batch();
+        sink.send(&batch).await.unwrap();
+
+        assert_eq!(primary.batches().len(), 1);
+        assert_eq!(mirror.batches().len(), 1);
+        assert_eq!(mirror.events()[0].sequence, batch[0].sequence);
+    }
+
+    #[tokio::test]
+    async fn test_only_primary_result_counts() {
+        let primary = CollectSink::default();
+        let sink = TeeSink::new(primary.clone(), FailingSink);
+        sink.send(&batch()).await.unwrap();
+        assert_eq!(primary.batches().len(), 1);
+
+        let mirror = CollectSink::default();
+        let sink = TeeSink::new(FailingSink, mirror.clone());
+        assert!(matches!(
+            sink.send(&batch()).await,
+            Err(AnalyticsError::Http { status: 503 })
+        ));
+        assert_eq!(mirror.batches().len(), 1);
+    }
+}
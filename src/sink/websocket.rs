@@ -0,0 +1,252 @@
+use super::EventSink;
+use crate::error::{AnalyticsError, Result};
+use crate::events::EnrichedEvent;
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// Initial delay before reconnecting after a disconnect
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Upper bound for the reconnect delay
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// WebSocket sink options
+#[derive(Debug, Clone)]
+pub struct WebSocketOptions {
+    /// Bearer token sent in the `Authorization` header of the handshake
+    pub auth_token: Option<String>,
+
+    /// Maximum number of events buffered while disconnected
+    pub buffer_capacity: usize,
+
+    /// Interval between keep-alive pings
+    pub ping_interval: Duration,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self {
+            auth_token: None,
+            buffer_capacity: 10_000,
+            ping_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Sink streaming events over a persistent WebSocket
+///
+/// Connects to `{base_url}/events/ws` (`http`/`https` base URLs are mapped to
+/// `ws`/`wss`). Wire format: every event is sent as one text frame holding a
+/// single JSON-serialized [`EnrichedEvent`] (the same object that appears in
+/// the HTTP batch array), in tracking order.
+///
+/// A ping frame is sent every `ping_interval` (30s by default); the server is
+/// expected to answer with a pong as per RFC 6455. Pings from the server are
+/// answered automatically, and any other frames it sends are ignored.
+///
+/// On disconnect the sink reconnects with exponential backoff (0.5s doubling
+/// up to 30s). Events tracked meanwhile are buffered up to `buffer_capacity`;
+/// beyond that they are dropped and `send` returns `AnalyticsError::QueueFull`.
+pub struct WebSocketSink {
+    frames: mpsc::Sender<String>,
+}
+
+impl WebSocketSink {
+    /// Start connecting to the ingestion service's WebSocket endpoint
+    ///
+    /// Must be called within a tokio runtime. The connection is established
+    /// in the background; events sent before it is up are buffered.
+    pub fn connect(base_url: &str, options: WebSocketOptions) -> Result<Self> {
+        let url = websocket_url(base_url)?;
+        let (frames, receiver) = mpsc::channel(options.buffer_capacity.max(1));
+
+        tokio::spawn(run(url, options, receiver));
+
+        Ok(Self { frames })
+    }
+}
+
+#[async_trait]
+impl EventSink for WebSocketSink {
+    async fn send(&self, batch: &[EnrichedEvent]) -> Result<()> {
+        let mut dropped = 0;
+
+        for event in batch {
+            let frame = match serde_json::to_string(event) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    tracing::warn!(
+                        "Dropping analytics event {}: {}",
+                        event.event.event_type(),
+                        AnalyticsError::Serialization(e)
+                    );
+                    continue;
+                }
+            };
+            match self.frames.try_send(frame) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => dropped += 1,
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    return Err(AnalyticsError::ChannelClosed)
+                }
+            }
+        }
+
+        if dropped > 0 {
+            tracing::warn!(
+                "Analytics WebSocket buffer full, dropped {} events",
+                dropped
+            );
+            return Err(AnalyticsError::QueueFull);
+        }
+
+        Ok(())
+    }
+}
+
+/// Map an ingestion base URL to its WebSocket endpoint
+fn websocket_url(base_url: &str) -> Result<String> {
+    let base = base_url.trim_end_matches('/');
+
+    let base = if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = base.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else if base.starts_with("ws://") || base.starts_with("wss://") {
+        base.to_string()
+    } else {
+        return Err(AnalyticsError::Config(format!(
+            "Unsupported WebSocket URL: {}",
+            base_url
+        )));
+    };
+
+    Ok(format!("{}/events/ws", base))
+}
+
+/// Connection task: (re)connects and forwards buffered frames
+async fn run(url: String, options: WebSocketOptions, mut frames: mpsc::Receiver<String>) {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut pending = None;
+
+    loop {
+        // Stop once the sink is gone and everything buffered has been sent
+        if frames.is_closed() && frames.is_empty() && pending.is_none() {
+            return;
+        }
+
+        let mut request = match url.as_str().into_client_request() {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::warn!("Invalid analytics WebSocket URL {}: {}", url, e);
+                return;
+            }
+        };
+        if let Some(token) = &options.auth_token {
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+                request.headers_mut().insert("Authorization", value);
+            }
+        }
+
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((stream, _)) => {
+                tracing::debug!("Analytics WebSocket connected to {}", url);
+                backoff = INITIAL_BACKOFF;
+
+                if pump(stream, &mut frames, &mut pending, options.ping_interval).await {
+                    return;
+                }
+                tracing::warn!("Analytics WebSocket disconnected, reconnecting");
+            }
+            Err(e) => {
+                tracing::warn!("Failed to connect analytics WebSocket: {}", e);
+            }
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Forward frames over an open connection
+///
+/// Returns `true` when the sink has been dropped and all frames were sent,
+/// `false` when the connection was lost. A frame that failed to send is kept
+/// in `pending` and retried first after reconnecting.
+async fn pump(
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    frames: &mut mpsc::Receiver<String>,
+    pending: &mut Option<String>,
+    ping_interval: Duration,
+) -> bool {
+    let (mut write, mut read) = stream.split();
+    let mut ping = tokio::time::interval(ping_interval);
+
+    // Skip first tick (happens immediately)
+    ping.tick().await;
+
+    loop {
+        let frame = match pending.take() {
+            Some(frame) => frame,
+            None => tokio::select! {
+                frame = frames.recv() => match frame {
+                    Some(frame) => frame,
+                    None => {
+                        let _ = write.close().await;
+                        return true;
+                    }
+                },
+
+                _ = ping.tick() => {
+                    if write.send(Message::Ping(Vec::new().into())).await.is_err() {
+                        return false;
+                    }
+                    continue;
+                }
+
+                message = read.next() => match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return false,
+                    Some(Ok(_)) => continue,
+                },
+            },
+        };
+
+        if write
+            .send(Message::Text(frame.clone().into()))
+            .await
+            .is_err()
+        {
+            *pending = Some(frame);
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_url() {
+        assert_eq!(
+            websocket_url("http://localhost:8094").unwrap(),
+            "ws://localhost:8094/events/ws"
+        );
+        assert_eq!(
+            websocket_url("https://analytics.example.com/").unwrap(),
+            "wss://analytics.example.com/events/ws"
+        );
+        assert_eq!(
+            websocket_url("ws://collector:9000").unwrap(),
+            "ws://collector:9000/events/ws"
+        );
+        assert!(websocket_url("ftp://nope").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! SQL statement fingerprints for `DatabaseQuery` events
+
+use crate::events::AnalyticsEvent;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Fingerprint of a SQL statement, for `DatabaseQuery::statement_fingerprint`
+///
+/// The first 16 hex digits of the SHA-256 of [`normalize_sql`], so every
+/// execution of the same statement gets the same fingerprint whatever its
+/// literal values, bind parameters, comments, letter case or formatting,
+/// while the fingerprint itself reveals nothing about the SQL. Compute it
+/// once per statement where queries are prepared, and log the normalized
+/// text next to it to map dashboards back to statements.
+///
+/// ```rust
+/// use lib_analytics_core::sql_fingerprint;
+///
+/// assert_eq!(
+///     sql_fingerprint("SELECT * FROM users WHERE email = 'a@example.com'"),
+///     sql_fingerprint("select *\n  from users where email = $1"),
+/// );
+/// ```
+pub fn sql_fingerprint(sql: &str) -> String {
+    let digest = Sha256::digest(normalize_sql(sql).as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// SQL statement with all literal values replaced by `?`
+///
+/// Strings (`'...'`, `$tag$...$tag$`), numbers and bind parameters (`$1`,
+/// `?`, `:name`) become `?`, lists of them such as `IN (1, 2, 3)` and
+/// multi-row `VALUES` collapse to a single `?` or row, comments are
+/// removed, whitespace is collapsed and everything but quoted identifiers
+/// is lowercased.
+pub fn normalize_sql(sql: &str) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '-' if next == Some('-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                push_space(&mut out);
+                continue;
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                push_space(&mut out);
+                continue;
+            }
+            '\'' => {
+                i = skip_quoted(&chars, i, '\'');
+                out.push('?');
+                continue;
+            }
+            '"' | '`' => {
+                let end = skip_quoted(&chars, i, c);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '$' if next.is_some_and(|n| n.is_ascii_digit()) => {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                out.push('?');
+                continue;
+            }
+            '$' => {
+                if let Some(end) = skip_dollar_quoted(&chars, i) {
+                    i = end;
+                    out.push('?');
+                    continue;
+                }
+            }
+            ':' if next.is_some_and(is_ident_start) && !out.ends_with(':') => {
+                i += 1;
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                out.push('?');
+                continue;
+            }
+            c if c.is_ascii_digit() && !out.chars().last().is_some_and(is_ident) => {
+                while i < chars.len() && (is_ident(chars[i]) || chars[i] == '.') {
+                    i += 1;
+                }
+                out.push('?');
+                continue;
+            }
+            c if c.is_whitespace() => {
+                push_space(&mut out);
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        out.extend(c.to_lowercase());
+        i += 1;
+    }
+
+    collapse_lists(&tidy_punctuation(out.trim()))
+}
+
+/// Set `is_slow` on a `DatabaseQuery` the caller didn't flag, from its
+/// duration and `threshold`
+pub(crate) fn flag_slow_query(event: &mut AnalyticsEvent, threshold: Duration) {
+    if let AnalyticsEvent::DatabaseQuery {
+        duration_ms,
+        is_slow: is_slow @ None,
+        ..
+    } = event
+    {
+        let threshold = i64::try_from(threshold.as_millis()).unwrap_or(i64::MAX);
+        *is_slow = Some(duration_ms.as_millis() >= threshold);
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn push_space(out: &mut String) {
+    if !out.is_empty() && !out.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+/// Index after the quoted section starting at `start`, where a doubled
+/// quote or a backslash escapes the quote
+fn skip_quoted(chars: &[char], start: usize, quote: char) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+        } else if chars[i] == quote {
+            if chars.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    chars.len()
+}
+
+/// Index after the PostgreSQL dollar-quoted string (`$$...$$` or
+/// `$tag$...$tag$`) starting at `start`, `None` if there is none
+fn skip_dollar_quoted(chars: &[char], start: usize) -> Option<usize> {
+    let tag_len = chars[start + 1..]
+        .iter()
+        .position(|&c| c == '$')
+        .filter(|&len| {
+            chars[start + 1..start + 1 + len]
+                .iter()
+                .all(|&c| is_ident(c))
+        })?;
+    let tag = &chars[start..start + tag_len + 2];
+    let body = start + tag.len();
+    let end = chars[body..]
+        .windows(tag.len())
+        .position(|window| window == tag)
+        .map_or(chars.len(), |pos| body + pos + tag.len());
+    Some(end)
+}
+
+/// Spaces inside parentheses and before commas removed, one after commas
+fn tidy_punctuation(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' if matches!(chars.peek(), Some(',' | ')')) => {}
+            '(' => {
+                out.push('(');
+                while chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+            }
+            ',' => {
+                out.push_str(", ");
+                while chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `?, ?, ?` to `?` and `(?), (?)` to `(?)`
+fn collapse_lists(sql: &str) -> String {
+    let mut sql = sql.to_string();
+    for (list, single) in [("?, ?", "?"), ("(?), (?)", "(?)")] {
+        while sql.contains(list) {
+            sql = sql.replace(list, single);
+        }
+    }
+    sql
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::DurationMs;
+
+    #[test]
+    fn test_normalize_replaces_literals() {
+        assert_eq!(
+            normalize_sql(
+                "SELECT id, name FROM users\n  WHERE email = 'o''brien@example.com' -- lookup\n  AND age > 42 /* adults */ LIMIT 10"
+            ),
+            "select id, name from users where email = ? and age > ? limit ?"
+        );
+        assert_eq!(
+            normalize_sql(
+                "UPDATE t SET body = $body$it's $1 secret$body$, n = 1.5e3 WHERE id = $1"
+            ),
+            "update t set body = ?, n = ? where id = ?"
+        );
+        assert_eq!(
+            normalize_sql(r#"SELECT "UserName" FROM t2 WHERE x = :name AND y::text = ?"#),
+            r#"select "UserName" from t2 where x = ? and y::text = ?"#
+        );
+    }
+
+    #[test]
+    fn test_normalize_collapses_lists() {
+        assert_eq!(
+            normalize_sql("SELECT * FROM t WHERE id IN ( 1, 2,3 )"),
+            "select * from t where id in (?)"
+        );
+        assert_eq!(
+            normalize_sql("INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y'), (3, 'z')"),
+            "insert into t (a, b) values (?)"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_values_and_formatting() {
+        let fingerprint =
+            sql_fingerprint("SELECT * FROM users WHERE id IN (1, 2) AND name = 'ann'");
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(
+            fingerprint,
+            sql_fingerprint("select *  from users\nwhere id in ($1, $2, $3) and name = $4")
+        );
+        assert_ne!(
+            fingerprint,
+            sql_fingerprint("SELECT * FROM projects WHERE id = 1")
+        );
+    }
+
+    #[test]
+    fn test_flag_slow_query() {
+        let query = |duration_ms, is_slow| AnalyticsEvent::DatabaseQuery {
+            service: "api".to_string(),
+            query_type: "select".to_string(),
+            duration_ms: DurationMs(duration_ms),
+            rows_affected: None,
+            statement_fingerprint: None,
+            is_slow,
+        };
+        let threshold = Duration::from_millis(100);
+
+        for (duration_ms, is_slow, expected) in [
+            (99, None, Some(false)),
+            (100, None, Some(true)),
+            // Set by the caller
+            (5, Some(true), Some(true)),
+        ] {
+            let mut event = query(duration_ms, is_slow);
+            flag_slow_query(&mut event, threshold);
+            assert!(
+                matches!(event, AnalyticsEvent::DatabaseQuery { is_slow, .. } if is_slow == expected),
+                "{:?}",
+                event
+            );
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::clock::ClockSkew;
+use crate::config::ClientConfig;
+use crate::events::Enrichment;
+use crate::metrics::{BatchOutcome, PipelineCounters};
+use crate::middleware::{self, EnrichMiddleware};
+use crate::throttle::LogThrottle;
+use arc_swap::ArcSwapOption;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Semaphore};
+
+/// Outcomes kept for subscribers that fall behind
+pub(crate) const OUTCOME_BUFFER: usize = 256;
+
+/// State shared between client handles, the background worker and sinks
+#[derive(Debug)]
+pub(crate) struct SharedState {
+    pub(crate) clock_skew: ClockSkew,
+    pub(crate) enrichment: Enrichment,
+    /// Built-in and configured enrichment steps, in the order they run
+    pub(crate) middleware: Vec<Arc<dyn EnrichMiddleware>>,
+    pub(crate) counters: Arc<PipelineCounters>,
+    /// Bounds concurrent sink requests (`max_concurrent_sends`)
+    pub(crate) send_permits: Semaphore,
+    /// Set while sending is paused (`AnalyticsClient::pause`)
+    paused: AtomicBool,
+    /// Per-batch delivery results (`AnalyticsClient::subscribe_outcomes`)
+    pub(crate) outcomes: broadcast::Sender<BatchOutcome>,
+    /// Number of the next request sent to the ingestion service
+    batch_seq: AtomicU64,
+    /// Base URL of the ingestion service, `None` for clients with a custom
+    /// sink. Read by the HTTP sink on every request (`AnalyticsClient::set_url`).
+    pub(crate) analytics_url: ArcSwapOption<String>,
+    /// Drop and failure logging (`log_throttle` and log levels)
+    pub(crate) log: LogThrottle,
+}
+
+impl SharedState {
+    pub(crate) fn new(config: &ClientConfig) -> Self {
+        let counters = Arc::new(PipelineCounters::default());
+        Self {
+            clock_skew: ClockSkew::new(),
+            enrichment: Enrichment::new(config.hostname.clone(), config.environment.clone()),
+            middleware: middleware::chain(config, &counters),
+            counters,
+            send_permits: Semaphore::new(config.max_concurrent_sends.max(1)),
+            paused: AtomicBool::new(false),
+            outcomes: broadcast::channel(OUTCOME_BUFFER).0,
+            batch_seq: AtomicU64::new(0),
+            analytics_url: ArcSwapOption::empty(),
+            log: LogThrottle::new(config),
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Take the number for a batch about to be sent, counting from 0
+    pub(crate) fn next_batch_seq(&self) -> u64 {
+        self.batch_seq.fetch_add(1, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,196 @@
+use crate::config::ClientConfig;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::Level;
+
+/// Whether a log message is about lost events, failed deliveries or
+/// something else worth a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogKind {
+    /// Events dropped before or instead of being sent
+    Drop,
+    /// Batches the sink failed to deliver
+    Failure,
+    /// Other problems, always logged at `warn` (e.g. clock skew)
+    Warning,
+}
+
+/// Logs drop and failure messages at their configured level, and warnings
+/// at `warn`, coalescing
+/// repeats of the same message within `log_throttle`
+///
+/// The first occurrence of a message is logged right away; identical ones
+/// within the window are only counted. The next occurrence after the window
+/// is logged again with the number suppressed in between, and counts of
+/// messages that stopped recurring are flushed as a summary once their
+/// window is over, so suppressed counts are reported at least once per
+/// window while the client keeps logging.
+#[derive(Debug)]
+pub(crate) struct LogThrottle {
+    window: Option<Duration>,
+    drop_level: Level,
+    failure_level: Level,
+    seen: Mutex<HashMap<String, Suppressed>>,
+}
+
+#[derive(Debug)]
+struct Suppressed {
+    since: Instant,
+    count: u64,
+}
+
+impl LogThrottle {
+    pub(crate) fn new(config: &ClientConfig) -> Self {
+        Self {
+            window: config.log_throttle,
+            drop_level: config.drop_log_level,
+            failure_level: config.failure_log_level,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Log `message` unless an identical one was logged within the window
+    pub(crate) fn log(&self, kind: LogKind, message: String) {
+        let level = match kind {
+            LogKind::Drop => self.drop_level,
+            LogKind::Failure => self.failure_level,
+            LogKind::Warning => Level::WARN,
+        };
+        if !level_enabled(level) {
+            return;
+        }
+        for line in self.check(message, Instant::now()) {
+            emit(level, &line);
+        }
+    }
+
+    /// Lines to log for `message` arriving at `now`
+    fn check(&self, message: String, now: Instant) -> Vec<String> {
+        let Some(window) = self.window else {
+            return vec![message];
+        };
+        // Only held for map updates without panicking code in between
+        let mut seen = self.seen.lock().unwrap();
+
+        // Summaries for messages that stopped recurring
+        let mut lines = Vec::new();
+        seen.retain(|seen_message, suppressed| {
+            let expired = now.duration_since(suppressed.since) >= window;
+            if expired && *seen_message != message && suppressed.count > 0 {
+                lines.push(format!(
+                    "Suppressed {} similar messages: {}",
+                    suppressed.count, seen_message
+                ));
+            }
+            !expired || *seen_message == message
+        });
+
+        match seen.get_mut(&message) {
+            Some(suppressed) if now.duration_since(suppressed.since) < window => {
+                suppressed.count += 1;
+            }
+            Some(suppressed) => {
+                let count = std::mem::take(&mut suppressed.count);
+                suppressed.since = now;
+                lines.push(if count > 0 {
+                    format!("{} (suppressed {} similar messages)", message, count)
+                } else {
+                    message
+                });
+            }
+            None => {
+                seen.insert(
+                    message.clone(),
+                    Suppressed {
+                        since: now,
+                        count: 0,
+                    },
+                );
+                lines.push(message);
+            }
+        }
+        lines
+    }
+}
+
+/// `tracing` macros need the level at compile time
+fn level_enabled(level: Level) -> bool {
+    match level {
+        Level::ERROR => tracing::enabled!(Level::ERROR),
+        Level::WARN => tracing::enabled!(Level::WARN),
+        Level::INFO => tracing::enabled!(Level::INFO),
+        Level::DEBUG => tracing::enabled!(Level::DEBUG),
+        Level::TRACE => tracing::enabled!(Level::TRACE),
+    }
+}
+
+fn emit(level: Level, message: &str) {
+    match level {
+        Level::ERROR => tracing::error!("{}", message),
+        Level::WARN => tracing::warn!("{}", message),
+        Level::INFO => tracing::info!("{}", message),
+        Level::DEBUG => tracing::debug!("{}", message),
+        Level::TRACE => tracing::trace!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle(window: Option<Duration>) -> LogThrottle {
+        LogThrottle::new(&ClientConfig {
+            log_throttle: window,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_unthrottled_logs_everything() {
+        let throttle = throttle(None);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(throttle.check("failed".to_string(), now), ["failed"]);
+        }
+    }
+
+    #[test]
+    fn test_repeats_are_coalesced() {
+        let throttle = throttle(Some(Duration::from_secs(60)));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(throttle.check("HTTP 503".to_string(), at(0)), ["HTTP 503"]);
+        for secs in 1..=4 {
+            assert!(throttle.check("HTTP 503".to_string(), at(secs)).is_empty());
+        }
+        // Different messages are throttled separately
+        assert_eq!(throttle.check("HTTP 500".to_string(), at(5)), ["HTTP 500"]);
+
+        assert_eq!(
+            throttle.check("HTTP 503".to_string(), at(60)),
+            ["HTTP 503 (suppressed 4 similar messages)"]
+        );
+        // The counter was reset with the new window
+        assert_eq!(
+            throttle.check("HTTP 503".to_string(), at(120)),
+            ["HTTP 503"]
+        );
+    }
+
+    #[test]
+    fn test_expired_counts_are_summarized() {
+        let throttle = throttle(Some(Duration::from_secs(60)));
+        let start = Instant::now();
+
+        throttle.check("HTTP 503".to_string(), start);
+        throttle.check("HTTP 503".to_string(), start + Duration::from_secs(1));
+
+        assert_eq!(
+            throttle.check("timeout".to_string(), start + Duration::from_secs(90)),
+            ["Suppressed 1 similar messages: HTTP 503", "timeout"]
+        );
+        assert_eq!(throttle.seen.lock().unwrap().len(), 1);
+    }
+}
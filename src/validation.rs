@@ -0,0 +1,310 @@
+use crate::error::ValidationError;
+use crate::events::AnalyticsEvent;
+use chrono::{DateTime, Duration, Utc};
+
+type Result = std::result::Result<(), ValidationError>;
+
+impl AnalyticsEvent {
+    /// Check the event for obviously broken values
+    ///
+    /// Catches empty required strings, negative durations and out-of-range
+    /// numbers. Returns the first problem found. Clients only enforce this
+    /// when `reject_invalid` is enabled.
+    pub fn validate(&self) -> Result {
+        match self {
+            AnalyticsEvent::AuthLoginAttempt { email, .. }
+            | AnalyticsEvent::AuthLoginAttemptsCompacted { email, .. } => non_empty("email", email),
+            AnalyticsEvent::AuthTokenExpiring {
+                expires_in_seconds, ..
+            } => non_negative("expires_in_seconds", *expires_in_seconds),
+            AnalyticsEvent::SessionEnded {
+                duration_seconds, ..
+            } => non_negative("duration_seconds", *duration_seconds),
+            AnalyticsEvent::TaskCreated { command, .. } => non_empty("command", command),
+            AnalyticsEvent::TaskProgress {
+                percent: Some(percent),
+                ..
+            } => in_range("percent", *percent, 0, 100),
+            AnalyticsEvent::TaskCompleted { duration_ms, .. } => {
+                non_negative("duration_ms", duration_ms.as_millis())
+            }
+            AnalyticsEvent::TaskFailed { duration_ms, .. }
+            | AnalyticsEvent::TaskCancelled { duration_ms, .. } => {
+                non_negative_opt("duration_ms", duration_ms.map(|d| d.as_millis()))
+            }
+            AnalyticsEvent::IntegrationUsed { action, .. } => non_empty("action", action),
+            AnalyticsEvent::OAuthFlowStarted { state, .. } => non_empty("state", state),
+            AnalyticsEvent::WebhookReceived {
+                event_type,
+                delivery_id,
+                ..
+            } => {
+                non_empty("event_type", event_type)?;
+                non_empty("delivery_id", delivery_id)
+            }
+            AnalyticsEvent::WebhookProcessed {
+                event_type,
+                delivery_id,
+                duration_ms,
+                ..
+            } => {
+                non_empty("event_type", event_type)?;
+                non_empty("delivery_id", delivery_id)?;
+                non_negative("duration_ms", duration_ms.as_millis())
+            }
+            AnalyticsEvent::WebhookRetryScheduled { delivery_id, .. } => {
+                non_empty("delivery_id", delivery_id)
+            }
+            AnalyticsEvent::CocoonDisconnected {
+                duration_seconds, ..
+            } => non_negative("duration_seconds", *duration_seconds),
+            AnalyticsEvent::CocoonHeartbeat {
+                uptime_seconds,
+                cpu_percent,
+                ..
+            } => {
+                non_negative("uptime_seconds", *uptime_seconds)?;
+                cpu_percent.map_or(Ok(()), |cpu| in_range("cpu_percent", cpu, 0.0, 100.0))
+            }
+            AnalyticsEvent::ProjectCreated { name, .. } => non_empty("name", name),
+            AnalyticsEvent::ApiRequest {
+                service,
+                endpoint,
+                method,
+                status_code,
+                duration_ms,
+                ..
+            } => {
+                non_empty("service", service)?;
+                non_empty("endpoint", endpoint)?;
+                non_empty("method", method)?;
+                in_range("status_code", *status_code, 100, 599)?;
+                non_negative("duration_ms", duration_ms.as_millis())
+            }
+            AnalyticsEvent::ProxyRequest {
+                request_id,
+                endpoint,
+                latency_ms,
+                ttft_ms,
+                ..
+            } => {
+                non_empty("request_id", request_id)?;
+                non_empty("endpoint", endpoint)?;
+                non_negative("latency_ms", i64::from(*latency_ms))?;
+                non_negative_opt("ttft_ms", ttft_ms.map(i64::from))
+            }
+            AnalyticsEvent::DatabaseQuery {
+                service,
+                query_type,
+                duration_ms,
+                ..
+            } => {
+                non_empty("service", service)?;
+                non_empty("query_type", query_type)?;
+                non_negative("duration_ms", duration_ms.as_millis())
+            }
+            AnalyticsEvent::ApplicationError {
+                service,
+                error_type,
+                ..
+            } => {
+                non_empty("service", service)?;
+                non_empty("error_type", error_type)
+            }
+            AnalyticsEvent::BalanceDeposit { amount, .. }
+            | AnalyticsEvent::BalanceDebit { amount, .. } => positive("amount", *amount),
+            AnalyticsEvent::BalanceInsufficient {
+                requested_amount, ..
+            } => positive("requested_amount", *requested_amount),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How far past `now` an explicit occurrence time may lie, allowing for
+/// clock differences between the host and where the event was recorded
+const MAX_FUTURE_OCCURRENCE: Duration = Duration::minutes(5);
+
+/// Check an explicit occurrence time isn't implausibly far in the future
+pub(crate) fn validate_occurred_at(occurred_at: DateTime<Utc>, now: DateTime<Utc>) -> Result {
+    if occurred_at > now + MAX_FUTURE_OCCURRENCE {
+        return Err(ValidationError::OutOfRange { field: "timestamp" });
+    }
+    Ok(())
+}
+
+fn non_empty(field: &'static str, value: &str) -> Result {
+    if value.trim().is_empty() {
+        return Err(ValidationError::EmptyField { field });
+    }
+    Ok(())
+}
+
+fn non_negative(field: &'static str, value: i64) -> Result {
+    if value < 0 {
+        return Err(ValidationError::NegativeDuration { field, value });
+    }
+    Ok(())
+}
+
+fn non_negative_opt(field: &'static str, value: Option<i64>) -> Result {
+    value.map_or(Ok(()), |value| non_negative(field, value))
+}
+
+fn positive(field: &'static str, value: i64) -> Result {
+    if value <= 0 {
+        return Err(ValidationError::OutOfRange { field });
+    }
+    Ok(())
+}
+
+fn in_range<T: PartialOrd>(field: &'static str, value: T, min: T, max: T) -> Result {
+    // Written as `contains` so that NaN is rejected too
+    if !(min..=max).contains(&value) {
+        return Err(ValidationError::OutOfRange { field });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::DurationMs;
+    use uuid::Uuid;
+
+    fn api_request(status_code: u16, duration_ms: i64) -> AnalyticsEvent {
+        AnalyticsEvent::ApiRequest {
+            service: "platform".to_string(),
+            endpoint: "/tasks".to_string(),
+            method: "GET".to_string(),
+            status_code,
+            duration_ms: DurationMs(duration_ms),
+            user_id: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_event() {
+        assert!(api_request(200, 12).validate().is_ok());
+    }
+
+    #[test]
+    fn test_negative_duration() {
+        assert_eq!(
+            api_request(200, -5).validate(),
+            Err(ValidationError::NegativeDuration {
+                field: "duration_ms",
+                value: -5
+            })
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_status() {
+        assert_eq!(
+            api_request(42, 12).validate(),
+            Err(ValidationError::OutOfRange {
+                field: "status_code"
+            })
+        );
+    }
+
+    #[test]
+    fn test_auth_token_expiring_in_the_past() {
+        let expiring = |expires_in_seconds| AnalyticsEvent::AuthTokenExpiring {
+            user_id: Uuid::new_v4(),
+            provider: Some("github".to_string()),
+            expires_in_seconds,
+        };
+
+        assert!(expiring(0).validate().is_ok());
+        assert_eq!(
+            expiring(-5).validate(),
+            Err(ValidationError::NegativeDuration {
+                field: "expires_in_seconds",
+                value: -5
+            })
+        );
+    }
+
+    #[test]
+    fn test_session_ended_negative_duration() {
+        let ended = |duration_seconds| AnalyticsEvent::SessionEnded {
+            session_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            duration_seconds,
+            reason: None,
+        };
+
+        assert!(ended(0).validate().is_ok());
+        assert_eq!(
+            ended(-1).validate(),
+            Err(ValidationError::NegativeDuration {
+                field: "duration_seconds",
+                value: -1
+            })
+        );
+    }
+
+    #[test]
+    fn test_occurred_at_in_the_future() {
+        let now = Utc::now();
+
+        assert!(validate_occurred_at(now - Duration::days(365), now).is_ok());
+        assert!(validate_occurred_at(now + Duration::minutes(1), now).is_ok());
+        assert_eq!(
+            validate_occurred_at(now + Duration::hours(1), now),
+            Err(ValidationError::OutOfRange { field: "timestamp" })
+        );
+    }
+
+    #[test]
+    fn test_task_progress_percent() {
+        let progress = |percent| AnalyticsEvent::TaskProgress {
+            task_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            percent: Some(percent),
+            stage: None,
+            message: None,
+        };
+
+        assert!(progress(100).validate().is_ok());
+        assert_eq!(
+            progress(101).validate(),
+            Err(ValidationError::OutOfRange { field: "percent" })
+        );
+    }
+
+    #[test]
+    fn test_cocoon_heartbeat_cpu_percent() {
+        let heartbeat = |cpu_percent| AnalyticsEvent::CocoonHeartbeat {
+            cocoon_id: Uuid::new_v4(),
+            user_id: None,
+            uptime_seconds: 3600,
+            cpu_percent: Some(cpu_percent),
+            mem_bytes: Some(512 * 1024 * 1024),
+        };
+
+        assert!(heartbeat(42.5).validate().is_ok());
+        for invalid in [-1.0, 100.5, f32::NAN] {
+            assert_eq!(
+                heartbeat(invalid).validate(),
+                Err(ValidationError::OutOfRange {
+                    field: "cpu_percent"
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_field() {
+        let event = AnalyticsEvent::ProjectCreated {
+            project_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "  ".to_string(),
+        };
+        let err = event.validate().unwrap_err();
+        assert_eq!(err, ValidationError::EmptyField { field: "name" });
+        assert_eq!(err.to_string(), "Field 'name' must not be empty");
+    }
+}
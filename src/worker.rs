@@ -0,0 +1,415 @@
+use crate::aggregate::Aggregator;
+use crate::batch::{BatchPolicy, BatchState};
+use crate::churn::ChurnTracker;
+use crate::compact::LoginCompactor;
+use crate::config::ClientConfig;
+use crate::dry_run;
+use crate::error::{AnalyticsError, Result};
+use crate::events::{AnalyticsEvent, EnrichedEvent};
+use crate::metrics::BatchOutcome;
+use crate::queue::{EventReceiver, Message};
+use crate::sink::EventSink;
+use crate::state::SharedState;
+use crate::throttle::LogKind;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::time::Instant;
+
+/// Background task that batches queued events and hands them to the sink
+pub(crate) struct Worker {
+    receiver: EventReceiver,
+    sink: Arc<dyn EventSink>,
+    config: Arc<ClientConfig>,
+    shared: Arc<SharedState>,
+    aggregator: Aggregator,
+    compactor: LoginCompactor,
+    churn: ChurnTracker,
+    policy: Arc<dyn BatchPolicy>,
+    /// When the first event entered the current batch
+    batch_started: Option<Instant>,
+    /// Serialized size of the events added to the current batch
+    batch_bytes: usize,
+}
+
+impl Worker {
+    pub(crate) fn new(
+        receiver: EventReceiver,
+        sink: Arc<dyn EventSink>,
+        config: Arc<ClientConfig>,
+        shared: Arc<SharedState>,
+    ) -> Self {
+        Self {
+            receiver,
+            sink,
+            aggregator: Aggregator::new(config.aggregations.clone()),
+            compactor: LoginCompactor::new(config.login_compaction_window),
+            churn: ChurnTracker::new(config.churn_window, config.churn_suppress_raw),
+            policy: config.batch_policy(),
+            batch_started: None,
+            batch_bytes: 0,
+            config,
+            shared,
+        }
+    }
+
+    /// Batch and send events until shut down
+    pub(crate) async fn run(mut self) {
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+
+        // Only armed while something waits to be sent, so idle clients
+        // (e.g. battery-powered Cocoons) don't wake up every flush interval
+        let mut flush_deadline: Option<Instant> = None;
+
+        let mut stats_interval = self
+            .config
+            .stats_interval
+            .map(|period| tokio::time::interval_at(Instant::now() + period, period));
+
+        loop {
+            tokio::select! {
+                // Receive event or control message
+                message = self.receiver.recv() => match message {
+                    Some(Message::Event(event)) => {
+                        self.add_event(event, &mut batch).await;
+                        self.shared.counters.record_dequeued(batch.len());
+                    }
+                    // Paused: keep everything until resumed
+                    Some(Message::Flush(reply)) if self.shared.is_paused() => {
+                        let _ = reply.send(Err(AnalyticsError::Paused));
+                    }
+                    Some(Message::Flush(reply)) => {
+                        self.drain_held_back(&mut batch);
+                        let result = self.send_batch(&mut batch).await;
+                        self.reset_batch_state();
+                        self.shared.counters.set_buffered(batch.len());
+                        let _ = reply.send(result);
+                    }
+                    Some(Message::Shutdown(reply)) => {
+                        self.drain_held_back(&mut batch);
+                        let _ = self.send_batch(&mut batch).await;
+                        let _ = reply.send(());
+                        return;
+                    }
+                    // All clients dropped: send what is left and stop
+                    None => {
+                        self.drain_held_back(&mut batch);
+                        let _ = self.send_batch(&mut batch).await;
+                        return;
+                    }
+                },
+
+                // Periodic self-report (only when enabled)
+                _ = tick(&mut stats_interval) => {
+                    self.report_stats(&mut batch);
+                    self.shared.counters.set_buffered(batch.len());
+                }
+
+                // Flush interval since the first pending event
+                _ = sleep_until(flush_deadline) => {
+                    flush_deadline = None;
+
+                    // Close the aggregation window
+                    batch.extend(self.aggregator.drain(&self.shared.enrichment));
+                    let now = std::time::Instant::now();
+                    batch.extend(self.compactor.drain_expired(now));
+                    batch.extend(self.churn.drain_expired(now, &self.shared.enrichment));
+                    if !batch.is_empty() && !self.shared.is_paused() {
+                        let state = self.batch_state(&batch);
+                        let _ = self.send_batch(&mut batch).await;
+                        self.policy.on_flush(&state);
+                        self.reset_batch_state();
+                    }
+                    self.shared.counters.set_buffered(batch.len());
+                }
+            }
+
+            if !self.has_pending(&batch) {
+                flush_deadline = None;
+            } else if flush_deadline.is_none() {
+                flush_deadline = Some(Instant::now() + self.policy.max_delay());
+            }
+        }
+    }
+
+    /// Whether any events wait to be sent, in the batch or held back
+    fn has_pending(&self, batch: &[EnrichedEvent]) -> bool {
+        !batch.is_empty()
+            || !self.aggregator.is_empty()
+            || !self.compactor.is_empty()
+            || !self.churn.is_empty()
+    }
+
+    /// Batch an event unless it is held back, sending once the batch is full
+    async fn add_event(&mut self, event: EnrichedEvent, batch: &mut Vec<EnrichedEvent>) {
+        #[cfg(feature = "blob-upload")]
+        let event = self.offload_context(event).await;
+
+        if self.churn.record(&event) {
+            return;
+        }
+        if self.aggregator.record(&event) {
+            return;
+        }
+        let Some(event) = self.compactor.record(event) else {
+            return;
+        };
+
+        if self.shared.is_paused() {
+            // The backlog of a paused client is bounded like the queue
+            if self
+                .config
+                .queue_capacity
+                .is_some_and(|capacity| batch.len() >= capacity)
+            {
+                self.shared.counters.record_dropped();
+                return;
+            }
+            batch.push(event);
+            return;
+        }
+
+        if self.batch_started.is_none() {
+            self.batch_started = Some(Instant::now());
+        }
+        self.batch_bytes += event.estimated_size();
+        batch.push(event);
+
+        let state = self.batch_state(batch);
+        if self.policy.should_flush(&state) {
+            let _ = self.send_batch(batch).await;
+            self.policy.on_flush(&state);
+            self.reset_batch_state();
+        }
+    }
+
+    /// Current batch as seen by the batch policy
+    fn batch_state(&self, batch: &[EnrichedEvent]) -> BatchState {
+        BatchState {
+            events: batch.len(),
+            bytes: self.batch_bytes,
+            age: self
+                .batch_started
+                .map_or(std::time::Duration::ZERO, |started| started.elapsed()),
+        }
+    }
+
+    /// Start tracking a new batch after the last one was sent
+    fn reset_batch_state(&mut self) {
+        self.batch_started = None;
+        self.batch_bytes = 0;
+    }
+
+    /// Upload large error context if a blob uploader is configured
+    #[cfg(feature = "blob-upload")]
+    async fn offload_context(&self, mut event: EnrichedEvent) -> EnrichedEvent {
+        if let Some(uploader) = &self.config.blob_uploader {
+            crate::blob::offload_context(
+                uploader.as_ref(),
+                &mut event,
+                self.config.blob_threshold,
+                self.config.max_field_len,
+            )
+            .await;
+        }
+        event
+    }
+
+    /// Add a `PipelineStats` event for the counts since the last report
+    fn report_stats(&self, batch: &mut Vec<EnrichedEvent>) {
+        let snapshot = self.shared.counters.take();
+        if snapshot.is_empty() {
+            return;
+        }
+
+        let interval_secs = self.config.stats_interval.unwrap_or_default().as_secs();
+        batch.push(
+            self.shared
+                .enrichment
+                .enrich(AnalyticsEvent::PipelineStats {
+                    enqueued: snapshot.enqueued,
+                    dropped: snapshot.dropped,
+                    sent: snapshot.sent,
+                    failed: snapshot.failed,
+                    interval_secs,
+                }),
+        );
+    }
+
+    /// Move aggregated and compacted events into the batch
+    fn drain_held_back(&mut self, batch: &mut Vec<EnrichedEvent>) {
+        batch.extend(self.aggregator.drain(&self.shared.enrichment));
+        batch.extend(self.compactor.drain());
+        batch.extend(self.churn.drain(&self.shared.enrichment));
+    }
+
+    /// Send a batch of events, split into `batch_size` chunks if it grew
+    /// larger while paused
+    ///
+    /// Returns the first error; later chunks are still attempted.
+    async fn send_batch(&self, batch: &mut Vec<EnrichedEvent>) -> Result<()> {
+        // Never split off empty chunks, which would loop forever
+        let chunk_size = self.config.batch_size.max(1);
+        if batch.len() <= chunk_size {
+            return self.send_chunk(batch).await;
+        }
+
+        let mut result = Ok(());
+        let mut rest = std::mem::take(batch);
+        while !rest.is_empty() {
+            let tail = rest.split_off(chunk_size.min(rest.len()));
+            let sent = self.send_chunk(&mut rest).await;
+            if result.is_ok() {
+                result = sent;
+            }
+            rest = tail;
+        }
+        result
+    }
+
+    /// Send one batch of events to the sink
+    async fn send_chunk(&self, batch: &mut Vec<EnrichedEvent>) -> Result<()> {
+        self.discard_stale(batch);
+        let count = batch.len();
+        if count == 0 {
+            return Ok(());
+        }
+
+        let result = if self.config.dry_run {
+            dry_run::log(&self.config, batch)
+        } else {
+            deliver(self.sink.as_ref(), &self.shared, batch).await
+        };
+
+        let counted = batch.iter().filter(|e| is_counted(e)).count();
+        match &result {
+            Ok(()) => {
+                // The mix by type as a field, so log aggregators can chart it
+                tracing::debug!(
+                    count,
+                    types = ?count_by_type(batch),
+                    "Sent {} analytics events",
+                    count
+                );
+                self.shared.counters.record_sent(counted);
+                if !self.config.dry_run {
+                    self.shared.counters.record_success(chrono::Utc::now());
+                }
+            }
+            // The durable buffer counted what it kept and what it dropped
+            Err(AnalyticsError::DurableBufferFull) => {}
+            Err(e) => {
+                self.shared.log.log(
+                    LogKind::Failure,
+                    format!("Failed to send analytics events: {}", e),
+                );
+                self.shared.counters.record_failed(counted);
+            }
+        }
+
+        batch.clear();
+        result
+    }
+
+    /// Drop events older than `max_event_age`
+    fn discard_stale(&self, batch: &mut Vec<EnrichedEvent>) {
+        let Some(max_age) = self.config.max_event_age else {
+            return;
+        };
+        let Ok(max_age) = chrono::Duration::from_std(max_age) else {
+            return;
+        };
+
+        // Timestamps were corrected on tracking, so compare with corrected time
+        let now =
+            chrono::Utc::now() + clock_correction(&self.config, &self.shared).unwrap_or_default();
+        let cutoff = now - max_age;
+        let before = batch.len();
+        batch.retain(|event| event.timestamp >= cutoff);
+
+        let stale = before - batch.len();
+        if stale > 0 {
+            self.shared.log.log(
+                LogKind::Drop,
+                format!("Discarded {} stale analytics events", stale),
+            );
+            self.shared.counters.record_stale(stale);
+        }
+    }
+}
+
+/// Hand a batch to the sink once a `max_concurrent_sends` slot is free,
+/// recording its latency and publishing its outcome
+pub(crate) async fn deliver(
+    sink: &dyn EventSink,
+    shared: &SharedState,
+    batch: &[EnrichedEvent],
+) -> Result<()> {
+    // The semaphore is never closed, so acquiring only waits for a free slot
+    let _permit = shared.send_permits.acquire().await;
+    let _in_flight = shared.counters.start_send();
+    let started = Instant::now();
+    let result = sink.send(batch).await;
+    let latency = started.elapsed();
+    #[cfg(feature = "latency-histogram")]
+    shared.counters.record_latency(latency, result.is_ok());
+
+    // Fails only without subscribers
+    let _ = shared.outcomes.send(BatchOutcome {
+        count: batch.len(),
+        success: result.is_ok(),
+        status: match &result {
+            Err(AnalyticsError::Http { status }) => Some(*status),
+            _ => None,
+        },
+        latency,
+    });
+    result
+}
+
+/// Offset added to timestamps taken from this host's clock
+///
+/// Only with `correct_clock_skew` and once the measured offset exceeds
+/// `clock_skew_threshold`.
+pub(crate) fn clock_correction(
+    config: &ClientConfig,
+    shared: &SharedState,
+) -> Option<chrono::Duration> {
+    if !config.correct_clock_skew {
+        return None;
+    }
+    shared.clock_skew.correction(config.clock_skew_threshold)
+}
+
+/// Whether an event counts toward the pipeline metrics
+///
+/// Stats events don't, so a failing sink can't feed its own reports.
+pub(crate) fn is_counted(event: &EnrichedEvent) -> bool {
+    !matches!(event.event, AnalyticsEvent::PipelineStats { .. })
+}
+
+/// Wait until an optional deadline, forever if it is `None`
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait for the next tick of an optional interval, forever if it is `None`
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Number of events of each type in a batch
+fn count_by_type(batch: &[EnrichedEvent]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for event in batch {
+        *counts.entry(event.event.event_type()).or_insert(0) += 1;
+    }
+    counts
+}